@@ -0,0 +1,9 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+// the first byte picks the source, the rest is the response body
+fuzz_target!(|data: &[u8]| {
+    if let Some((selector, body)) = data.split_first() {
+        let _ = sub::fuzz::parse(*selector, body);
+    }
+});
@@ -0,0 +1,19 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+// the first line holds the roots separated by spaces, every other line is a result. An odd
+// first byte filters with --subs-only
+fuzz_target!(|data: &[u8]| {
+    let subs_only = data.first().map_or(false, |b| b % 2 == 1);
+    let text = String::from_utf8_lossy(data);
+    let mut lines = text.lines();
+    let roots = match lines.next() {
+        Some(roots) => roots.split_whitespace().map(|r| r.to_string()).collect(),
+        None => return,
+    };
+    let results = lines.map(|l| l.to_string()).collect();
+
+    for kept in sub::fuzz::clean(roots, results, subs_only) {
+        assert!(!kept.contains('*'), "{} kept a wildcard", kept);
+    }
+});
@@ -0,0 +1,266 @@
+use crate::sources::Source;
+use addr::DomainName;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// How often labels and their parts show up in a set of hostnames, useful for building
+/// permutation word lists tailored to a target.
+#[derive(Debug, Default, Serialize)]
+pub struct Analysis {
+    /// How many hostnames were analysed
+    pub hosts: usize,
+    /// The label directly below the registrable domain, e.g. `api` in `dev.api.hackerone.com`
+    pub prefixes: Vec<(String, usize)>,
+    /// Every label below the registrable domain
+    pub labels: Vec<(String, usize)>,
+    /// The words labels are made of, split on `-`, `_` and digits
+    pub tokens: Vec<(String, usize)>,
+    /// Labels containing numbers with each run of digits replaced by `{n}`, e.g. `web{n}`
+    pub numeric: Vec<(String, usize)>,
+}
+
+impl Analysis {
+    /// Keeps only the `n` most common entries of each list
+    pub fn top(mut self, n: usize) -> Self {
+        self.prefixes.truncate(n);
+        self.labels.truncate(n);
+        self.tokens.truncate(n);
+        self.numeric.truncate(n);
+        self
+    }
+}
+
+/// Tokenizes the labels of `hosts` below their registrable domain and counts them. Each list
+/// is ordered from most to least common, ties are ordered alphabetically.
+pub fn analyze<I, S>(hosts: I) -> Analysis
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut count = 0;
+    let mut prefixes = HashMap::new();
+    let mut labels = HashMap::new();
+    let mut tokens = HashMap::new();
+    let mut numeric = HashMap::new();
+
+    for host in hosts {
+        let host = host.as_ref().trim().trim_end_matches('.').to_lowercase();
+        let root = match host.parse::<DomainName>() {
+            Ok(d) => d.root().to_string(),
+            Err(_) => continue,
+        };
+        count += 1;
+
+        let sub = match host.strip_suffix(&root) {
+            Some(s) => s.trim_end_matches('.'),
+            None => continue,
+        };
+        if sub.is_empty() {
+            continue;
+        }
+
+        for (i, label) in sub.rsplit('.').enumerate() {
+            if i == 0 {
+                *prefixes.entry(label.to_string()).or_insert(0) += 1;
+            }
+            *labels.entry(label.to_string()).or_insert(0) += 1;
+
+            for token in label.split(|c: char| c == '-' || c == '_' || c.is_ascii_digit()) {
+                if !token.is_empty() {
+                    *tokens.entry(token.to_string()).or_insert(0) += 1;
+                }
+            }
+
+            if label.chars().any(|c| c.is_ascii_digit()) {
+                *numeric.entry(numeric_pattern(label)).or_insert(0) += 1;
+            }
+        }
+    }
+
+    Analysis {
+        hosts: count,
+        prefixes: ranked(prefixes),
+        labels: ranked(labels),
+        tokens: ranked(tokens),
+        numeric: ranked(numeric),
+    }
+}
+
+/// Builds a word list for brute forcing and permutations out of the labels below the
+/// registrable domain of `hosts` and the words they're made of, most common first. Words in
+/// `base` which weren't seen follow in their own order, so a generic list can be topped up
+/// with the target's own naming. `top` limits how many of the seen words are kept.
+pub fn wordlist<I, S>(hosts: I, base: &[String], top: Option<usize>) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let analysis = analyze(hosts);
+    // a label made of a single word shows up as a token too, so take the larger count
+    // rather than adding them up
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for (word, count) in analysis.labels.into_iter().chain(analysis.tokens) {
+        let entry = counts.entry(word).or_insert(0);
+        *entry = (*entry).max(count);
+    }
+
+    let mut words: Vec<String> = ranked(counts).into_iter().map(|(word, _)| word).collect();
+    if let Some(top) = top {
+        words.truncate(top);
+    }
+
+    let mut seen: HashSet<String> = words.iter().cloned().collect();
+    for word in base {
+        let word = word.trim().to_lowercase();
+        if !word.is_empty() && seen.insert(word.clone()) {
+            words.push(word);
+        }
+    }
+
+    words
+}
+
+/// Tracks which sources returned each result, to work out how many results only one source
+/// provided. Useful for deciding which paid subscriptions are worth keeping, a source with
+/// few unique results could be dropped without losing much.
+#[derive(Debug, Default)]
+pub struct Contributions {
+    // the sources which returned each result, a bit per position in `Source::ALL`
+    found_by: HashMap<String, u32>,
+    totals: BTreeMap<Source, usize>,
+}
+
+impl Contributions {
+    /// Records that `source` returned `result`, results should already be cleaned
+    pub fn record<S: Into<String>>(&mut self, source: Source, result: S) {
+        let bit = 1 << Source::ALL.iter().position(|s| *s == source).unwrap_or(0);
+        let found_by = self.found_by.entry(result.into()).or_insert(0);
+        if *found_by & bit == 0 {
+            *found_by |= bit;
+            *self.totals.entry(source).or_insert(0) += 1;
+        }
+    }
+
+    /// How many results each source returned, and how many of those no other source did,
+    /// ordered by source
+    pub fn unique(&self) -> Vec<(Source, usize, usize)> {
+        let mut unique: BTreeMap<Source, usize> = BTreeMap::new();
+        for found_by in self.found_by.values().filter(|f| f.count_ones() == 1) {
+            let source = Source::ALL[found_by.trailing_zeros() as usize];
+            *unique.entry(source).or_insert(0) += 1;
+        }
+
+        self.totals
+            .iter()
+            .map(|(source, total)| (*source, *total, unique.get(source).copied().unwrap_or(0)))
+            .collect()
+    }
+
+    /// The sources which returned `result` so far, in `Source::ALL` order
+    pub fn sources(&self, result: &str) -> Vec<Source> {
+        let found_by = self.found_by.get(result).copied().unwrap_or(0);
+        Source::ALL
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| found_by & (1 << i) != 0)
+            .map(|(_, s)| *s)
+            .collect()
+    }
+}
+
+/// Replaces each run of digits in `label` with `{n}`
+fn numeric_pattern(label: &str) -> String {
+    let mut pattern = String::with_capacity(label.len());
+    let mut in_digits = false;
+    for c in label.chars() {
+        if c.is_ascii_digit() {
+            if !in_digits {
+                pattern.push_str("{n}");
+            }
+            in_digits = true;
+        } else {
+            pattern.push(c);
+            in_digits = false;
+        }
+    }
+
+    pattern
+}
+
+fn ranked(counts: HashMap<String, usize>) -> Vec<(String, usize)> {
+    let mut ranked: Vec<(String, usize)> = counts.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_unique_contributions() {
+        let mut contributions = Contributions::default();
+        contributions.record(Source::Crtsh, "api.hackerone.com");
+        contributions.record(Source::Crtsh, "api.hackerone.com");
+        contributions.record(Source::Crtsh, "www.hackerone.com");
+        contributions.record(Source::Wayback, "www.hackerone.com");
+        contributions.record(Source::Wayback, "docs.hackerone.com");
+        contributions.record(Source::Chaos, "www.hackerone.com");
+        contributions.record(Source::Crtsh, "www.hackerone.com");
+
+        assert_eq!(
+            contributions.unique(),
+            vec![
+                (Source::Chaos, 1, 0),
+                (Source::Crtsh, 2, 1),
+                (Source::Wayback, 2, 1)
+            ]
+        );
+        assert_eq!(
+            contributions.sources("docs.hackerone.com"),
+            vec![Source::Wayback]
+        );
+        assert_eq!(contributions.sources("www.hackerone.com").len(), 3);
+        assert!(contributions.sources("mail.hackerone.com").is_empty());
+    }
+
+    #[test]
+    fn builds_wordlists() {
+        let hosts = vec![
+            "api.hackerone.com",
+            "dev-api.hackerone.com",
+            "api.docs.hackerone.com",
+            "web01.hackerone.com",
+        ];
+        let base = vec!["www".to_string(), "API".to_string(), "".to_string()];
+
+        let words = wordlist(hosts.clone(), &base, None);
+        assert_eq!(words[0], "api");
+        assert!(words.contains(&"dev-api".to_string()));
+        assert!(words.contains(&"dev".to_string()));
+        assert_eq!(words.last().unwrap(), "www");
+        assert_eq!(words.iter().filter(|w| *w == "api").count(), 1);
+
+        assert_eq!(wordlist(hosts, &base, Some(1)), vec!["api", "www"]);
+    }
+
+    #[test]
+    fn counts_labels() {
+        let analysis = analyze(vec![
+            "dev.api.hackerone.com",
+            "api.hackerone.com",
+            "web01.hackerone.com",
+            "web-02.staging.hackerone.com",
+            "hackerone.com",
+        ]);
+
+        assert_eq!(analysis.hosts, 5);
+        assert_eq!(analysis.prefixes[0], ("api".to_string(), 2));
+        assert_eq!(analysis.labels.len(), 5);
+        assert!(analysis.tokens.contains(&("web".to_string(), 2)));
+        assert_eq!(
+            analysis.numeric,
+            vec![("web-{n}".to_string(), 1), ("web{n}".to_string(), 1)]
+        );
+    }
+}
@@ -1,194 +1,1991 @@
 extern crate sub;
-use clap::{App, Arg};
-use futures::stream::StreamExt;
-use std::collections::HashSet;
-use std::env;
+use addr::DomainName;
+use clap::{ArgAction, Args, Parser, Subcommand};
+use futures::stream::{self, StreamExt};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs::File;
 use std::io::{self, BufRead, BufReader};
-use sub::error::Result;
-use sub::{CleanExt, PostProcessor, Runner};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use sub::analyze::{self, Contributions};
+use sub::cdn::{Cdn, CdnRanges};
+use sub::demo;
+use sub::dnscache::{self, DnsCache};
+use sub::enrich::{Enricher, Internal, Probe, Reachable, Resolve};
+use sub::error::{Result, SubError};
+use sub::failures::{self, Failure};
+use sub::fdlimit::FdLimit;
+use sub::generate;
+use sub::history::redact_arg;
+use sub::input::{self, RootSpec};
+use sub::output::Format;
+use sub::telemetry::{Report, Telemetry};
+use sub::units::{KIB, MIB};
+#[cfg(feature = "worker")]
+use sub::worker::{Coordination, Worker};
+use sub::{
+    ByteSize, CleanExt, Date, Dedup, DnsRecord, Engagement, Enrichment, Event, Fanout, Heuristics,
+    History, IpVersion, OutputOptions, OutputSink, OutputSpec, Overflow, PostProcessor, Profile,
+    ProfileSettings, Quotas, RecordType, Records, RunRecord, Runner, ScrapePolicy, Settings,
+    SkipReason, Skipped, Source, Subdomain, Summary, Template, TimeSpan, TlsBackend,
+};
+use tokio::sync::Semaphore;
+use tokio::time;
+use tracing_subscriber::EnvFilter;
+
+/// Used when neither the profile nor the command line sets the concurrency
+const DEFAULT_CONCURRENCY: usize = 200;
+/// Used when neither the profile nor the command line sets the timeout
+const DEFAULT_TIMEOUT: u64 = 15;
+/// How many results are handed to the enrichers at once when printing collected results
+const PRINT_BATCH: usize = 1000;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    init_tracing(&cli);
+
+    let engagement = match &cli.engagement {
+        Some(path) => Engagement::from_path(path)?,
+        None => Engagement::default(),
+    };
+
+    match &cli.command {
+        Some(Command::Sources {
+            command: SourcesCommand::Endpoints,
+        }) => {
+            // not built, so endpoints can be listed before any keys are set up
+            let runner = RunnerOpts::new(&cli, &engagement)?.runner();
+            for host in runner.endpoints() {
+                println!("{}", host);
+            }
+            return Ok(());
+        }
+        Some(Command::Runs { command }) => return show_runs(&history(&cli)?, command),
+        Some(Command::Analyze { file, top }) => return print_analysis(file, *top, cli.print.json),
+        Some(Command::Generate { file, count }) => {
+            return print_candidates(file, *count, &cli.enrich).await
+        }
+        Some(Command::Wordlist {
+            command: WordlistCommand::Build { files, base, top },
+        }) => return print_wordlist(files, base.as_deref(), *top),
+        #[cfg(feature = "worker")]
+        Some(Command::Worker(worker)) => return run_worker(&cli, &engagement, worker).await,
+        None => {}
+    }
+
     let ParsedArgs {
-        runner,
+        mut runners,
         cleaner,
         flush,
+        flush_interval,
+        flush_after,
+        stats,
+        preflight,
+        sampled,
+        telemetry,
+        mut printer,
+        mut results,
         hosts,
-    } = ParsedArgs::new(create_clap_app())?;
-    let mut results: HashSet<String> = HashSet::new();
+        dns_cache,
+        client,
+    } = ParsedArgs::new(&cli, &engagement)?;
+    let start = Instant::now();
+    let mut run = RunRecord::new(SystemTime::now(), std::env::args().skip(1).collect());
+    let roots = hosts.len();
+    let incremental = flush_interval.is_some() || flush_after.is_some();
+    let mut ticker = flush_interval.map(time::interval);
+    let mut pending = Pending::default();
+    let mut failures: Vec<(Source, Arc<String>, SubError)> = Vec::new();
+    let mut found: BTreeMap<Source, usize> = BTreeMap::new();
+    let mut outcome = Ok(());
+    let mut printed = 0;
 
-    let mut stream = runner.run(hosts).await?;
-    while let Some(v) = stream.next().await {
-        v.iter().clean(&cleaner).for_each(|r| {
+    let missing: BTreeSet<Source> = runners
+        .iter()
+        .flat_map(|(r, _)| r.missing_keys().iter().copied())
+        .collect();
+    if !missing.is_empty() {
+        let skipped: Vec<String> = missing.iter().map(|s| s.to_string()).collect();
+        eprintln!("skipping sources without api keys: {}", skipped.join(", "));
+    }
+    let undated: BTreeSet<Source> = runners.iter().flat_map(|(r, _)| r.undated()).collect();
+    if !undated.is_empty() {
+        let names: Vec<String> = undated.iter().map(|s| s.to_string()).collect();
+        eprintln!(
+            "ignoring --since and --until for sources which can't filter by date: {}",
+            names.join(", ")
+        );
+    }
+    if preflight {
+        let mut unreachable = BTreeMap::new();
+        for (runner, _) in runners.iter_mut() {
+            unreachable.extend(runner.preflight().await);
+        }
+        for (source, e) in unreachable {
+            eprintln!("skipping {}, {}", source, e);
+        }
+    }
+    let mut disallowed = BTreeMap::new();
+    for (runner, _) in runners.iter_mut() {
+        disallowed.extend(runner.check_robots().await?);
+    }
+    for (source, reason) in disallowed {
+        eprintln!("skipping {}, {}", source, reason);
+    }
+
+    // the runners share their budgets, so any of them can report the usage
+    let quotas = runners[0].0.quotas();
+    let mut skipped: Vec<Skipped> = runners.iter().flat_map(|(r, _)| r.skipped()).collect();
+    skipped.sort_by_key(|s| s.source);
+    skipped.dedup();
+    let mut streams = Vec::new();
+    for (runner, hosts) in runners {
+        streams.push(runner.events(hosts).await?);
+    }
+    let mut stream = stream::select_all(streams);
+    'recv: loop {
+        let next = match ticker.as_mut() {
+            Some(ticker) => tokio::select! {
+                v = stream.next() => v,
+                _ = ticker.tick() => {
+                    printed += printer.write_all(pending.take_all()).await?;
+                    continue;
+                }
+            },
+            None => stream.next().await,
+        };
+
+        let (host, source, v) = match next {
+            Some(Event::Results {
+                host,
+                source,
+                results,
+            }) => {
+                *found.entry(source).or_insert(0) += results.len();
+                (host, source, results)
+            }
+            Some(Event::Failed {
+                host,
+                source,
+                error,
+            }) => {
+                failures.push((source, host, error));
+                continue;
+            }
+            Some(Event::Finished { host }) => {
+                // a finished root's results go out now rather than with the slowest root's
+                if flush || incremental {
+                    printed += printer.write_all(pending.take(&host)).await?;
+                }
+                continue;
+            }
+            None => break,
+        };
+
+        for r in v.iter().clean_for(&cleaner, &host) {
+            printer.record(source, &r);
             if flush {
-                println!("{}", r);
+                if printer.write(&r, &host).await? {
+                    printed += 1;
+                }
+                continue;
+            }
+
+            let inserted = if incremental {
+                results.insert(r.clone()).map(|new| {
+                    if new {
+                        pending.push(r, Some(host.clone()));
+                    }
+                })
             } else {
-                results.insert(r);
+                results.insert(r).map(|_| ())
+            };
+
+            if let Err(e) = inserted {
+                // print what we've collected so far before bailing
+                outcome = Err(e);
+                break 'recv;
+            }
+        }
+
+        if flush_after.map_or(false, |n| pending.len() >= n) {
+            printed += printer.write_all(pending.take_all()).await?;
+        }
+    }
+
+    let peak_memory = results.peak_memory();
+    let spilled = (results.spilled_bytes(), results.spilled_runs());
+    if incremental {
+        printed += printer.write_all(pending.take_all()).await?;
+    } else if !flush {
+        // batched so enrichers have something to run concurrently
+        for r in results.into_results()? {
+            pending.push(r?, None);
+            if pending.len() >= PRINT_BATCH {
+                printed += printer.write_all(pending.take_all()).await?;
+            }
+        }
+        printed += printer.write_all(pending.take_all()).await?;
+    }
+    skipped.extend(quotas.exhausted().into_iter().map(|source| Skipped {
+        source,
+        reason: SkipReason::BudgetExhausted,
+    }));
+    printer
+        .finalize(&Summary {
+            roots,
+            results: printed,
+            failures: failures.len(),
+            elapsed: start.elapsed(),
+            skipped: skipped.clone(),
+        })
+        .await?;
+
+    if stats {
+        eprintln!("results: {}", printed);
+        eprintln!("peak memory: {} KiB", peak_memory / 1024);
+        eprintln!("spilled: {} KiB in {} runs", spilled.0 / 1024, spilled.1);
+        print_errors(&failures);
+        print_skipped(&skipped);
+    }
+    if stats || sampled {
+        eprintln!("results by source:");
+        for (source, count) in found.iter() {
+            eprintln!("  {}: {}", source, count);
+        }
+    }
+    if cli.print.unique_contribution {
+        print_contributions(printer.contributions().unwrap_or(&Contributions::default()));
+    }
+    if stats || quotas.is_limited() {
+        print_usage(&quotas);
+    }
+
+    let report = Report::new(failures.iter().map(|(source, _, e)| (source, e)));
+    if !report.is_empty() {
+        for t in telemetry.iter() {
+            t.send(&report, &client).await;
+        }
+    }
+
+    if let Some(path) = &cli.enrich.dns_cache {
+        if let Err(e) = dns_cache.save(path) {
+            eprintln!("couldn't save the dns cache: {}", e);
+        }
+    }
+
+    if let Some(path) = &cli.errors {
+        let records: Vec<Failure> = failures
+            .iter()
+            .map(|(source, host, error)| Failure::new(host.as_str(), *source, error))
+            .collect();
+        if let Err(e) = failures::write(path, &records) {
+            eprintln!("couldn't write the errors: {}", e);
+        }
+    }
+
+    if cli.record_history {
+        run.duration_ms = start.elapsed().as_millis() as u64;
+        run.roots = roots;
+        run.results = printed;
+        run.failures = failures.len();
+        run.errors = error_summary(&failures);
+        if let Err(e) = history(&cli).and_then(|h| h.record(&run)) {
+            eprintln!("couldn't record the run: {}", e);
+        }
+    }
+
+    engagement.notify(printed, &client).await;
+    outcome
+}
+
+/// Reads a file of results, either plain or written with --json, --null or
+/// --flush-prefix-root
+fn read_results(path: &Path) -> Result<Vec<String>> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| format!("tried to read {:?} got {}", path, e))?;
+    let hosts = contents
+        .split(|c| c == '\n' || c == '\0')
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| {
+            if !line.starts_with('{') {
+                let host = line.rsplit('\t').next().unwrap_or(line);
+                return Some(host.to_string());
+            }
+
+            serde_json::from_str::<serde_json::Value>(line)
+                .ok()
+                .and_then(|v| v["subdomain"].as_str().map(|s| s.to_string()))
+        })
+        .collect();
+
+    Ok(hosts)
+}
+
+/// The results an earlier run wrote to `spec`, so merging a retry into it only adds new ones
+fn written_results(spec: &OutputSpec) -> Result<Vec<String>> {
+    let path = Path::new(&spec.destination);
+    match spec.format {
+        _ if spec.destination == "-" => Ok(Vec::new()),
+        Some(Format::Webhook) => Ok(Vec::new()),
+        Some(Format::Roots) if path.is_dir() => {
+            let mut results = Vec::new();
+            for entry in std::fs::read_dir(path)? {
+                results.extend(read_results(&entry?.path())?);
             }
-        });
+            Ok(results)
+        }
+        _ if path.is_file() => read_results(path),
+        _ => Ok(Vec::new()),
     }
+}
 
-    if !flush {
-        results.iter().for_each(|r| println!("{}", r));
+/// Prints candidates generated from a file of results, only those which resolve with
+/// --resolve
+async fn print_candidates(path: &Path, count: usize, enrich: &EnrichArgs) -> Result<()> {
+    let candidates = generate::candidates(read_results(path)?, count);
+    if !enrich.resolve {
+        candidates.iter().for_each(|c| println!("{}", c));
+        return Ok(());
+    }
+
+    let cache = enrich.dns_cache()?;
+    let resolve = Resolve::with_cache(cache.clone());
+    let resolved = resolve.name().to_string();
+    let mut enrichment = Enrichment::default();
+    enrichment.add(resolve, enrich.enrich_concurrency);
+    let batch = candidates.into_iter().map(Subdomain::new).collect();
+    for candidate in enrichment.run(batch).await {
+        if candidate.enrichments.contains_key(&resolved) {
+            println!("{}", candidate.name);
+        }
+    }
+
+    match &enrich.dns_cache {
+        Some(path) => cache.save(path),
+        None => Ok(()),
+    }
+}
+
+/// Prints a word list built from the labels in files of results, followed by the words of
+/// the base list which weren't seen
+fn print_wordlist(paths: &[PathBuf], base: Option<&Path>, top: Option<usize>) -> Result<()> {
+    let mut hosts = Vec::new();
+    for path in paths {
+        hosts.extend(read_results(path)?);
+    }
+
+    let base: Vec<String> = match base {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| format!("tried to read {:?} got {}", path, e))?
+            .lines()
+            .filter(|l| !l.trim_start().starts_with('#'))
+            .map(|l| l.to_string())
+            .collect(),
+        None => Vec::new(),
+    };
+
+    for word in analyze::wordlist(hosts, &base, top) {
+        println!("{}", word);
     }
 
     Ok(())
 }
 
+/// Prints label statistics for a file of results, either plain or written with --json
+fn print_analysis(path: &Path, top: usize, json: bool) -> Result<()> {
+    let analysis = analyze::analyze(read_results(path)?).top(top);
+
+    if json {
+        let out = serde_json::to_string(&analysis).map_err(|e| e.to_string())?;
+        println!("{}", out);
+        return Ok(());
+    }
+
+    println!("hosts: {}", analysis.hosts);
+    for (name, counts) in [
+        ("prefixes", &analysis.prefixes),
+        ("labels", &analysis.labels),
+        ("tokens", &analysis.tokens),
+        ("numeric patterns", &analysis.numeric),
+    ]
+    .iter()
+    {
+        println!("{}:", name);
+        for (value, count) in counts.iter() {
+            println!("  {}\t{}", count, value);
+        }
+    }
+
+    Ok(())
+}
+
+/// The run history at the path passed with --history, or the default one
+fn history(cli: &Cli) -> Result<History> {
+    match cli.history.clone().or_else(History::default_path) {
+        Some(path) => Ok(History::new(path)),
+        None => Err(SubError::ConfigError(
+            "no home directory to keep the run history in, pass --history".into(),
+        )),
+    }
+}
+
+fn show_runs(history: &History, command: &RunsCommand) -> Result<()> {
+    match command {
+        RunsCommand::List => {
+            for run in history.runs()? {
+                println!(
+                    "{}\t{:.1}s\t{} roots\t{} results\t{} failures\t{}",
+                    run.id,
+                    run.duration().as_secs_f64(),
+                    run.roots,
+                    run.results,
+                    run.failures,
+                    redacted_args(&run)
+                );
+            }
+        }
+        RunsCommand::Show { id } => match history.get(*id)? {
+            Some(run) => {
+                println!("id: {}", run.id);
+                println!("args: {}", redacted_args(&run));
+                println!("duration: {:.1}s", run.duration().as_secs_f64());
+                println!("roots: {}", run.roots);
+                println!("results: {}", run.results);
+                println!("failures: {}", run.failures);
+                for e in run.errors.iter() {
+                    println!("  {}", e);
+                }
+            }
+            None => return Err(SubError::ConfigError(format!("no run with id {}", id))),
+        },
+    }
+
+    Ok(())
+}
+
+/// The arguments of a run, redacted again for runs recorded before arguments were
+fn redacted_args(run: &RunRecord) -> String {
+    let args: Vec<String> = run.args.iter().map(|a| redact_arg(a)).collect();
+    args.join(" ")
+}
+
+/// How often each source failed along with its first error, a line per source
+fn error_summary(failures: &[(Source, Arc<String>, SubError)]) -> Vec<String> {
+    let mut by_source: BTreeMap<Source, Vec<&SubError>> = BTreeMap::new();
+    for (source, _, error) in failures.iter() {
+        by_source
+            .entry(*source)
+            .or_insert_with(Vec::new)
+            .push(error);
+    }
+
+    by_source
+        .iter()
+        .map(|(source, errors)| {
+            format!(
+                "{}: {} failed, first error: {}",
+                source,
+                errors.len(),
+                errors[0]
+            )
+        })
+        .collect()
+}
+
+/// Prints how often each source failed along with its first error. Schema changes are
+/// listed in full as they're the most useful thing to include in a bug report.
+fn print_errors(failures: &[(Source, Arc<String>, SubError)]) {
+    if failures.is_empty() {
+        return;
+    }
+
+    eprintln!("errors:");
+    for line in error_summary(failures) {
+        eprintln!("  {}", line);
+    }
+
+    for (_, host, error) in failures.iter() {
+        if let SubError::SchemaChanged { .. } = error {
+            eprintln!("  schema changed for {}: {}", host, error);
+        }
+    }
+}
+
+/// Prints how many results each source found which no other source did
+fn print_contributions(contributions: &Contributions) {
+    eprintln!("unique results by source:");
+    for (source, total, unique) in contributions.unique() {
+        eprintln!("  {}: {} of {}", source, unique, total);
+    }
+}
+
+/// Lists the sources which didn't give full coverage and why
+fn print_skipped(skipped: &[Skipped]) {
+    if skipped.is_empty() {
+        return;
+    }
+
+    eprintln!("skipped sources:");
+    for s in skipped {
+        eprintln!("  {}: {}", s.source, s.reason);
+    }
+}
+
+/// Prints how many requests each source made, and its budget if it has one
+fn print_usage(quotas: &Quotas) {
+    eprintln!("requests:");
+    for (source, usage) in quotas.usage() {
+        let downloaded = usage.bytes / 1024;
+        match usage.max_requests {
+            Some(max) => eprintln!(
+                "  {}: {}/{}, {} KiB",
+                source, usage.requests, max, downloaded
+            ),
+            None => eprintln!("  {}: {}, {} KiB", source, usage.requests, downloaded),
+        }
+    }
+}
+
+/// Attaches the root, tags and heuristic matches to results before handing them to the sink
+struct Printer {
+    sink: Box<dyn OutputSink>,
+    roots: HashMap<String, Vec<String>>,
+    // maps the registrable domain of each root back to the root, for results which don't end
+    // with any of the roots.
+    registrable: HashMap<String, String>,
+    heuristics: Heuristics,
+    only_interesting: bool,
+    records: Records,
+    record_types: Vec<RecordType>,
+    enrichment: Enrichment,
+    // results are dropped if an enricher added any of `drop_with`, or didn't add all of
+    // `keep_with`
+    drop_with: Vec<String>,
+    keep_with: Vec<String>,
+    // whether batches are sorted before they're written, for reproducible runs
+    sorted: bool,
+    // the sources which returned each result, when contributions or `{sources}` need them
+    contributions: Option<Contributions>,
+}
+
+impl Printer {
+    fn new(sink: Box<dyn OutputSink>, roots: HashMap<String, Vec<String>>) -> Self {
+        let registrable = roots
+            .keys()
+            .filter_map(|r| {
+                r.parse::<DomainName>()
+                    .ok()
+                    .map(|d| (d.root().to_string(), r.to_owned()))
+            })
+            .collect();
+
+        Self {
+            sink,
+            roots,
+            registrable,
+            heuristics: Heuristics::default(),
+            only_interesting: false,
+            records: Records::default(),
+            record_types: Vec::new(),
+            enrichment: Enrichment::default(),
+            drop_with: Vec::new(),
+            keep_with: Vec::new(),
+            sorted: false,
+            contributions: None,
+        }
+    }
+
+    /// Sorts every batch before it's written, so incremental output is reproducible too
+    fn sorted(mut self) -> Self {
+        self.sorted = true;
+        self
+    }
+
+    /// Tracks which sources returned each result, so they're attached to what's written
+    fn track_sources(mut self) -> Self {
+        self.contributions = Some(Contributions::default());
+        self
+    }
+
+    /// Records that `source` returned `result`, if sources are tracked
+    fn record(&mut self, source: Source, result: &str) {
+        if let Some(contributions) = self.contributions.as_mut() {
+            contributions.record(source, result);
+        }
+    }
+
+    fn contributions(&self) -> Option<&Contributions> {
+        self.contributions.as_ref()
+    }
+
+    /// Sets the patterns used to flag interesting results, and whether to only print those
+    fn heuristics(mut self, heuristics: Heuristics, only_interesting: bool) -> Self {
+        self.heuristics = heuristics;
+        self.only_interesting = only_interesting;
+        self
+    }
+
+    /// Sets where the dns records of results are looked up, and the record types results
+    /// need one of to be printed, any result is printed if `types` is empty
+    fn records(mut self, records: Records, types: Vec<RecordType>) -> Self {
+        self.records = records;
+        self.record_types = types;
+        self
+    }
+
+    /// Sets the enrichers run over results before they're written
+    fn enrichment(mut self, enrichment: Enrichment) -> Self {
+        self.enrichment = enrichment;
+        self
+    }
+
+    /// Drops results `enricher` added something to
+    fn drop_with(mut self, enricher: &str) -> Self {
+        self.drop_with.push(enricher.to_string());
+        self
+    }
+
+    /// Only keeps results `enricher` added something to
+    fn keep_with(mut self, enricher: &str) -> Self {
+        self.keep_with.push(enricher.to_string());
+        self
+    }
+
+    /// Whether an enriched result should still be printed
+    fn keep(&self, subdomain: &Subdomain) -> bool {
+        let has = |e: &String| subdomain.enrichments.contains_key(e);
+        !self.drop_with.iter().any(has) && self.keep_with.iter().all(has)
+    }
+
+    /// Finds the most specific root a result belongs to
+    fn root_of<'a>(&'a self, result: &'a str) -> Option<&'a str> {
+        let mut suffix = result;
+        loop {
+            if let Some((root, _)) = self.roots.get_key_value(suffix) {
+                return Some(root);
+            }
+
+            match suffix.find('.') {
+                Some(idx) => suffix = &suffix[idx + 1..],
+                None => break,
+            }
+        }
+
+        result
+            .parse::<DomainName>()
+            .ok()
+            .and_then(|d| self.registrable.get(d.root().to_str()))
+            .map(|r| r.as_str())
+    }
+
+    /// Attaches what's known about a result, or returns `None` if it's filtered out. `origin`
+    /// is the root the result was returned for, when that's known
+    fn prepare(&self, result: &str, origin: Option<&str>) -> Option<Subdomain> {
+        let interesting: Vec<String> = self
+            .heuristics
+            .matches(result)
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        if self.only_interesting && interesting.is_empty() {
+            return None;
+        }
+        let records = self.records.get(result);
+        let has_type = |r: &DnsRecord| self.record_types.contains(&r.record_type);
+        if !self.record_types.is_empty() && !records.iter().any(has_type) {
+            return None;
+        }
+
+        let mut subdomain = Subdomain::new(result);
+        subdomain.root = origin
+            .filter(|o| self.roots.contains_key(*o))
+            .or_else(|| self.root_of(result))
+            .map(|r| r.to_string());
+        subdomain.tags = subdomain
+            .root
+            .as_ref()
+            .and_then(|r| self.roots.get(r))
+            .cloned()
+            .unwrap_or_default();
+        subdomain.sources = self
+            .contributions
+            .iter()
+            .flat_map(|c| c.sources(result))
+            .map(|s| s.to_string())
+            .collect();
+        subdomain.interesting = interesting;
+        subdomain.records = records;
+        Some(subdomain)
+    }
+
+    /// Writes a result returned for `origin`, returning whether it was printed or filtered out
+    async fn write(&mut self, result: &str, origin: &str) -> Result<bool> {
+        let subdomain = match self.prepare(result, Some(origin)) {
+            Some(s) => s,
+            None => return Ok(false),
+        };
+
+        let mut printed = false;
+        for subdomain in self.enrichment.run(vec![subdomain]).await {
+            if self.keep(&subdomain) {
+                self.sink.write(&subdomain).await?;
+                printed = true;
+            }
+        }
+        Ok(printed)
+    }
+
+    /// Prints the results which haven't been written yet, returning how many were printed
+    async fn write_all(&mut self, mut pending: Batch) -> Result<usize> {
+        if self.sorted {
+            pending.sort();
+        }
+        let batch: Vec<Subdomain> = pending
+            .into_iter()
+            .filter_map(|(r, origin)| self.prepare(&r, origin.as_deref().map(|o| o.as_str())))
+            .collect();
+        let mut count = 0;
+        for subdomain in self.enrichment.run(batch).await {
+            if self.keep(&subdomain) {
+                self.sink.write(&subdomain).await?;
+                count += 1;
+            }
+        }
+
+        self.sink.flush().await?;
+        Ok(count)
+    }
+
+    async fn finalize(&mut self, summary: &Summary) -> Result<()> {
+        self.sink.finalize(summary).await
+    }
+}
+
+/// Results to print along with the root they were returned for, when that's known
+type Batch = Vec<(String, Option<Arc<String>>)>;
+
+/// Results waiting to be printed, kept by the root they were returned for so a finished
+/// root's can be printed without the others'
+#[derive(Default)]
+struct Pending {
+    roots: BTreeMap<Option<Arc<String>>, Vec<String>>,
+    len: usize,
+}
+
+impl Pending {
+    /// Adds a result returned for `origin`, when that's known
+    fn push(&mut self, result: String, origin: Option<Arc<String>>) {
+        self.roots.entry(origin).or_default().push(result);
+        self.len += 1;
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Takes the results waiting for `root`
+    fn take(&mut self, root: &Arc<String>) -> Batch {
+        let results = self
+            .roots
+            .remove(&Some(Arc::clone(root)))
+            .unwrap_or_default();
+        self.len -= results.len();
+        results
+            .into_iter()
+            .map(|r| (r, Some(Arc::clone(root))))
+            .collect()
+    }
+
+    /// Takes every result which is waiting
+    fn take_all(&mut self) -> Batch {
+        self.len = 0;
+        std::mem::take(&mut self.roots)
+            .into_iter()
+            .flat_map(|(origin, results)| results.into_iter().map(move |r| (r, origin.clone())))
+            .collect()
+    }
+}
+
 struct ParsedArgs {
-    runner: Runner,
+    // a runner per profile the roots asked for, along with their roots
+    runners: Vec<(Runner, HashSet<String>)>,
     cleaner: PostProcessor,
     flush: bool,
+    flush_interval: Option<Duration>,
+    flush_after: Option<usize>,
+    stats: bool,
+    preflight: bool,
+    sampled: bool,
+    telemetry: Vec<Telemetry>,
+    printer: Printer,
+    results: Dedup,
     hosts: HashSet<String>,
+    dns_cache: DnsCache,
+    // what webhooks, telemetry and notifications are sent with
+    client: reqwest::Client,
 }
 
 impl ParsedArgs {
-    fn new(app: clap::App<'static, 'static>) -> Result<Self> {
-        let matches = app.get_matches();
-        // make it a hashset incase user provided duplicate domains
-        let mut hosts: HashSet<String> = HashSet::new();
-        let mut excluded: Vec<&str> = Vec::new();
-        let max_concurrent: usize = matches.value_of("concurrency").unwrap().parse()?;
-        let timeout: u64 = matches.value_of("timeout").unwrap().parse()?;
-
-        if matches.is_present("verbosity") {
-            let builder = tracing_subscriber::fmt()
-                .with_env_filter(matches.value_of("verbosity").unwrap())
-                .with_filter_reloading();
-            let _handle = builder.reload_handle();
-            builder.try_init()?;
-        }
-
-        if matches.is_present("file") {
-            let input = matches.value_of("input").unwrap();
-            hosts = read_input(Some(input))?;
-        } else if matches.is_present("domain") {
-            hosts.insert(matches.value_of("input").unwrap().to_string());
+    fn new(cli: &Cli, engagement: &Engagement) -> Result<Self> {
+        let mut specs: Vec<RootSpec> = Vec::new();
+        let mut results = Dedup::default();
+
+        let retry = cli.retry_from.as_deref().map(failures::read).transpose()?;
+        if cli.demo {
+            specs.push(RootSpec::new(demo::DOMAIN));
+        } else if let Some(pairs) = &retry {
+            specs.extend(pairs.iter().map(|(root, _)| RootSpec::new(root.clone())));
+        } else if let Some(path) = &cli.file {
+            specs = read_input(Some(path))?;
+        } else if !cli.domain.is_empty() {
+            specs.extend(cli.domain.iter().map(|line| RootSpec::from_line(line)));
+        } else if let Some(input) = &cli.input {
+            if Path::new(input).is_file() {
+                specs = read_input(Some(Path::new(input)))?;
+            } else {
+                specs.push(RootSpec::from_line(input));
+            }
+        } else if !engagement.roots.is_empty() {
+            specs.extend(
+                engagement
+                    .roots
+                    .iter()
+                    .map(|line| RootSpec::from_line(line)),
+            );
         } else {
-            hosts = read_input(None)?;
+            specs = read_input(None)?;
+        }
+
+        // make it a map incase user provided duplicate domains
+        let mut roots: HashMap<String, Vec<String>> = HashMap::new();
+        for spec in specs.iter() {
+            let tags = roots.entry(spec.root.clone()).or_insert_with(Vec::new);
+            for tag in spec.tags.iter() {
+                if !tags.contains(tag) {
+                    tags.push(tag.clone());
+                }
+            }
+        }
+        let hosts: HashSet<String> = roots.keys().cloned().collect();
+        // with --auto-root the registrable domain is enumerated in place of each root, and the
+        // cleaner narrows the results back down to the roots
+        let enumerated = |root: &str| -> Result<String> {
+            if cli.auto_root {
+                registrable(root)
+            } else {
+                Ok(root.to_string())
+            }
+        };
+
+        if cli.print.flush_interval.map_or(false, |t| t.as_secs() == 0) {
+            return Err(SubError::ConfigError(
+                "--flush-interval needs to be at least 1s".into(),
+            ));
+        }
+        if let Some(limit) = cli.memory.max_memory {
+            let incremental = cli.print.flush_interval.is_some() || cli.print.flush_after.is_some();
+            // spilled results aren't in memory to dedup against, so they'd be printed again
+            if incremental && !cli.memory.no_spill {
+                return Err(SubError::ConfigError(
+                    "--max-memory can't spill results with --flush-interval or --flush-after, \
+                     add --no-spill"
+                        .into(),
+                ));
+            }
+            let overflow = if cli.memory.no_spill {
+                Overflow::Abort
+            } else {
+                Overflow::Spill
+            };
+            results = Dedup::with_limit(limit.bytes() as usize, overflow);
         }
 
-        if matches.is_present("exclude") {
-            excluded = matches.values_of("exclude").unwrap().collect();
+        if cli.seed.is_some() {
+            results = results.sorted();
         }
 
         let mut cleaner = PostProcessor::default();
-        if matches.is_present("subs-only") {
+        if cli.subs_only || engagement.subs_only {
             cleaner.any_subdomain(hosts.clone());
+        } else if cli.auto_root {
+            cleaner.within(hosts.clone());
         } else {
             cleaner.any_root(hosts.clone());
         }
+        cleaner.exclude(engagement.scope_exclude.iter().cloned());
+        for path in cli.print.known.iter() {
+            cleaner.known(read_results(path)?);
+        }
 
-        let mut runner = Runner::default()
-            .concurrency(max_concurrent)
-            .timeout(timeout)
-            .free_sources()
-            .exclude(&excluded);
-        if matches.is_present("all_sources") {
-            runner = runner.all_sources().exclude(&excluded);
+        let json = cli.print.json || engagement.json;
+        let mut outputs = cli.print.output.clone();
+        if outputs.is_empty() {
+            outputs.push(OutputSpec {
+                format: None,
+                destination: engagement
+                    .output
+                    .as_ref()
+                    .map_or("-".into(), |p| p.to_string_lossy().into_owned()),
+            });
+        }
+        // webhooks, probes and notifications connect like the sources do, with the same
+        // timeout, bound address and tls
+        let runner = RunnerOpts::new(cli, engagement)?.runner();
+        let client = runner.client_builder()?.build()?;
+        let options = OutputOptions {
+            json,
+            prefix_root: cli.print.flush_prefix_root,
+            template: cli.print.format_template.clone(),
+            null: cli.print.null,
+            // retried results are merged into what the earlier run wrote
+            append: retry.is_some(),
+            client: client.clone(),
+        };
+        if retry.is_some() {
+            for spec in outputs.iter() {
+                cleaner.known(written_results(spec)?);
+            }
+        }
+        let mut sink = Fanout::default();
+        for spec in outputs.iter() {
+            sink.push(spec.open(&options)?);
+        }
+        let filter_internal = cli.enrich.only_internal || cli.enrich.no_internal;
+        // every enricher which resolves results shares the answers
+        let dns_cache = cli.enrich.dns_cache()?;
+        let mut enrichment = Enrichment::default();
+        if cli.enrich.resolve || cli.enrich.exclude_cdn || filter_internal {
+            let resolve = Resolve::with_cache(dns_cache.clone());
+            enrichment.add(resolve, cli.enrich.enrich_concurrency);
+            enrichment.add(Internal, cli.enrich.enrich_concurrency);
+        }
+        if cli.enrich.exclude_cdn {
+            let ranges = match &cli.enrich.cdn_ranges {
+                Some(path) => CdnRanges::from_path(path)?,
+                None => CdnRanges::default(),
+            };
+            enrichment.add(Cdn::new(ranges), cli.enrich.enrich_concurrency);
+        }
+        if cli.enrich.check_reachable {
+            let mut reachable = Reachable::default()
+                .timeout(cli.enrich.reachable_timeout.duration())
+                .cache(dns_cache.clone());
+            if let Some(addr) = runner.bind_address() {
+                reachable = reachable.local_address(addr);
+            }
+            enrichment.gate(reachable, cli.enrich.reachable_concurrency);
+        }
+        if cli.enrich.probe {
+            let probe = Probe::new(client.clone());
+            enrichment.add(probe, cli.enrich.enrich_concurrency);
+        }
+        let config = Settings::load(cli.config.as_deref())?;
+        let mut heuristics = Heuristics::default();
+        heuristics.extend(config.heuristics.patterns);
+
+        // every runner adds to the same records, so the printer can look any result up. Only
+        // json output and --record-types use them, the rest of the time they're not kept
+        let shows_records = json
+            || !cli.print.record_types.is_empty()
+            || outputs
+                .iter()
+                .any(|o| matches!(o.format, Some(Format::Jsonl) | Some(Format::Webhook)));
+        let records = match shows_records {
+            true => Records::default(),
+            false => Records::untracked(),
+        };
+        let mut printer = Printer::new(Box::new(sink), roots)
+            .heuristics(heuristics, cli.print.only_interesting)
+            .records(records.clone(), cli.print.record_types.clone())
+            .enrichment(enrichment);
+        if cli.enrich.exclude_cdn {
+            printer = printer.drop_with(Cdn::default().name());
+        }
+        if cli.enrich.only_internal {
+            printer = printer.keep_with(Internal.name());
+        }
+        if cli.enrich.no_internal {
+            printer = printer.drop_with(Internal.name());
+        }
+        if cli.seed.is_some() {
+            printer = printer.sorted();
+        }
+        let sources_field = cli.print.format_template.as_ref();
+        if cli.print.unique_contribution || sources_field.map_or(false, |t| t.uses("sources")) {
+            printer = printer.track_sources();
+        }
+
+        let mut telemetry = Vec::new();
+        if cli.print.telemetry {
+            let endpoint = config.telemetry.endpoint.ok_or_else(|| {
+                SubError::ConfigError("--telemetry needs [telemetry] endpoint to be set".into())
+            })?;
+            telemetry.push(Telemetry::Endpoint(endpoint));
+        }
+        if let Some(path) = &cli.print.telemetry_file {
+            telemetry.push(Telemetry::File(path.clone()));
+        }
+
+        // roots sharing a profile and sources share a runner
+        let mut groups: HashMap<(Option<Profile>, Option<Vec<Source>>), HashSet<String>> =
+            HashMap::new();
+        for spec in specs.iter() {
+            groups
+                .entry((spec.profile()?, spec.sources()?))
+                .or_default()
+                .insert(enumerated(&spec.root)?);
+        }
+        if groups.is_empty() {
+            groups.insert((None, None), HashSet::new());
+        }
+
+        let mut runners = Vec::new();
+        let mut quotas: Option<Quotas> = None;
+        // -c bounds the whole run, however many profiles split it up
+        let slots = Arc::new(Semaphore::new(
+            cli.concurrency.unwrap_or(DEFAULT_CONCURRENCY),
+        ));
+        for ((profile, sources), hosts) in groups {
+            let mut runner = RunnerOpts::with_profile(cli, engagement, profile)?.runner();
+            if let Some(sources) = &sources {
+                runner = runner.only_sources(sources);
+            }
+            if let Some(pairs) = &retry {
+                runner = runner.only_pairs(pairs.clone());
+            }
+            if cli.demo {
+                runner = runner
+                    .only_sources(&demo::sources())
+                    .fixtures(demo::fixtures());
+            }
+            // budgets apply to the whole run, however many profiles it uses
+            match &quotas {
+                Some(quotas) => runner = runner.share_quotas(quotas.clone()),
+                None => quotas = Some(runner.quotas()),
+            }
+            runner = runner
+                .share_records(records.clone())
+                .share_concurrency(Arc::clone(&slots));
+            runners.push((runner.build()?, hosts));
+        }
+
+        // every runner's connections are open at the same time, so they share the limit
+        if let Some(mut limit) = FdLimit::current() {
+            if cli.raise_fd_limit {
+                limit = limit.raise()?;
+            }
+            let open: u64 = runners.iter().map(|(r, _)| r.open_files()).sum();
+            if let Some(shortfall) = limit.check(open) {
+                if cli.auto_tune {
+                    let available = limit.available();
+                    runners = runners
+                        .into_iter()
+                        .map(|(runner, hosts)| {
+                            let share = available * runner.open_files() / open;
+                            (runner.limit_open_files(share), hosts)
+                        })
+                        .collect();
+                    eprintln!(
+                        "lowered concurrency to fit the open file limit of {}",
+                        limit.soft
+                    );
+                } else {
+                    eprintln!("warning: {}", shortfall);
+                }
+            }
         }
 
         Ok(Self {
-            runner,
+            runners,
             cleaner,
-            flush: matches.is_present("flush"),
+            flush: cli.print.flush,
+            flush_interval: cli.print.flush_interval.map(|t| t.duration()),
+            flush_after: cli.print.flush_after,
+            stats: cli.print.stats,
+            preflight: cli.network.preflight,
+            sampled: cli.sources.sample.is_some(),
+            telemetry,
+            printer,
+            results,
             hosts,
+            dns_cache,
+            client,
         })
     }
 }
-/// Reads input from stdin or a file
-fn read_input(path: Option<&str>) -> Result<HashSet<String>> {
-    let mut contents = HashSet::new();
+
+/// The registrable domain `host` belongs to, e.g. `example.com` for `deep.api.example.com`
+fn registrable(host: &str) -> Result<String> {
+    host.parse::<DomainName>()
+        .map(|d| d.root().to_string())
+        .map_err(|_| {
+            SubError::ConfigError(format!("couldn't find the registrable domain of {}", host))
+        })
+}
+
+/// The options used to build a `Runner`, kept around so a worker can build one per root
+struct RunnerOpts {
+    settings: ProfileSettings,
+    keys: HashMap<String, String>,
+    fallbacks: Vec<Vec<Source>>,
+    budgets: Vec<(Source, usize)>,
+    max_response_size: Option<usize>,
+    size_limits: Vec<(Source, usize)>,
+    spool_threshold: Option<usize>,
+    max_bandwidth: Option<u64>,
+    extra_params: Vec<(Source, Vec<(String, String)>)>,
+    enabled: Vec<(Source, bool)>,
+    policy: ScrapePolicy,
+    polite: bool,
+    connect_timeout: Option<u64>,
+    source_timeouts: Vec<(Source, u64)>,
+    source_connect_timeouts: Vec<(Source, u64)>,
+    pool_idle_timeout: Option<u64>,
+    pool_max_idle: Option<usize>,
+    tcp_keepalive: Option<u64>,
+    http2_prior_knowledge: bool,
+    local_address: Option<IpAddr>,
+    ip_version: IpVersion,
+    tls_backend: Option<TlsBackend>,
+    root_certificates: Vec<PathBuf>,
+    sample: Option<usize>,
+    since: Option<Date>,
+    until: Option<Date>,
+    seed: Option<u64>,
+}
+
+impl RunnerOpts {
+    fn new(cli: &Cli, engagement: &Engagement) -> Result<Self> {
+        Self::with_profile(cli, engagement, None)
+    }
+
+    /// Like `new`, but using `profile` rather than the one given by the flags or engagement
+    fn with_profile(cli: &Cli, engagement: &Engagement, profile: Option<Profile>) -> Result<Self> {
+        let concurrency = cli.concurrency.unwrap_or(DEFAULT_CONCURRENCY);
+        let timeout = cli.timeout.map_or(DEFAULT_TIMEOUT, |t| t.as_secs());
+        let mut excluded = cli.sources.exclude.clone();
+        excluded.extend(engagement.exclude_sources.iter().cloned());
+        let all_sources = cli.sources.all_sources || engagement.all_sources;
+        let config = Settings::load(cli.config.as_deref())?;
+
+        let profile = match (profile, cli.sources.profile, engagement.profile.as_deref()) {
+            (Some(profile), _, _) | (None, Some(profile), _) => Some(profile),
+            (None, None, Some(name)) => Some(parse_profile(name)?),
+            (None, None, None) => None,
+        };
+        let settings = match profile {
+            Some(profile) => {
+                let mut settings = profile.settings();
+                if let Some(overrides) = config.profiles.get(&profile.to_string()) {
+                    settings = settings.merge(overrides);
+                }
+
+                // flags given on the command line take precedence over the profile
+                if let Some(concurrency) = cli.concurrency {
+                    settings.concurrency = concurrency;
+                }
+                if let Some(timeout) = cli.timeout {
+                    settings.timeout = timeout.as_secs();
+                }
+                settings.all_sources |= all_sources;
+                settings.exclude.extend(excluded);
+                settings
+            }
+            None => ProfileSettings {
+                all_sources,
+                exclude: excluded,
+                timeout,
+                concurrency,
+                max_results: None,
+            },
+        };
+
+        let mut fallbacks: Vec<Vec<Source>> = Vec::new();
+        if cli.sources.fallback {
+            fallbacks.extend(sub::fallback::GROUPS.iter().map(|g| g.to_vec()));
+        }
+        for group in cli.sources.fallback_group.iter() {
+            fallbacks.push(parse_group(group)?);
+        }
+
+        Ok(Self {
+            settings,
+            keys: engagement.keys.clone(),
+            fallbacks,
+            budgets: config.budgets()?,
+            max_response_size: cli.network.max_response_size.map(|b| b.bytes() as usize),
+            size_limits: config.size_limits()?,
+            spool_threshold: cli.memory.spool_threshold.map(|b| b.bytes() as usize),
+            max_bandwidth: cli.network.max_bandwidth.map(|b| b.bytes()),
+            extra_params: config.extra_params()?,
+            enabled: config.enabled()?,
+            policy: ScrapePolicy {
+                api_only: config.policy.api_only || cli.sources.stealth,
+                ..config.policy.clone()
+            },
+            polite: cli.sources.polite,
+            connect_timeout: cli.network.connect_timeout.map(|t| t.as_secs()),
+            source_timeouts: config.timeouts()?,
+            source_connect_timeouts: config.connect_timeouts()?,
+            pool_idle_timeout: cli.network.pool_idle_timeout.map(|t| t.as_secs()),
+            pool_max_idle: cli.network.pool_max_idle,
+            tcp_keepalive: cli.network.tcp_keepalive.map(|t| t.as_secs()),
+            http2_prior_knowledge: cli.network.http2_prior_knowledge,
+            local_address: local_address(&cli.network)?,
+            ip_version: cli.network.ip_version(),
+            tls_backend: cli.network.tls_backend.or(config.tls.backend),
+            root_certificates: config
+                .tls
+                .ca_certs
+                .iter()
+                .chain(cli.network.ca_cert.iter())
+                .cloned()
+                .collect(),
+            sample: cli.sources.sample,
+            since: cli.sources.since,
+            until: cli.sources.until,
+            seed: cli.seed,
+        })
+    }
+
+    fn runner(&self) -> Runner {
+        let mut runner = Runner::default()
+            .api_keys(self.keys.clone())
+            .profile(&self.settings);
+        for (source, enabled) in self.enabled.iter() {
+            runner = runner.enabled_by_default(*source, *enabled);
+        }
+        for group in self.fallbacks.iter() {
+            runner = runner.fallback(group);
+        }
+        runner = runner.scrape_policy(self.policy.clone());
+        if self.polite {
+            runner = runner.polite();
+        }
+        for (source, max) in self.budgets.iter() {
+            runner = runner.max_requests(*source, *max);
+        }
+        if let Some(bytes) = self.max_response_size {
+            runner = runner.max_response_size(bytes);
+        }
+        for (source, bytes) in self.size_limits.iter() {
+            runner = runner.source_max_response_size(*source, *bytes);
+        }
+        if let Some(bytes) = self.spool_threshold {
+            runner = runner.spool_threshold(bytes);
+        }
+        if let Some(bytes) = self.max_bandwidth {
+            runner = runner.max_bandwidth(bytes);
+        }
+        for (source, params) in self.extra_params.iter() {
+            runner = runner.extra_params(*source, params.clone());
+        }
+        if let Some(seconds) = self.connect_timeout {
+            runner = runner.connect_timeout(seconds);
+        }
+        for (source, seconds) in self.source_timeouts.iter() {
+            runner = runner.source_timeout(*source, *seconds);
+        }
+        for (source, seconds) in self.source_connect_timeouts.iter() {
+            runner = runner.source_connect_timeout(*source, *seconds);
+        }
+        if let Some(seconds) = self.pool_idle_timeout {
+            runner = runner.pool_idle_timeout(seconds);
+        }
+        if let Some(max) = self.pool_max_idle {
+            runner = runner.pool_max_idle_per_host(max);
+        }
+        if let Some(seconds) = self.tcp_keepalive {
+            runner = runner.tcp_keepalive(seconds);
+        }
+        if self.http2_prior_knowledge {
+            runner = runner.http2_prior_knowledge();
+        }
+        if let Some(addr) = self.local_address {
+            runner = runner.local_address(addr);
+        }
+        runner = runner.ip_version(self.ip_version);
+        if let Some(backend) = self.tls_backend {
+            runner = runner.tls_backend(backend);
+        }
+        for path in self.root_certificates.iter() {
+            runner = runner.root_certificate(path);
+        }
+        if let Some(n) = self.sample {
+            runner = runner.sample(n);
+        }
+        if let Some(date) = self.since {
+            runner = runner.since(date);
+        }
+        if let Some(date) = self.until {
+            runner = runner.until(date);
+        }
+        if let Some(seed) = self.seed {
+            runner = runner.seed(seed);
+        }
+
+        runner
+    }
+}
+
+/// Works out the address to bind outbound connections to from `--source-ip` and
+/// `--interface`. An interface with several addresses uses the first one of the requested
+/// family, preferring IPv4 when no family was given, and one without an address of the
+/// requested family is an error.
+fn local_address(network: &NetworkArgs) -> Result<Option<IpAddr>> {
+    if network.source_ip.is_some() {
+        return Ok(network.source_ip);
+    }
+
+    if let Some(name) = &network.interface {
+        let addrs: Vec<IpAddr> = if_addrs::get_if_addrs()?
+            .into_iter()
+            .filter(|i| &i.name == name)
+            .map(|i| i.ip())
+            .collect();
+        if addrs.is_empty() {
+            return Err(SubError::ConfigError(format!(
+                "no addresses found for {}",
+                name
+            )));
+        }
+        let version = network.ip_version();
+        let addr = match version {
+            IpVersion::Auto => addrs
+                .iter()
+                .find(|ip| ip.is_ipv4())
+                .or_else(|| addrs.first()),
+            version => addrs.iter().find(|ip| version.allows(ip)),
+        };
+        let family = match version {
+            IpVersion::V6 => "IPv6",
+            _ => "IPv4",
+        };
+        return addr
+            .copied()
+            .map(Some)
+            .ok_or_else(|| SubError::ConfigError(format!("{} has no {} address", name, family)));
+    }
+
+    Ok(None)
+}
+
+/// Parses a comma separated list of source names into a fallback group
+fn parse_group(group: &str) -> Result<Vec<Source>> {
+    group
+        .split(',')
+        .map(|s| {
+            Source::from_str(s.trim())
+                .map_err(|_| SubError::ConfigError(format!("unknown source {} in {}", s, group)))
+        })
+        .collect()
+}
+
+/// Parses a size flag which used to take a number of MiB, by still reading bare numbers as MiB
+fn mebibytes(s: &str) -> Result<ByteSize> {
+    ByteSize::parse_in(s, MIB)
+}
+
+fn kibibytes(s: &str) -> Result<ByteSize> {
+    ByteSize::parse_in(s, KIB)
+}
+
+/// Logs to stderr at the level picked by `-v`, or with the filter in `RUST_LOG` when it's set.
+/// A subscriber which is already installed is left in place rather than failing the run.
+fn init_tracing(cli: &Cli) {
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) => match cli.verbose {
+            0 => return,
+            1 => EnvFilter::new("sub=info,subdomaingather=info"),
+            2 => EnvFilter::new("sub=debug,subdomaingather=debug"),
+            _ => EnvFilter::new("trace"),
+        },
+    };
+
+    let res = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr)
+        .try_init();
+    if res.is_err() {
+        eprintln!("a tracing subscriber is already set, logs will go there");
+    }
+}
+
+/// Consumes roots from a Redis list until it's empty or the connection drops
+#[cfg(feature = "worker")]
+async fn run_worker(cli: &Cli, engagement: &Engagement, worker: &WorkerArgs) -> Result<()> {
+    let opts = RunnerOpts::new(cli, engagement)?;
+    // every root gets a fresh runner, check the config once up front
+    opts.runner().build()?;
+    let mut w = Worker::new(&worker.redis)?
+        .queue(&worker.queue)
+        .output(&worker.output)
+        .subs_only(cli.subs_only || engagement.subs_only)
+        .exit_when_empty(worker.exit_when_empty);
+
+    if worker.coordinate {
+        w = w.coordinate(
+            Coordination::default()
+                .lease_ttl(worker.lease_ttl.as_secs() as usize)
+                .done_ttl(worker.done_ttl.as_secs() as usize),
+        );
+    }
+
+    w.run(|| opts.runner()).await
+}
+
+/// Looks up a built in profile by name
+fn parse_profile(name: &str) -> Result<Profile> {
+    Profile::from_str(name).map_err(|_| SubError::ConfigError(format!("unknown profile {}", name)))
+}
+
+/// Reads roots from stdin or a file in any of the formats `input::read` accepts
+fn read_input(path: Option<&Path>) -> Result<Vec<RootSpec>> {
     let reader: Box<dyn BufRead> = match path {
         Some(filepath) => {
             Box::new(BufReader::new(File::open(filepath).map_err(|e| {
-                format!("tried to read filepath {} got {}", &filepath, e)
+                format!("tried to read filepath {:?} got {}", &filepath, e)
             })?))
         }
         None => Box::new(BufReader::new(io::stdin())),
     };
 
-    for line in reader.lines() {
-        contents.insert(line?);
-    }
-
-    Ok(contents)
-}
-
-/// Creates the Clap app to use sub library as a cli tool
-fn create_clap_app() -> clap::App<'static, 'static> {
-    App::new("sub")
-        .version(env!("CARGO_PKG_VERSION"))
-        .about("Gather subdomains from passive sources")
-        .usage("subdomaingather -d <domain.com>")
-        .arg(Arg::with_name("input").index(1).required(false))
-        .arg(
-            Arg::with_name("file")
-                .help("subdomaingather -f <roots.txt>")
-                .short("f")
-                .long("file"),
-        )
-        .arg(
-            Arg::with_name("domain")
-                .help("subdomaingather -d domain.com")
-                .short("d")
-                .long("domain"),
-        )
-        .arg(
-            Arg::with_name("all_sources")
-                .help("use sources which require an Api key")
-                .short("a")
-                .long("all"),
-        )
-        .arg(
-            Arg::with_name("exclude")
-                .help("Excludes sources from data collection")
-                .short("e")
-                .long("exclude")
-                .multiple(true)
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("subs-only")
-                .help("filter the results to only those which have the same subdomain")
-                .long("subs-only"),
-        )
-        .arg(
-            Arg::with_name("flush")
-                .help(
-                    "Prints results to stdout as they're received. Results will still be filtered, 
-                    but no deduplication will be done",
-                )
-                .long("flush"),
-        )
-        .arg(
-            Arg::with_name("concurrency")
-                .help("The number of domains to fetch data for concurrently")
-                .short("c")
-                .long("concurrency")
-                .default_value("200")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("verbosity")
-                .help(
-                    "different levels of verbosity you can set for debugging, 
-                    values include: debug,info and warn",
-                )
-                .short("v")
-                .long("verbosity")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("timeout")
-                .help(
-                    "connection timeouts can be useful if you don't want to wait
-                    for sources like wayback archive which quite a while. Default is 10 seconds.",
-                )
-                .short("t")
-                .long("timeout")
-                .default_value("15")
-                .takes_value(true),
-        )
+    input::read(reader)
+}
+
+/// Gather subdomains from passive sources
+#[derive(Parser)]
+#[command(
+    name = "sub",
+    version,
+    override_usage = "subdomaingather -d <domain.com>"
+)]
+struct Cli {
+    /// Roots to enumerate, optionally followed by comma separated tags: -d hackerone.com,prod
+    #[arg(short, long, value_name = "DOMAIN", num_args = 1.., conflicts_with = "file")]
+    domain: Vec<String>,
+
+    /// A file of roots, one per line, as json lines or a json array. Roots are read from stdin
+    /// when neither -d nor -f is given
+    #[arg(short, long, value_name = "FILE")]
+    file: Option<PathBuf>,
+
+    /// A domain or a file of roots, for compatibility with `-d` and `-f` no longer taking
+    /// the value from here
+    #[arg(hide = true, conflicts_with_all = ["domain", "file"])]
+    input: Option<String>,
+
+    /// Only queries the roots and sources which failed in a file written by --errors
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["domain", "file", "input"])]
+    retry_from: Option<PathBuf>,
+
+    /// Enumerates example.com from responses bundled with the binary instead of the network,
+    /// to try out output formats, filters and sinks without api keys
+    #[arg(long, conflicts_with_all = ["domain", "file", "input", "retry_from"])]
+    demo: bool,
+
+    /// Writes which sources failed for which roots and why to <FILE> as json, so they can be
+    /// retried with --retry-from
+    #[arg(long, value_name = "FILE")]
+    errors: Option<PathBuf>,
+
+    /// Filter the results to only those which have the same subdomain
+    #[arg(long)]
+    subs_only: bool,
+
+    /// Enumerates the registrable domain of each root, e.g. example.com for
+    /// deep.api.example.com, then keeps only results which are the root or beneath it, or
+    /// only those beneath it with --subs-only. Sources often only index the registrable domain
+    #[arg(long)]
+    auto_root: bool,
+
+    /// Path to the config file, defaults to ~/.config/sub/config.toml
+    #[arg(long)]
+    config: Option<String>,
+
+    /// Reads roots, scope exclusions, sources, API keys, output and notification settings
+    /// from an engagement file. Flags override the file
+    #[arg(long)]
+    engagement: Option<String>,
+
+    /// The number of domains to fetch data for concurrently [default: 200]
+    #[arg(short, long)]
+    concurrency: Option<usize>,
+
+    /// Lowers the concurrency when concurrency x sources would need more open files than
+    /// the process limit allows, rather than only warning about it
+    #[arg(long)]
+    auto_tune: bool,
+
+    /// Raises the soft limit on open files to the hard limit before the run
+    #[arg(long)]
+    raise_fd_limit: bool,
+
+    /// How long a request may take in total, including reading the response, e.g. 30s or 2m.
+    /// Useful if you don't want to wait for sources like wayback archive which take quite a
+    /// while [default: 15s]
+    #[arg(short, long)]
+    timeout: Option<TimeSpan>,
+
+    /// Makes the run reproducible, results are printed in sorted order and the random gaps of
+    /// --polite are derived from the seed. With --flush-interval or --flush-after each batch
+    /// is sorted, and it has no effect on --flush output
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Logs more detail to stderr, repeat for more: -v info, -vv debug, -vvv everything.
+    /// RUST_LOG takes precedence when it's set
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Where runs are recorded and read from by `runs`, defaults to
+    /// ~/.local/share/sub/runs.jsonl
+    #[arg(long, value_name = "FILE")]
+    history: Option<PathBuf>,
+
+    /// Records this run in the history, with urls and anything which looks like a key in its
+    /// arguments redacted
+    #[arg(long)]
+    record_history: bool,
+
+    #[command(flatten)]
+    sources: SourceArgs,
+
+    #[command(flatten)]
+    print: PrintArgs,
+
+    #[command(flatten)]
+    enrich: EnrichArgs,
+
+    #[command(flatten)]
+    memory: MemoryArgs,
+
+    #[command(flatten)]
+    network: NetworkArgs,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Sources")]
+struct SourceArgs {
+    /// Use sources which require an Api key, those whose keys aren't set are skipped
+    #[arg(short, long = "all")]
+    all_sources: bool,
+
+    /// Use a named set of sources and timeouts, one of: fast, thorough or full. Profiles can
+    /// be adjusted in the config file
+    #[arg(short, long)]
+    profile: Option<Profile>,
+
+    /// Excludes sources from data collection
+    #[arg(short, long, num_args = 1..)]
+    exclude: Vec<String>,
+
+    /// Slows sources which scrape websites rather than using an api, crt.sh and VirusTotal,
+    /// to about a request a second with random gaps
+    #[arg(long)]
+    polite: bool,
+
+    /// Doesn't query sources which scrape websites at all, like `api_only` in the config
+    #[arg(long, conflicts_with = "polite")]
+    stealth: bool,
+
+    /// Only queries alternate sources when the preferred one fails or has no key, using the
+    /// built in groups: SecurityTrails,PassiveTotal,AlienVault and Crtsh,CertSpotter
+    #[arg(long)]
+    fallback: bool,
+
+    /// Adds a fallback group as a comma separated list of sources, tried in order. Can be
+    /// given more than once
+    #[arg(long)]
+    fallback_group: Vec<String>,
+
+    /// Stops each source after its first <count> results, usually its first page, and prints
+    /// how many each source found to stderr. Useful to see which sources are fruitful for a
+    /// target before a full run
+    #[arg(long, value_name = "count")]
+    sample: Option<usize>,
+
+    /// Only fetches data seen on or after this date (YYYY-MM-DD) from sources which can
+    /// filter by date
+    #[arg(long, value_name = "date")]
+    since: Option<Date>,
+
+    /// Only fetches data seen on or before this date (YYYY-MM-DD) from sources which can
+    /// filter by date
+    #[arg(long, value_name = "date")]
+    until: Option<Date>,
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Output")]
+struct PrintArgs {
+    /// Where to write results, as [text|jsonl|webhook|roots:]<path or url>. Repeat to write
+    /// to several places at once, `-` is stdout. `roots:<dir>` writes a file per root
+    #[arg(short, long, value_name = "spec")]
+    output: Vec<OutputSpec>,
+
+    /// Prints each result as a json object along with the root it was found for and that
+    /// root's tags
+    #[arg(long)]
+    json: bool,
+
+    /// Prints results to stdout as they're received. Results will still be filtered, but no
+    /// deduplication will be done
+    #[arg(long)]
+    flush: bool,
+
+    /// Prefixes each text line with the root it was found for and a tab, so results from
+    /// different roots can be told apart while they're being flushed
+    #[arg(long)]
+    flush_prefix_root: bool,
+
+    /// Ends each result with a NUL instead of a newline, for piping into `xargs -0`
+    #[arg(short = '0', long)]
+    null: bool,
+
+    /// Writes each text line in a format like '{host},{root},{ips}'. Fields are host, root,
+    /// sources, tags, interesting, ips and anything enrichers add, ips needs --resolve
+    #[arg(long, value_name = "template", conflicts_with = "json")]
+    format_template: Option<Template>,
+
+    /// Prints new deduplicated results every <interval>, e.g. 30s or 5m, so long runs produce
+    /// partial output as they go
+    #[arg(long, value_name = "interval", conflicts_with = "flush")]
+    flush_interval: Option<TimeSpan>,
+
+    /// Prints new deduplicated results once <count> of them are waiting
+    #[arg(long, value_name = "count", conflicts_with = "flush")]
+    flush_after: Option<usize>,
+
+    /// Doesn't print results which are already in <file>, either plain or written with
+    /// --json. Repeat to pass several files
+    #[arg(long, value_name = "file")]
+    known: Vec<PathBuf>,
+
+    /// Only prints results whose names suggest they're worth a closer look, like vpn, jenkins
+    /// or staging hosts. More patterns can be added in the config file
+    #[arg(long)]
+    only_interesting: bool,
+
+    /// Only prints results a passive DNS source saw a record of one of <types> for, e.g.
+    /// a,cname. Results from sources which don't report record types never match
+    #[arg(long, value_name = "types", value_delimiter = ',')]
+    record_types: Vec<RecordType>,
+
+    /// Opt in to sending an anonymous report of which sources returned responses that
+    /// couldn't be parsed to the endpoint in the config file. Only source names and counts
+    /// are sent
+    #[arg(long)]
+    telemetry: bool,
+
+    /// Appends the same anonymous report to a local file instead, nothing is sent
+    #[arg(long, value_name = "FILE")]
+    telemetry_file: Option<PathBuf>,
+
+    /// Prints how many results each source found which no other source did to stderr, to
+    /// help decide which subscriptions are worth keeping
+    #[arg(long)]
+    unique_contribution: bool,
+
+    /// Prints the number of results, memory used, and a summary of the errors sources
+    /// returned to stderr once finished
+    #[arg(long)]
+    stats: bool,
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Enrichment")]
+struct EnrichArgs {
+    /// Resolves each result and adds its addresses to the json output
+    #[arg(long)]
+    resolve: bool,
+
+    /// Requests each result over https or http and adds the status to the json output
+    #[arg(long)]
+    probe: bool,
+
+    /// Checks each result accepts TCP connections on 443 or 80 before probing it, so --probe
+    /// only waits on hosts which are plausibly up. Results which don't are still printed
+    #[arg(long, requires = "probe")]
+    check_reachable: bool,
+
+    /// How many reachability checks run at once, kept apart from --enrich-concurrency as
+    /// they're far cheaper than requests
+    #[arg(long, value_name = "count", default_value_t = 500)]
+    reachable_concurrency: usize,
+
+    /// How long a reachability check waits for each connection
+    #[arg(long, value_name = "duration", default_value = "3s")]
+    reachable_timeout: TimeSpan,
+
+    /// Resolves each result and drops those whose addresses all belong to Cloudflare, Akamai
+    /// or Fastly
+    #[arg(long)]
+    exclude_cdn: bool,
+
+    /// Replaces the built in CDN ranges with those in a file, a provider and range per line
+    /// like `cloudflare 104.16.0.0/13`
+    #[arg(long, value_name = "FILE")]
+    cdn_ranges: Option<PathBuf>,
+
+    /// Resolves each result and only prints those pointing at private, loopback or link local
+    /// addresses
+    #[arg(long, conflicts_with = "no_internal")]
+    only_internal: bool,
+
+    /// Resolves each result and drops those pointing at private, loopback or link local
+    /// addresses
+    #[arg(long)]
+    no_internal: bool,
+
+    /// How many lookups each enricher runs at once
+    #[arg(long, value_name = "count", default_value_t = 50)]
+    enrich_concurrency: usize,
+
+    /// Keeps the addresses results resolve to in <FILE> between runs, so a run soon after
+    /// another reuses its answers rather than resolving every result again
+    #[arg(long, value_name = "FILE")]
+    dns_cache: Option<PathBuf>,
+
+    /// How long resolved addresses are reused for, within a run and through --dns-cache
+    /// [default: 5m]
+    #[arg(long, value_name = "duration")]
+    dns_cache_ttl: Option<TimeSpan>,
+}
+
+impl EnrichArgs {
+    fn dns_cache(&self) -> Result<DnsCache> {
+        let ttl = self
+            .dns_cache_ttl
+            .map_or(dnscache::DEFAULT_TTL, |t| t.duration());
+        match &self.dns_cache {
+            Some(path) => DnsCache::load(path, ttl),
+            None => Ok(DnsCache::new(ttl)),
+        }
+    }
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Memory")]
+struct MemoryArgs {
+    /// The amount of memory the results can use before they're spilled to disk for
+    /// deduplication, e.g. 512MiB or 2GiB. Bare numbers are MiB
+    #[arg(long, value_parser = mebibytes)]
+    max_memory: Option<ByteSize>,
+
+    /// Stop and print the results collected so far once --max-memory is exceeded
+    #[arg(long, requires = "max_memory")]
+    no_spill: bool,
+
+    /// Responses from crt.sh and the Wayback Machine larger than this are written to a
+    /// temporary file and parsed from there. Bare numbers are MiB [default: 32MiB]
+    #[arg(long, value_parser = mebibytes)]
+    spool_threshold: Option<ByteSize>,
+}
+
+#[derive(Args)]
+#[command(next_help_heading = "Network")]
+struct NetworkArgs {
+    /// Checks every enabled source can be reached before starting, and skips those which
+    /// can't
+    #[arg(long)]
+    preflight: bool,
+
+    /// The largest response read from a source, larger responses are abandoned. Bare
+    /// numbers are MiB [default: 256MiB]
+    #[arg(long, value_parser = mebibytes)]
+    max_response_size: Option<ByteSize>,
+
+    /// The most every source together may download per second, e.g. 512KiB. Responses are
+    /// read more slowly once it's reached. Bare numbers are KiB
+    #[arg(long, value_parser = kibibytes)]
+    max_bandwidth: Option<ByteSize>,
+
+    /// Uses HTTP/2 without negotiating it, so requests to the same api share a connection.
+    /// Only works when every enabled source supports HTTP/2
+    #[arg(long)]
+    http2_prior_knowledge: bool,
+
+    /// How long connecting to a source may take, so dead endpoints fail fast without cutting
+    /// off slow responses. Defaults to the timeout
+    #[arg(long, value_name = "duration")]
+    connect_timeout: Option<TimeSpan>,
+
+    /// How long idle connections are kept open, defaults to the timeout
+    #[arg(long)]
+    pool_idle_timeout: Option<TimeSpan>,
+
+    /// The most idle connections kept open to each host
+    #[arg(long)]
+    pool_max_idle: Option<usize>,
+
+    /// Sends TCP keepalive probes every <interval>
+    #[arg(long, value_name = "interval")]
+    tcp_keepalive: Option<TimeSpan>,
+
+    /// Binds outbound connections to a local address
+    #[arg(long, conflicts_with = "interface")]
+    source_ip: Option<IpAddr>,
+
+    /// Binds outbound connections to an address of a network interface
+    #[arg(long)]
+    interface: Option<String>,
+
+    /// Which address family to connect over, `4`, `6` or `auto`. `auto` tries both and falls
+    /// back to the other family when one doesn't connect, so broken AAAA records don't stall
+    /// requests [default: auto]
+    #[arg(long, value_name = "4|6|auto", conflicts_with_all = ["ipv4", "ipv6"])]
+    ip_version: Option<IpVersion>,
+
+    /// Only connects over IPv4, short for `--ip-version 4`
+    #[arg(short = '4', conflicts_with = "ipv6")]
+    ipv4: bool,
+
+    /// Only connects over IPv6, short for `--ip-version 6`
+    #[arg(short = '6')]
+    ipv6: bool,
+
+    /// The TLS implementation to connect with, each needs its cargo feature. Defaults to
+    /// native when it's built in
+    #[arg(long, value_name = "native|rustls")]
+    tls_backend: Option<TlsBackend>,
+
+    /// Trusts the PEM certificates in <path> on top of the backend's roots, e.g. the CA of a
+    /// proxy which intercepts TLS. Can be given more than once
+    #[arg(long, value_name = "path")]
+    ca_cert: Vec<PathBuf>,
+}
+
+impl NetworkArgs {
+    fn ip_version(&self) -> IpVersion {
+        match self.ip_version {
+            Some(version) => version,
+            None if self.ipv4 => IpVersion::V4,
+            None if self.ipv6 => IpVersion::V6,
+            None => IpVersion::Auto,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Inspect the sources the current flags and config would use
+    Sources {
+        #[command(subcommand)]
+        command: SourcesCommand,
+    },
+    /// Prints how often labels, the words they're made of and numeric patterns show up in a
+    /// file of results, to build permutation lists from. Prints json with --json
+    Analyze {
+        /// A file of results, plain or written with --json
+        file: PathBuf,
+
+        /// How many of the most common entries to print for each list
+        #[arg(long, default_value_t = 20)]
+        top: usize,
+    },
+    /// Proposes likely hostnames which weren't found, from a model trained on the labels in
+    /// a file of results. With --resolve only candidates which resolve are printed
+    Generate {
+        /// A file of results, plain or written with --json
+        file: PathBuf,
+
+        /// How many candidates to propose for each root
+        #[arg(long, default_value_t = 100)]
+        count: usize,
+    },
+    /// Builds word lists for brute forcing and permutations from files of results
+    Wordlist {
+        #[command(subcommand)]
+        command: WordlistCommand,
+    },
+    /// Lists the runs recorded in the history, or shows one of them
+    Runs {
+        #[command(subcommand)]
+        command: RunsCommand,
+    },
+    /// Enumerates roots popped from a Redis list and pushes the results to another
+    #[cfg(feature = "worker")]
+    Worker(WorkerArgs),
+}
+
+#[derive(Subcommand)]
+enum SourcesCommand {
+    /// Prints every host the enabled sources would connect to, one per line, so they can be
+    /// added to firewall or proxy allow lists before running
+    Endpoints,
+}
+
+#[derive(Subcommand)]
+enum WordlistCommand {
+    /// Prints a word list from the labels in files of results, most common first, optionally
+    /// merged with a base list
+    Build {
+        /// Files of results, plain or written with --json
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// A word list to add after the words found, one per line. Lines starting with `#`
+        /// are skipped
+        #[arg(long, value_name = "path")]
+        base: Option<PathBuf>,
+
+        /// How many of the most common words found to keep, every base word is kept
+        #[arg(long)]
+        top: Option<usize>,
+    },
+}
+
+#[derive(Subcommand)]
+enum RunsCommand {
+    /// Prints a line per recorded run, oldest first
+    List,
+    /// Prints the arguments, counts and errors of a run
+    Show {
+        /// The id shown by `runs list`
+        id: u64,
+    },
+}
+
+#[cfg(feature = "worker")]
+#[derive(Args)]
+struct WorkerArgs {
+    /// The Redis server to connect to, e.g. redis://127.0.0.1/
+    #[arg(long)]
+    redis: String,
+
+    /// The list to pop roots from
+    #[arg(long, default_value = "sub:roots")]
+    queue: String,
+
+    /// The list to push json results onto
+    #[arg(long, default_value = "sub:results")]
+    output: String,
+
+    /// Stop once the queue has been empty for a few seconds
+    #[arg(long)]
+    exit_when_empty: bool,
+
+    /// Share work with other coordinating workers, each root and source pair is only
+    /// queried by one worker and results are deduplicated centrally
+    #[arg(long)]
+    coordinate: bool,
+
+    /// How long a worker can hold a root and source pair while querying it
+    #[arg(long, default_value = "10m")]
+    lease_ttl: TimeSpan,
+
+    /// How long finished root and source pairs and seen results are remembered
+    #[arg(long, default_value = "1d")]
+    done_ttl: TimeSpan,
 }
@@ -0,0 +1,236 @@
+use crate::enrich::Enricher;
+use crate::error::{Result, SubError};
+use crate::output::Subdomain;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Published ranges of the larger CDNs. Akamai doesn't publish theirs, so only the blocks
+/// they're best known for are listed. Use `CdnRanges::from_path` to refresh them.
+const RANGES: &[(&str, &str)] = &[
+    ("cloudflare", "173.245.48.0/20"),
+    ("cloudflare", "103.21.244.0/22"),
+    ("cloudflare", "103.22.200.0/22"),
+    ("cloudflare", "103.31.4.0/22"),
+    ("cloudflare", "141.101.64.0/18"),
+    ("cloudflare", "108.162.192.0/18"),
+    ("cloudflare", "190.93.240.0/20"),
+    ("cloudflare", "188.114.96.0/20"),
+    ("cloudflare", "197.234.240.0/22"),
+    ("cloudflare", "198.41.128.0/17"),
+    ("cloudflare", "162.158.0.0/15"),
+    ("cloudflare", "104.16.0.0/13"),
+    ("cloudflare", "104.24.0.0/14"),
+    ("cloudflare", "172.64.0.0/13"),
+    ("cloudflare", "131.0.72.0/22"),
+    ("cloudflare", "2400:cb00::/32"),
+    ("cloudflare", "2606:4700::/32"),
+    ("cloudflare", "2803:f800::/32"),
+    ("cloudflare", "2405:b500::/32"),
+    ("cloudflare", "2405:8100::/32"),
+    ("cloudflare", "2a06:98c0::/29"),
+    ("cloudflare", "2c0f:f248::/32"),
+    ("fastly", "23.235.32.0/20"),
+    ("fastly", "43.249.72.0/22"),
+    ("fastly", "103.244.50.0/24"),
+    ("fastly", "103.245.222.0/23"),
+    ("fastly", "103.245.224.0/24"),
+    ("fastly", "104.156.80.0/20"),
+    ("fastly", "140.248.64.0/18"),
+    ("fastly", "140.248.128.0/17"),
+    ("fastly", "146.75.0.0/17"),
+    ("fastly", "151.101.0.0/16"),
+    ("fastly", "157.52.64.0/18"),
+    ("fastly", "167.82.0.0/17"),
+    ("fastly", "172.111.64.0/18"),
+    ("fastly", "185.31.16.0/22"),
+    ("fastly", "199.27.72.0/21"),
+    ("fastly", "199.232.0.0/16"),
+    ("fastly", "2a04:4e40::/32"),
+    ("fastly", "2a04:4e42::/32"),
+    ("akamai", "2.16.0.0/13"),
+    ("akamai", "23.32.0.0/11"),
+    ("akamai", "23.192.0.0/11"),
+    ("akamai", "104.64.0.0/10"),
+    ("akamai", "184.24.0.0/13"),
+];
+
+/// A block of addresses, e.g. `104.16.0.0/13`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = SubError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || SubError::ConfigError(format!("{} isn't a range like 10.0.0.0/8", s));
+        let (addr, prefix) = s.split_once('/').ok_or_else(invalid)?;
+        let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+        let prefix: u8 = prefix.parse().map_err(|_| invalid())?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix > max {
+            return Err(invalid());
+        }
+
+        Ok(Self { addr, prefix })
+    }
+}
+
+/// Address ranges belonging to CDNs, along with whose they are
+#[derive(Debug, Clone)]
+pub struct CdnRanges {
+    ranges: Vec<(String, Cidr)>,
+}
+
+impl Default for CdnRanges {
+    fn default() -> Self {
+        Self {
+            ranges: RANGES
+                .iter()
+                .map(|(name, range)| (name.to_string(), range.parse().unwrap()))
+                .collect(),
+        }
+    }
+}
+
+impl CdnRanges {
+    /// Reads ranges from a file with a provider and range per line, e.g.
+    /// `cloudflare 104.16.0.0/13`, replacing the built in ones. Blank lines and lines
+    /// starting with `#` are skipped.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let contents = fs::read_to_string(path.as_ref()).map_err(|e| {
+            SubError::ConfigError(format!("couldn't read {:?} got {}", path.as_ref(), e))
+        })?;
+
+        let mut ranges = Vec::new();
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                [name, range] => ranges.push((name.to_string(), range.parse()?)),
+                _ => {
+                    return Err(SubError::ConfigError(format!(
+                        "expected a provider and range, got {}",
+                        line
+                    )))
+                }
+            }
+        }
+
+        Ok(Self { ranges })
+    }
+
+    /// The CDN `ip` belongs to, if any
+    pub fn provider(&self, ip: &IpAddr) -> Option<&str> {
+        self.ranges
+            .iter()
+            .find(|(_, range)| range.contains(ip))
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+/// Marks results whose addresses all belong to a CDN with the CDN's name
+#[derive(Default)]
+pub struct Cdn {
+    ranges: CdnRanges,
+}
+
+impl Cdn {
+    pub fn new(ranges: CdnRanges) -> Self {
+        Self { ranges }
+    }
+}
+
+#[async_trait]
+impl Enricher for Cdn {
+    fn name(&self) -> &str {
+        "cdn"
+    }
+
+    async fn enrich(&self, subdomain: &Subdomain) -> Result<Option<Value>> {
+        let addrs: Vec<IpAddr> = match subdomain.enrichments.get("addresses") {
+            Some(Value::Array(addrs)) => addrs
+                .iter()
+                .filter_map(|a| a.as_str().and_then(|a| a.parse().ok()))
+                .collect(),
+            _ => return Ok(None),
+        };
+
+        let mut providers = addrs.iter().map(|a| self.ranges.provider(a));
+        let first = match providers.next() {
+            Some(Some(first)) => first,
+            _ => return Ok(None),
+        };
+
+        if providers.all(|p| p.is_some()) {
+            Ok(Some(json!(first)))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_ranges() {
+        let ranges = CdnRanges::default();
+        assert_eq!(
+            ranges.provider(&"104.16.99.52".parse().unwrap()),
+            Some("cloudflare")
+        );
+        assert_eq!(
+            ranges.provider(&"151.101.1.69".parse().unwrap()),
+            Some("fastly")
+        );
+        assert_eq!(
+            ranges.provider(&"2606:4700::6810:6334".parse().unwrap()),
+            Some("cloudflare")
+        );
+        assert_eq!(ranges.provider(&"8.8.8.8".parse().unwrap()), None);
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+    }
+
+    #[tokio::test]
+    async fn flags_hosts_behind_a_cdn() {
+        let mut subdomain = Subdomain::new("www.hackerone.com");
+        subdomain
+            .enrichments
+            .insert("addresses".into(), json!(["104.16.99.52", "104.16.100.52"]));
+        assert_eq!(
+            Cdn::default().enrich(&subdomain).await.unwrap(),
+            Some(json!("cloudflare"))
+        );
+
+        subdomain
+            .enrichments
+            .insert("addresses".into(), json!(["104.16.99.52", "8.8.8.8"]));
+        assert_eq!(Cdn::default().enrich(&subdomain).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::sources::Source;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// How far a chunked run has got, which can be saved and handed back to
+/// `Runner::run_chunked` to pick up where it left off after a restart.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunState {
+    /// Roots which have been enumerated
+    pub done: Vec<String>,
+    /// Roots still to be enumerated, in the order they'll be run
+    pub remaining: Vec<String>,
+}
+
+impl RunState {
+    /// Creates the state for a fresh run over `roots`
+    pub fn new<I: IntoIterator<Item = String>>(roots: I) -> Self {
+        let roots: BTreeSet<String> = roots.into_iter().collect();
+        Self {
+            done: Vec::new(),
+            remaining: roots.into_iter().collect(),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.remaining.is_empty()
+    }
+
+    /// Marks the next root as done, returning it
+    pub(crate) fn advance(&mut self) -> Option<String> {
+        if self.remaining.is_empty() {
+            return None;
+        }
+
+        let root = self.remaining.remove(0);
+        self.done.push(root.clone());
+        Some(root)
+    }
+}
+
+/// Every unique result found for a single root, along with the state to resume from once the
+/// chunk has been handled.
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    pub root: String,
+    pub results: Vec<String>,
+    /// Sources which returned an error for the root
+    pub failed: Vec<Source>,
+    pub state: RunState,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advances_and_roundtrips() {
+        let mut state = RunState::new(vec!["hackerone.com".into(), "bugcrowd.com".into()]);
+        assert_eq!(state.advance().unwrap(), "bugcrowd.com");
+
+        let saved = serde_json::to_string(&state).unwrap();
+        let mut restored: RunState = serde_json::from_str(&saved).unwrap();
+        assert_eq!(restored, state);
+        assert_eq!(restored.advance().unwrap(), "hackerone.com");
+        assert!(restored.is_finished());
+        assert!(restored.advance().is_none());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::error::SubError;
+use crate::sources::Source;
+use std::fmt;
+use std::str::FromStr;
+
+/// A calendar day, parsed from `YYYY-MM-DD`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    /// How many days `month` of `year` has
+    fn days_in_month(year: u16, month: u8) -> u8 {
+        match month {
+            4 | 6 | 9 | 11 => 30,
+            2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+            2 => 28,
+            _ => 31,
+        }
+    }
+
+    /// The date as `YYYYMMDD`, the timestamp format the Wayback CDX api takes
+    fn compact(&self) -> String {
+        format!("{:04}{:02}{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl FromStr for Date {
+    type Err = SubError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SubError::ConfigError(format!("{} isn't a date like 2020-01-31", s));
+        let parts: Vec<&str> = s.split('-').collect();
+        if parts.len() != 3 {
+            return Err(invalid());
+        }
+
+        let date = Date {
+            year: parts[0].parse().map_err(|_| invalid())?,
+            month: parts[1].parse().map_err(|_| invalid())?,
+            day: parts[2].parse().map_err(|_| invalid())?,
+        };
+        if !(1..=12).contains(&date.month) {
+            return Err(invalid());
+        }
+        if !(1..=Date::days_in_month(date.year, date.month)).contains(&date.day) {
+            return Err(invalid());
+        }
+
+        Ok(date)
+    }
+}
+
+/// Limits historical sources to data seen between two dates, so repeated runs don't download
+/// years of history every time. Only sources with a temporal filter are affected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DateRange {
+    pub since: Option<Date>,
+    pub until: Option<Date>,
+}
+
+impl DateRange {
+    pub fn is_empty(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    /// Whether `source` can filter what it returns by date, the range is ignored by the others
+    pub fn supports(source: Source) -> bool {
+        source == Source::Wayback
+    }
+
+    /// The query parameters which apply the range to `source`, only Wayback takes any
+    pub fn params(&self, source: Source) -> Vec<(String, String)> {
+        let mut params = Vec::new();
+        if source == Source::Wayback {
+            if let Some(since) = self.since {
+                params.push(("from".to_string(), since.compact()));
+            }
+            if let Some(until) = self.until {
+                params.push(("to".to_string(), until.compact()));
+            }
+        }
+
+        params
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dates() {
+        let date = Date::from_str("2020-01-31").unwrap();
+        assert_eq!(date.to_string(), "2020-01-31");
+        assert!(Date::from_str("2020-13-01").is_err());
+        assert!(Date::from_str("2021-02-29").is_err());
+        assert!(Date::from_str("2020-04-31").is_err());
+        assert!(Date::from_str("2020-02-29").is_ok());
+        assert!(Date::from_str("1900-02-29").is_err());
+        assert!(Date::from_str("20200131").is_err());
+    }
+
+    #[test]
+    fn builds_source_params() {
+        let range = DateRange {
+            since: Some(Date::from_str("2020-01-31").unwrap()),
+            until: None,
+        };
+        assert_eq!(
+            range.params(Source::Wayback),
+            vec![("from".to_string(), "20200131".to_string())]
+        );
+        assert!(range.params(Source::Crtsh).is_empty());
+        assert!(range.params(Source::AlienVault).is_empty());
+    }
+}
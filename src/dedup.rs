@@ -0,0 +1,299 @@
+use crate::error::{Result, SubError};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::mem;
+use std::path::PathBuf;
+use tracing::{debug, info};
+
+/// Rough cost of a single slot in the backing `HashSet`, not counting the string contents.
+const SLOT_SIZE: usize = mem::size_of::<String>() + mem::size_of::<u64>();
+
+/// What to do once the result set grows past the memory limit
+pub enum Overflow {
+    /// Write the current results to disk as a sorted run and keep going
+    Spill,
+    /// Stop collecting and return `SubError::MemoryLimit`
+    Abort,
+}
+
+/// Deduplicates results while keeping track of roughly how much memory they use.
+///
+/// When a limit is set and exceeded the in-memory set is either spilled to disk as a sorted
+/// run, or collection is aborted, depending on the chosen `Overflow` behaviour.
+pub struct Dedup {
+    seen: HashSet<String>,
+    bytes: usize,
+    peak: usize,
+    limit: Option<(usize, Overflow)>,
+    spill_dir: PathBuf,
+    runs: Vec<PathBuf>,
+    spilled: u64,
+    sorted: bool,
+}
+
+impl Default for Dedup {
+    fn default() -> Self {
+        Self {
+            seen: HashSet::new(),
+            bytes: 0,
+            peak: 0,
+            limit: None,
+            spill_dir: std::env::temp_dir().join(format!("sub-spill-{}", std::process::id())),
+            runs: Vec::new(),
+            spilled: 0,
+            sorted: false,
+        }
+    }
+}
+
+impl Dedup {
+    /// Creates a `Dedup` which reacts with `overflow` once `limit` bytes are used
+    pub fn with_limit(limit: usize, overflow: Overflow) -> Self {
+        Self {
+            limit: Some((limit, overflow)),
+            ..Self::default()
+        }
+    }
+
+    /// Sets the directory sorted runs are written to when spilling
+    pub fn spill_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.spill_dir = dir.into();
+        self
+    }
+
+    /// Makes `into_results` return the results in sorted order, so output is reproducible
+    pub fn sorted(mut self) -> Self {
+        self.sorted = true;
+        self
+    }
+
+    /// Adds a result to the set, returns `true` if it wasn't already held in memory.
+    ///
+    /// Once results have been spilled to disk duplicates of spilled entries can't be detected
+    /// until `into_results` merges the runs.
+    pub fn insert(&mut self, result: String) -> Result<bool> {
+        let len = result.capacity();
+        if !self.seen.insert(result) {
+            return Ok(false);
+        }
+
+        self.bytes += len;
+        let usage = self.memory_usage();
+        if usage > self.peak {
+            self.peak = usage;
+        }
+
+        if let Some((limit, overflow)) = &self.limit {
+            if usage > *limit {
+                match overflow {
+                    Overflow::Spill => self.spill()?,
+                    Overflow::Abort => return Err(SubError::MemoryLimit(*limit)),
+                }
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Approximate number of bytes currently used by the results held in memory
+    pub fn memory_usage(&self) -> usize {
+        self.bytes + self.seen.capacity() * SLOT_SIZE
+    }
+
+    /// The highest memory usage seen so far
+    pub fn peak_memory(&self) -> usize {
+        self.peak
+    }
+
+    /// Number of bytes written to disk
+    pub fn spilled_bytes(&self) -> u64 {
+        self.spilled
+    }
+
+    /// Number of sorted runs written to disk
+    pub fn spilled_runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Number of results currently held in memory
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty() && self.runs.is_empty()
+    }
+
+    /// Iterates the results currently held in memory, without consuming them
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        self.seen.iter()
+    }
+
+    /// Consumes the `Dedup` returning every unique result, merging any spilled runs.
+    ///
+    /// Results are always sorted once they have been spilled.
+    pub fn into_results(mut self) -> Result<Box<dyn Iterator<Item = Result<String>>>> {
+        if self.runs.is_empty() {
+            let seen = mem::take(&mut self.seen);
+            if self.sorted {
+                let mut sorted: Vec<String> = seen.into_iter().collect();
+                sorted.sort_unstable();
+                return Ok(Box::new(sorted.into_iter().map(Ok)));
+            }
+
+            return Ok(Box::new(seen.into_iter().map(Ok)));
+        }
+
+        if !self.seen.is_empty() {
+            self.spill()?;
+        }
+
+        info!("merging {} spilled runs", self.runs.len());
+        let runs = mem::take(&mut self.runs);
+        Ok(Box::new(Merge::new(runs, self.spill_dir.clone())?))
+    }
+
+    fn spill(&mut self) -> Result<()> {
+        fs::create_dir_all(&self.spill_dir)?;
+        let path = self.spill_dir.join(format!("run-{}", self.runs.len()));
+        debug!("spilling {} results to {:?}", self.seen.len(), &path);
+
+        let mut sorted: Vec<String> = self.seen.drain().collect();
+        sorted.sort_unstable();
+        let mut writer = BufWriter::new(File::create(&path)?);
+        for r in sorted.iter() {
+            writer.write_all(r.as_bytes())?;
+            writer.write_all(b"\n")?;
+            self.spilled += r.len() as u64 + 1;
+        }
+        writer.flush()?;
+
+        self.seen.shrink_to_fit();
+        self.bytes = 0;
+        self.runs.push(path);
+        Ok(())
+    }
+}
+
+impl Drop for Dedup {
+    fn drop(&mut self) {
+        if !self.runs.is_empty() {
+            let _ = fs::remove_dir_all(&self.spill_dir);
+        }
+    }
+}
+
+/// K-way merge over sorted runs which skips duplicates and cleans up the runs once dropped
+struct Merge {
+    runs: Vec<Lines<BufReader<File>>>,
+    heap: BinaryHeap<Reverse<(String, usize)>>,
+    last: Option<String>,
+    dir: PathBuf,
+}
+
+impl Merge {
+    fn new(paths: Vec<PathBuf>, dir: PathBuf) -> Result<Self> {
+        let mut merge = Self {
+            runs: Vec::with_capacity(paths.len()),
+            heap: BinaryHeap::with_capacity(paths.len()),
+            last: None,
+            dir,
+        };
+
+        for (idx, path) in paths.iter().enumerate() {
+            merge.runs.push(BufReader::new(File::open(path)?).lines());
+            merge.advance(idx)?;
+        }
+
+        Ok(merge)
+    }
+
+    fn advance(&mut self, idx: usize) -> Result<()> {
+        if let Some(line) = self.runs[idx].next() {
+            self.heap.push(Reverse((line?, idx)));
+        }
+
+        Ok(())
+    }
+}
+
+impl Iterator for Merge {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(Reverse((line, idx))) = self.heap.pop() {
+            if let Err(e) = self.advance(idx) {
+                return Some(Err(e));
+            }
+
+            if self.last.as_ref() != Some(&line) {
+                self.last = Some(line.clone());
+                return Some(Ok(line));
+            }
+        }
+
+        None
+    }
+}
+
+impl Drop for Merge {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_path;
+    use matches::matches;
+
+    fn spill_dir(name: &str) -> PathBuf {
+        temp_path(&format!("spill-{}", name))
+    }
+
+    #[test]
+    fn dedups_in_memory() {
+        let mut dedup = Dedup::default();
+        assert!(dedup.insert("a.hackerone.com".into()).unwrap());
+        assert!(!dedup.insert("a.hackerone.com".into()).unwrap());
+        assert!(dedup.insert("b.hackerone.com".into()).unwrap());
+        assert_eq!(dedup.len(), 2);
+        assert!(dedup.memory_usage() > 0);
+    }
+
+    #[test]
+    fn spills_and_merges() {
+        let mut dedup = Dedup::with_limit(1, Overflow::Spill).spill_dir(spill_dir("merge"));
+        for r in &["c.h1.com", "a.h1.com", "b.h1.com", "a.h1.com", "c.h1.com"] {
+            dedup.insert(r.to_string()).unwrap();
+        }
+
+        assert!(dedup.spilled_runs() > 1);
+        let results: Vec<String> = dedup.into_results().unwrap().map(|r| r.unwrap()).collect();
+        assert_eq!(results, vec!["a.h1.com", "b.h1.com", "c.h1.com"]);
+        assert!(!spill_dir("merge").exists());
+    }
+
+    #[test]
+    fn sorts_results() {
+        let mut dedup = Dedup::default().sorted();
+        for r in &["c.h1.com", "a.h1.com", "b.h1.com"] {
+            dedup.insert(r.to_string()).unwrap();
+        }
+
+        let results: Vec<String> = dedup.into_results().unwrap().map(|r| r.unwrap()).collect();
+        assert_eq!(results, vec!["a.h1.com", "b.h1.com", "c.h1.com"]);
+    }
+
+    #[test]
+    fn aborts_over_limit() {
+        let mut dedup = Dedup::with_limit(1, Overflow::Abort);
+        assert!(matches!(
+            dedup.insert("a.hackerone.com".into()).err().unwrap(),
+            SubError::MemoryLimit(1)
+        ));
+    }
+}
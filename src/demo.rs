@@ -0,0 +1,72 @@
+use crate::http::Fixtures;
+use crate::sources::Source;
+
+/// The domain demo runs enumerate, reserved for examples so nothing real is looked up
+pub const DOMAIN: &str = "example.com";
+
+/// The domain the bundled responses were captured for, swapped for `DOMAIN`
+const CAPTURED: &str = "hackerone.com";
+
+macro_rules! fixture {
+    ($source:ident, $file:literal) => {
+        (
+            Source::$source,
+            include_bytes!(concat!("../tests/fixtures/", $file)),
+        )
+    };
+}
+
+/// The parser fixtures of every source which doesn't need api keys, built into the binary so
+/// a demo run works wherever it's copied to
+const RESPONSES: &[(Source, &[u8])] = &[
+    fixture!(AlienVault, "alienvault.json"),
+    fixture!(AnubisDB, "anubisdb.json"),
+    fixture!(CertSpotter, "certspotter.json"),
+    fixture!(Crtsh, "crtsh.json"),
+    fixture!(HackerTarget, "hackertarget.txt"),
+    fixture!(Sublister, "sublister.json"),
+    fixture!(ThreatCrowd, "threatcrowd.json"),
+    fixture!(ThreatMiner, "threatminer.json"),
+    fixture!(UrlScan, "urlscan.json"),
+    fixture!(VirusTotal, "virustotal.json"),
+    fixture!(Wayback, "wayback.json"),
+];
+
+/// The sources a demo run queries
+pub fn sources() -> Vec<Source> {
+    RESPONSES.iter().map(|(source, _)| *source).collect()
+}
+
+/// The bundled responses, naming `DOMAIN` in place of the domain they were captured for
+pub fn fixtures() -> Fixtures {
+    let bodies = RESPONSES
+        .iter()
+        .map(|(source, body)| {
+            let body = String::from_utf8_lossy(body).replace(CAPTURED, DOMAIN);
+            (*source, body.into_bytes())
+        })
+        .collect();
+    Fixtures::new(bodies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Runner;
+    use futures::stream::StreamExt;
+    use std::collections::HashSet;
+
+    #[tokio::test]
+    async fn runs_without_the_network() {
+        let runner = Runner::default()
+            .only_sources(&sources())
+            .fixtures(fixtures())
+            .build()
+            .unwrap();
+        let hosts: HashSet<String> = vec![DOMAIN.to_string()].into_iter().collect();
+
+        let results: Vec<String> = runner.run(hosts).await.unwrap().concat().await;
+        assert!(results.iter().any(|r| r == "api.example.com"));
+        assert!(!results.iter().any(|r| r.contains(CAPTURED)));
+    }
+}
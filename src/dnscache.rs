@@ -0,0 +1,167 @@
+use crate::error::{Result, SubError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::lookup_host;
+
+/// How long answers are kept unless configured otherwise, 5 minutes
+pub const DEFAULT_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// The addresses names resolved to, shared by every enricher which resolves results so a
+/// result resolved by one isn't looked up again by the next, and saved between runs with
+/// `save`. The system resolver doesn't say how long records live, so every answer is kept
+/// for the same ttl. Names which don't resolve aren't cached, and names are matched ignoring
+/// case and a trailing dot.
+#[derive(Debug, Clone)]
+pub struct DnsCache {
+    inner: Arc<Mutex<HashMap<String, Entry>>>,
+    ttl: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Entry {
+    addrs: Vec<IpAddr>,
+    /// Seconds since the epoch the answer stops being used at
+    expires: u64,
+}
+
+impl Default for DnsCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL)
+    }
+}
+
+impl DnsCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            inner: Arc::default(),
+            ttl,
+        }
+    }
+
+    /// Reads the answers a previous run saved to `path`, dropping those which have expired.
+    /// A file which doesn't exist yet gives an empty cache.
+    pub fn load<P: AsRef<Path>>(path: P, ttl: Duration) -> Result<Self> {
+        let cache = Self::new(ttl);
+        let contents = match fs::read_to_string(path.as_ref()) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(cache),
+            Err(e) => {
+                return Err(SubError::ConfigError(format!(
+                    "couldn't read {:?} got {}",
+                    path.as_ref(),
+                    e
+                )))
+            }
+        };
+        let mut entries: HashMap<String, Entry> = serde_json::from_str(&contents).map_err(|e| {
+            SubError::ConfigError(format!("couldn't parse {:?} got {}", path.as_ref(), e))
+        })?;
+
+        let now = now();
+        entries.retain(|_, entry| entry.expires > now);
+        *cache.inner.lock().unwrap_or_else(PoisonError::into_inner) = entries;
+        Ok(cache)
+    }
+
+    /// Writes the answers which haven't expired to `path` as json
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let now = now();
+        let entries: HashMap<String, Entry> = self
+            .inner
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .filter(|(_, entry)| entry.expires > now)
+            .map(|(name, entry)| (name.clone(), entry.clone()))
+            .collect();
+
+        let json = serde_json::to_string(&entries).map_err(|e| e.to_string())?;
+        fs::write(path.as_ref(), json)
+            .map_err(|e| format!("tried to write {:?} got {}", path.as_ref(), e))?;
+        Ok(())
+    }
+
+    /// The addresses `name` resolves to, from the cache while the answer is fresh
+    pub async fn lookup(&self, name: &str) -> Result<Vec<IpAddr>> {
+        if let Some(addrs) = self.get(name, now()) {
+            return Ok(addrs);
+        }
+
+        let mut addrs: Vec<IpAddr> = lookup_host((name, 0)).await?.map(|a| a.ip()).collect();
+        addrs.sort();
+        addrs.dedup();
+        if !addrs.is_empty() {
+            self.insert(name, addrs.clone(), now());
+        }
+        Ok(addrs)
+    }
+
+    fn get(&self, name: &str, now: u64) -> Option<Vec<IpAddr>> {
+        let inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner
+            .get(&key(name))
+            .filter(|entry| entry.expires > now)
+            .map(|entry| entry.addrs.clone())
+    }
+
+    fn insert(&self, name: &str, addrs: Vec<IpAddr>, now: u64) {
+        let entry = Entry {
+            addrs,
+            expires: now + self.ttl.as_secs(),
+        };
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner.insert(key(name), entry);
+    }
+}
+
+/// Names are matched the way results are cleaned
+fn key(name: &str) -> String {
+    name.trim().trim_end_matches('.').to_lowercase()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_path;
+
+    #[test]
+    fn expires_answers() {
+        let cache = DnsCache::new(Duration::from_secs(60));
+        let addrs: Vec<IpAddr> = vec!["104.16.99.52".parse().unwrap()];
+        cache.insert("api.hackerone.com", addrs.clone(), 1000);
+
+        assert_eq!(cache.get("api.hackerone.com", 1059), Some(addrs.clone()));
+        assert_eq!(cache.get("API.hackerone.com.", 1059), Some(addrs));
+        assert_eq!(cache.get("api.hackerone.com", 1060), None);
+        assert_eq!(cache.get("www.hackerone.com", 1000), None);
+    }
+
+    #[tokio::test]
+    async fn saves_and_loads_fresh_answers() {
+        let path = temp_path("dns.json");
+        let cache = DnsCache::new(Duration::from_secs(60));
+        let addrs: Vec<IpAddr> = vec!["104.16.99.52".parse().unwrap()];
+        // cached answers are used without a lookup, which would fail for .invalid names
+        cache.insert("api.hackerone.invalid", addrs.clone(), now());
+        cache.insert("old.hackerone.invalid", addrs.clone(), 0);
+        cache.save(&path).unwrap();
+
+        let loaded = DnsCache::load(&path, Duration::from_secs(60)).unwrap();
+        assert_eq!(loaded.lookup("api.hackerone.invalid").await.unwrap(), addrs);
+        assert_eq!(loaded.get("old.hackerone.invalid", 0), None);
+
+        fs::remove_file(&path).unwrap();
+        assert!(DnsCache::load(&path, DEFAULT_TTL).is_ok());
+    }
+}
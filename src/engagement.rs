@@ -0,0 +1,109 @@
+use crate::error::{Result, SubError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Everything needed to rerun an engagement, read from a single toml file.
+///
+/// ```toml
+/// name = "acme"
+/// roots = ["acme.com", "acme.io,prod"]
+/// scope_exclude = ["corp.acme.com"]
+/// profile = "thorough"
+/// exclude_sources = ["Wayback"]
+/// output = "acme.txt"
+/// notify = ["https://hooks.example.com/sub"]
+///
+/// [keys]
+/// SECURITY_TRAILS_KEY = "..."
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Engagement {
+    pub name: Option<String>,
+    /// Roots to enumerate, in the same `root,tag,tag` form as input files
+    pub roots: Vec<String>,
+    /// Hosts which are out of scope, along with anything beneath them
+    pub scope_exclude: Vec<String>,
+    pub profile: Option<String>,
+    pub all_sources: bool,
+    pub exclude_sources: Vec<String>,
+    pub subs_only: bool,
+    /// API keys which take the place of those set in the environment
+    pub keys: HashMap<String, String>,
+    pub output: Option<PathBuf>,
+    pub json: bool,
+    /// Urls which are sent a json summary once the run finishes
+    pub notify: Vec<String>,
+}
+
+/// The summary posted to each notification target
+#[derive(Serialize)]
+pub struct Notification<'a> {
+    pub engagement: Option<&'a str>,
+    pub roots: usize,
+    pub results: usize,
+    pub output: Option<&'a Path>,
+}
+
+impl Engagement {
+    /// Reads the engagement from the file at `path`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let contents = fs::read_to_string(path.as_ref()).map_err(|e| {
+            SubError::ConfigError(format!("couldn't read {:?} got {}", path.as_ref(), e))
+        })?;
+
+        toml::from_str(&contents).map_err(|e| SubError::ConfigError(e.to_string()))
+    }
+
+    /// Posts the summary to every notification target with `client`, failures are only logged
+    pub async fn notify(&self, results: usize, client: &reqwest::Client) {
+        if self.notify.is_empty() {
+            return;
+        }
+
+        let summary = Notification {
+            engagement: self.name.as_deref(),
+            roots: self.roots.len(),
+            results,
+            output: self.output.as_deref(),
+        };
+
+        for url in self.notify.iter() {
+            match client.post(url).json(&summary).send().await {
+                Ok(resp) if resp.status().is_success() => info!("notified {}", url),
+                Ok(resp) => warn!("got status: {} notifying {}", resp.status().as_str(), url),
+                Err(e) => warn!("couldn't notify {} got {}", url, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_engagement() {
+        let engagement: Engagement = toml::from_str(
+            r#"
+            name = "acme"
+            roots = ["acme.com", "acme.io,prod"]
+            scope_exclude = ["corp.acme.com"]
+            output = "acme.txt"
+
+            [keys]
+            C99_KEY = "abc"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(engagement.name.as_deref(), Some("acme"));
+        assert_eq!(engagement.roots.len(), 2);
+        assert_eq!(engagement.keys["C99_KEY"], "abc");
+        assert!(engagement.notify.is_empty());
+        assert!(!engagement.all_sources);
+    }
+}
@@ -0,0 +1,426 @@
+use crate::dnscache::DnsCache;
+use crate::error::{Result, SubError};
+use crate::output::Subdomain;
+use crate::preflight;
+use async_trait::async_trait;
+use futures::future;
+use futures::stream::{self, StreamExt};
+use serde_json::{json, Value};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tracing::debug;
+
+/// Adds information to results after they've been filtered and before they're written.
+///
+/// Whatever `enrich` returns is stored on the result under the enricher's name, and a failure
+/// only means the enrichment is left out.
+#[async_trait]
+pub trait Enricher: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn enrich(&self, subdomain: &Subdomain) -> Result<Option<Value>>;
+}
+
+/// A chain of enrichers, each limited to its own number of concurrent lookups so a slow
+/// enricher doesn't starve the others. Stages run in the order they're added, so enrichers
+/// which read the addresses `Resolve` added, like `Internal` and `Cdn`, go after it.
+#[derive(Default, Clone)]
+pub struct Enrichment {
+    stages: Vec<Stage>,
+    in_flight: usize,
+}
+
+#[derive(Clone)]
+struct Stage {
+    enricher: Arc<dyn Enricher>,
+    permits: Arc<Semaphore>,
+    // later stages are skipped for results this stage finds nothing for
+    gate: bool,
+}
+
+impl Enrichment {
+    /// Adds an enricher which runs `concurrency` lookups at a time
+    pub fn add<E: Enricher + 'static>(&mut self, enricher: E, concurrency: usize) {
+        self.push(enricher, concurrency, false);
+    }
+
+    /// Adds an enricher like `add`, but results it fails for or finds nothing for skip the
+    /// enrichers added after it, so cheap checks can keep expensive stages off dead hosts
+    pub fn gate<E: Enricher + 'static>(&mut self, enricher: E, concurrency: usize) {
+        self.push(enricher, concurrency, true);
+    }
+
+    fn push<E: Enricher + 'static>(&mut self, enricher: E, concurrency: usize, gate: bool) {
+        let concurrency = concurrency.max(1);
+        self.in_flight += concurrency;
+        self.stages.push(Stage {
+            enricher: Arc::new(enricher),
+            permits: Arc::new(Semaphore::new(concurrency)),
+            gate,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Runs every enricher over `batch`, keeping the order of the results
+    pub async fn run(&self, batch: Vec<Subdomain>) -> Vec<Subdomain> {
+        if self.is_empty() {
+            return batch;
+        }
+
+        stream::iter(batch)
+            .map(|mut subdomain| async move {
+                for stage in self.stages.iter() {
+                    let enricher = &stage.enricher;
+                    let _permit = stage.permits.acquire().await;
+                    match enricher.enrich(&subdomain).await {
+                        Ok(Some(v)) => {
+                            subdomain.enrichments.insert(enricher.name().to_string(), v);
+                            continue;
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            debug!("{} failed for {}: {}", enricher.name(), subdomain.name, e)
+                        }
+                    }
+                    if stage.gate {
+                        break;
+                    }
+                }
+                subdomain
+            })
+            .buffered(self.in_flight)
+            .collect()
+            .await
+    }
+}
+
+/// Resolves results to their addresses, results which don't resolve are left alone
+#[derive(Default)]
+pub struct Resolve {
+    cache: DnsCache,
+}
+
+impl Resolve {
+    /// Resolves through `cache`, sharing answers with whatever else holds it
+    pub fn with_cache(cache: DnsCache) -> Self {
+        Self { cache }
+    }
+}
+
+#[async_trait]
+impl Enricher for Resolve {
+    fn name(&self) -> &str {
+        "addresses"
+    }
+
+    async fn enrich(&self, subdomain: &Subdomain) -> Result<Option<Value>> {
+        let addrs: Vec<String> = self
+            .cache
+            .lookup(&subdomain.name)
+            .await?
+            .iter()
+            .map(|ip| ip.to_string())
+            .collect();
+        if addrs.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(json!(addrs)))
+    }
+}
+
+/// Flags results which resolve to private, loopback or link local addresses, listing those
+/// addresses. Leaked internal records are often worth a closer look.
+#[derive(Default)]
+pub struct Internal;
+
+impl Internal {
+    pub fn is_internal(ip: &IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(ip) => {
+                ip.is_private() || ip.is_loopback() || ip.is_link_local() || ip.is_unspecified()
+            }
+            IpAddr::V6(ip) => {
+                let first = ip.segments()[0];
+                ip.is_loopback()
+                    || ip.is_unspecified()
+                    // unique local fc00::/7 and link local fe80::/10
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80
+                    || ip.to_ipv4_mapped().map_or(false, |v4| Self::is_internal(&v4.into()))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Enricher for Internal {
+    fn name(&self) -> &str {
+        "internal"
+    }
+
+    async fn enrich(&self, subdomain: &Subdomain) -> Result<Option<Value>> {
+        let internal: Vec<&str> = match subdomain.enrichments.get("addresses") {
+            Some(Value::Array(addrs)) => addrs
+                .iter()
+                .filter_map(|a| a.as_str())
+                .filter(|a| {
+                    a.parse::<IpAddr>()
+                        .map_or(false, |ip| Self::is_internal(&ip))
+                })
+                .collect(),
+            _ => return Ok(None),
+        };
+
+        if internal.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(json!(internal)))
+    }
+}
+
+/// Opens a TCP connection to each result on the https and http ports, recording those which
+/// accepted. Far cheaper than a request, so gating `Probe` on it saves waiting out request
+/// timeouts for hosts which are gone. Connects to the addresses `Resolve` added when it ran
+/// first rather than resolving the result again.
+pub struct Reachable {
+    ports: Vec<u16>,
+    timeout: Duration,
+    cache: DnsCache,
+    local: Option<IpAddr>,
+}
+
+impl Default for Reachable {
+    fn default() -> Self {
+        Self {
+            ports: vec![443, 80],
+            timeout: Duration::from_secs(3),
+            cache: DnsCache::default(),
+            local: None,
+        }
+    }
+}
+
+impl Reachable {
+    /// Sets how long each connection attempt may take
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Resolves results `Resolve` didn't through `cache`
+    pub fn cache(mut self, cache: DnsCache) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Connects from `addr`, like the run's other connections when they're bound to an
+    /// address. Only addresses of its family are tried.
+    pub fn local_address(mut self, addr: IpAddr) -> Self {
+        self.local = Some(addr);
+        self
+    }
+
+    /// Whether any of `addrs` accepts a connection on `port`
+    async fn accepts(&self, addrs: &[IpAddr], port: u16) -> bool {
+        let family = |addr: &&IpAddr| self.local.map_or(true, |l| l.is_ipv4() == addr.is_ipv4());
+        for addr in addrs.iter().filter(family) {
+            let connect =
+                preflight::connect(SocketAddr::new(*addr, port), self.local, self.timeout);
+            if connect.await.is_ok() {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[async_trait]
+impl Enricher for Reachable {
+    fn name(&self) -> &str {
+        "reachable"
+    }
+
+    async fn enrich(&self, subdomain: &Subdomain) -> Result<Option<Value>> {
+        let addrs: Vec<IpAddr> = match subdomain.enrichments.get("addresses") {
+            Some(Value::Array(addrs)) => addrs
+                .iter()
+                .filter_map(|a| a.as_str().and_then(|a| a.parse().ok()))
+                .collect(),
+            _ => self.cache.lookup(&subdomain.name).await?,
+        };
+
+        let checks = self.ports.iter().map(|port| self.accepts(&addrs, *port));
+        let open: Vec<u16> = self
+            .ports
+            .iter()
+            .zip(future::join_all(checks).await)
+            .filter(|(_, accepted)| *accepted)
+            .map(|(port, _)| *port)
+            .collect();
+        if open.is_empty() {
+            return Err(SubError::Unreachable(
+                subdomain.name.clone(),
+                "no port accepted a connection".into(),
+            ));
+        }
+
+        Ok(Some(json!(open)))
+    }
+}
+
+/// Requests the root of each result over https, falling back to http, and records the status
+/// of whichever answered
+pub struct Probe {
+    client: reqwest::Client,
+}
+
+impl Probe {
+    /// Probes with `client`, usually built from `Runner::client_builder` so probes use the
+    /// run's timeout, bound address and tls settings. Hosts are resolved by the client
+    /// rather than from the `DnsCache`, so one `Resolve` looked up is looked up again: a
+    /// https request sent to a cached address would have its certificate checked against
+    /// the address instead of the host.
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl Enricher for Probe {
+    fn name(&self) -> &str {
+        "http"
+    }
+
+    async fn enrich(&self, subdomain: &Subdomain) -> Result<Option<Value>> {
+        for scheme in ["https", "http"].iter() {
+            let url = format!("{}://{}/", scheme, subdomain.name);
+            if let Ok(resp) = self.client.get(&url).send().await {
+                return Ok(Some(json!({
+                    "url": url,
+                    "status": resp.status().as_u16(),
+                })));
+            }
+        }
+
+        Err(SubError::Unreachable(
+            subdomain.name.clone(),
+            "no response over https or http".into(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Length;
+
+    #[async_trait]
+    impl Enricher for Length {
+        fn name(&self) -> &str {
+            "length"
+        }
+
+        async fn enrich(&self, subdomain: &Subdomain) -> Result<Option<Value>> {
+            Ok(Some(json!(subdomain.name.len())))
+        }
+    }
+
+    struct Failing;
+
+    #[async_trait]
+    impl Enricher for Failing {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        async fn enrich(&self, _: &Subdomain) -> Result<Option<Value>> {
+            Err(SubError::EmptyResults)
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_enrichers_in_order() {
+        let mut enrichment = Enrichment::default();
+        enrichment.add(Length, 2);
+        enrichment.add(Failing, 1);
+
+        let batch = vec![
+            Subdomain::new("a.hackerone.com"),
+            Subdomain::new("hackerone.com"),
+        ];
+        let results = enrichment.run(batch).await;
+        assert_eq!(results[0].name, "a.hackerone.com");
+        assert_eq!(results[0].enrichments["length"], json!(15));
+        assert_eq!(results[1].enrichments["length"], json!(13));
+        assert!(!results[1].enrichments.contains_key("failing"));
+    }
+
+    #[tokio::test]
+    async fn gates_later_enrichers() {
+        let mut enrichment = Enrichment::default();
+        enrichment.gate(Failing, 1);
+        enrichment.add(Length, 1);
+
+        let results = enrichment.run(vec![Subdomain::new("hackerone.com")]).await;
+        assert!(results[0].enrichments.is_empty());
+    }
+
+    #[tokio::test]
+    async fn checks_the_resolved_addresses() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let reachable = Reachable {
+            ports: vec![port],
+            timeout: Duration::from_secs(1),
+            cache: DnsCache::default(),
+        };
+
+        // the name doesn't resolve, so the addresses added must be the ones used
+        let mut subdomain = Subdomain::new("unresolvable.invalid");
+        subdomain
+            .enrichments
+            .insert("addresses".into(), json!(["127.0.0.1"]));
+        assert_eq!(
+            reachable.enrich(&subdomain).await.unwrap(),
+            Some(json!([port]))
+        );
+
+        drop(listener);
+        assert!(reachable.enrich(&subdomain).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn flags_internal_addresses() {
+        let mut subdomain = Subdomain::new("vpn.hackerone.com");
+        subdomain.enrichments.insert(
+            "addresses".into(),
+            json!(["10.1.2.3", "8.8.8.8", "fd00::1", "::ffff:192.168.0.1"]),
+        );
+        assert_eq!(
+            Internal.enrich(&subdomain).await.unwrap(),
+            Some(json!(["10.1.2.3", "fd00::1", "::ffff:192.168.0.1"]))
+        );
+
+        subdomain
+            .enrichments
+            .insert("addresses".into(), json!(["8.8.8.8", "2606:4700::1"]));
+        assert_eq!(Internal.enrich(&subdomain).await.unwrap(), None);
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn resolves_hosts() {
+        let addrs = Resolve::default()
+            .enrich(&Subdomain::new("hackerone.com"))
+            .await
+            .unwrap();
+        assert!(addrs.is_some());
+    }
+}
@@ -1,17 +1,73 @@
+use crate::dates::Date;
+use crate::http::TlsBackend;
 use std::error::Error;
 use std::fmt::{self, Formatter};
 
 pub type Result<T> = std::result::Result<T, SubError>;
 
+/// Problems with a `Runner`'s configuration, found by `Runner::build` before anything runs
+#[derive(Debug, PartialEq)]
+pub enum BuildError {
+    ZeroConcurrency,
+    ZeroTimeout,
+    TimeoutTooLong(u64),
+    ZeroBandwidth,
+    UnknownSources(Vec<String>),
+    NoKeys,
+    InvertedDateRange(Date, Date),
+    TlsUnavailable(TlsBackend),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::ZeroConcurrency => write!(f, "concurrency must be at least 1"),
+            BuildError::ZeroTimeout => write!(f, "the timeout must be at least 1 second"),
+            BuildError::TimeoutTooLong(t) => {
+                write!(f, "a timeout of {} seconds is longer than an hour", t)
+            }
+            BuildError::ZeroBandwidth => write!(f, "the bandwidth ceiling must be above 0"),
+            BuildError::UnknownSources(v) => write!(f, "unknown sources {:?}", v),
+            BuildError::NoKeys => write!(
+                f,
+                "all sources were requested but none of the api keys they need are set"
+            ),
+            BuildError::InvertedDateRange(since, until) => {
+                write!(f, "since {} is after until {}", since, until)
+            }
+            BuildError::TlsUnavailable(backend) => {
+                write!(f, "the {} tls backend wasn't compiled in", backend)
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum SubError {
     SourceError(String),
     AuthError(String),
+    SchemaChanged {
+        source: String,
+        error: String,
+        sample: String,
+    },
     UnsetKeys(Vec<String>),
     ReqwestError(reqwest::Error),
     JoinError(tokio::task::JoinError),
     IoError(std::io::Error),
+    #[cfg(feature = "worker")]
+    RedisError(redis::RedisError),
     Msg(String),
+    ConfigError(String),
+    Build(BuildError),
+    MemoryLimit(usize),
+    QuotaExceeded(String, usize),
+    ResponseTooLarge(String, usize),
+    Unreachable(String, String),
+    /// An output sink couldn't write to its destination, with why
+    SinkError(String, String),
+    /// A source panicked, with the panic's message
+    SourcePanicked(String, String),
     ParseError,
     CrobatError,
     EmptyResults,
@@ -28,16 +84,52 @@ impl fmt::Display for SubError {
                     s
                 )
             }
+            SubError::SchemaChanged {
+                source,
+                error,
+                sample,
+            } => write!(
+                f,
+                "{} returned a response which couldn't be parsed, the api may have changed: {}, \
+                body started with: {}",
+                source, error, sample
+            ),
             SubError::UnsetKeys(v) => write!(f, "error reading environment variables {:?}", v),
             SubError::EmptyResults => write!(f, "returned no results"),
             SubError::CrobatError => {
                 write!(f, "got error when trying to pull results from crobat")
             }
+            SubError::MemoryLimit(l) => {
+                write!(f, "results exceeded the memory limit of {} bytes", l)
+            }
+            SubError::QuotaExceeded(s, n) => {
+                write!(
+                    f,
+                    "{} has used its budget of {} requests for this run",
+                    s, n
+                )
+            }
+            SubError::ResponseTooLarge(s, n) => {
+                write!(
+                    f,
+                    "{} sent a response larger than the limit of {} bytes",
+                    s, n
+                )
+            }
+            SubError::Unreachable(host, reason) => write!(f, "couldn't reach {}: {}", host, reason),
+            SubError::SinkError(dest, reason) => {
+                write!(f, "couldn't write results to {}: {}", dest, reason)
+            }
+            SubError::SourcePanicked(s, msg) => write!(f, "{} panicked: {}", s, msg),
             SubError::ParseError => write!(f, "got error trying to parse cli args"),
             SubError::Msg(s) => write!(f, "got error {}", s),
+            SubError::ConfigError(s) => write!(f, "invalid config: {}", s),
+            SubError::Build(e) => write!(f, "invalid runner config: {}", e),
             SubError::ReqwestError(ref err) => err.fmt(f),
             SubError::JoinError(ref err) => err.fmt(f),
             SubError::IoError(ref err) => err.fmt(f),
+            #[cfg(feature = "worker")]
+            SubError::RedisError(ref err) => err.fmt(f),
         }
     }
 }
@@ -50,6 +142,12 @@ impl From<String> for SubError {
     }
 }
 
+impl From<BuildError> for SubError {
+    fn from(err: BuildError) -> Self {
+        SubError::Build(err)
+    }
+}
+
 impl From<reqwest::Error> for SubError {
     fn from(err: reqwest::Error) -> Self {
         SubError::ReqwestError(err)
@@ -68,6 +166,13 @@ impl From<std::io::Error> for SubError {
     }
 }
 
+#[cfg(feature = "worker")]
+impl From<redis::RedisError> for SubError {
+    fn from(err: redis::RedisError) -> Self {
+        SubError::RedisError(err)
+    }
+}
+
 impl From<std::num::ParseIntError> for SubError {
     fn from(_: std::num::ParseIntError) -> Self {
         SubError::ParseError
@@ -0,0 +1,62 @@
+use crate::error::SubError;
+use crate::sources::Source;
+use serde::Serialize;
+use std::fmt;
+use std::sync::Arc;
+
+/// Something which happened while fetching data from the sources
+#[derive(Debug)]
+pub enum Event {
+    /// A batch of results `source` returned for `host`
+    Results {
+        host: Arc<String>,
+        source: Source,
+        results: Vec<String>,
+    },
+    /// `source` returned an error for `host`
+    Failed {
+        host: Arc<String>,
+        source: Source,
+        error: SubError,
+    },
+    /// Every source has finished with `host`, sent as soon as its last one is done
+    Finished { host: Arc<String> },
+}
+
+/// Why a source wasn't queried during a run, or stopped being queried part way through it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// The api keys it needs aren't set
+    MissingKeys,
+    /// It was excluded by name
+    Excluded,
+    /// Its endpoint couldn't be reached before the run started
+    Unreachable,
+    /// It used up its request budget
+    BudgetExhausted,
+    /// It scrapes a website and the scrape policy doesn't allow it
+    Policy,
+    /// It scrapes a website whose robots.txt disallows it
+    Robots,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::MissingKeys => write!(f, "missing keys"),
+            SkipReason::Excluded => write!(f, "excluded"),
+            SkipReason::Unreachable => write!(f, "unreachable"),
+            SkipReason::BudgetExhausted => write!(f, "budget exhausted"),
+            SkipReason::Policy => write!(f, "disallowed by policy"),
+            SkipReason::Robots => write!(f, "disallowed by robots.txt"),
+        }
+    }
+}
+
+/// A source which didn't give full coverage, and why
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Skipped {
+    pub source: Source,
+    pub reason: SkipReason,
+}
@@ -0,0 +1,79 @@
+use crate::error::{Result, SubError};
+use crate::sources::Source;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// A root a source failed for and why, written by `--errors` so the failed pairs can be
+/// retried with `--retry-from` rather than running every root again
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Failure {
+    pub root: String,
+    pub source: String,
+    pub error: String,
+}
+
+impl Failure {
+    pub fn new<S: Into<String>>(root: S, source: Source, error: &SubError) -> Self {
+        Self {
+            root: root.into(),
+            source: source.to_string(),
+            error: error.to_string(),
+        }
+    }
+}
+
+/// Writes `failures` to `path` as a json array
+pub fn write<P: AsRef<Path>>(path: P, failures: &[Failure]) -> Result<()> {
+    let json = serde_json::to_string_pretty(failures).map_err(|e| e.to_string())?;
+    fs::write(path.as_ref(), json)
+        .map_err(|e| format!("tried to write {:?} got {}", path.as_ref(), e))?;
+    Ok(())
+}
+
+/// Reads the pairs of root and source which failed from a file written by `write`
+pub fn read<P: AsRef<Path>>(path: P) -> Result<Vec<(String, Source)>> {
+    let contents = fs::read_to_string(path.as_ref()).map_err(|e| {
+        SubError::ConfigError(format!("couldn't read {:?} got {}", path.as_ref(), e))
+    })?;
+    let failures: Vec<Failure> = serde_json::from_str(&contents).map_err(|e| {
+        SubError::ConfigError(format!("couldn't parse {:?} got {}", path.as_ref(), e))
+    })?;
+
+    failures
+        .into_iter()
+        .map(|f| match Source::from_name(&f.source) {
+            Some(source) => Ok((f.root, source)),
+            None => Err(SubError::ConfigError(format!(
+                "unknown source {}",
+                f.source
+            ))),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_path;
+
+    #[test]
+    fn writes_and_reads_failures() {
+        let path = temp_path("errors.json");
+        let failures = vec![
+            Failure::new("hackerone.com", Source::Crtsh, &SubError::EmptyResults),
+            Failure::new("google.com", Source::Wayback, &SubError::EmptyResults),
+        ];
+        write(&path, &failures).unwrap();
+
+        assert_eq!(
+            read(&path).unwrap(),
+            vec![
+                ("hackerone.com".to_string(), Source::Crtsh),
+                ("google.com".to_string(), Source::Wayback)
+            ]
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,166 @@
+use crate::error::{Result, SubError};
+use crate::sources::Source;
+use crate::DataSource;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::mpsc::{self, Sender};
+use tracing::{debug, info};
+
+/// Passive DNS providers, the keyed ones are tried first and AlienVault is the free fallback
+pub const PASSIVE_DNS: &[Source] = &[
+    Source::SecurityTrails,
+    Source::PassiveTotal,
+    Source::AlienVault,
+];
+
+/// Certificate transparency logs, which mostly return the same certificates
+pub const CERT_TRANSPARENCY: &[Source] = &[Source::Crtsh, Source::CertSpotter];
+
+/// The built in groups enabled by `Runner::fallbacks`
+pub const GROUPS: &[&[Source]] = &[PASSIVE_DNS, CERT_TRANSPARENCY];
+
+/// Queries a chain of equivalent sources in order, moving on to the next one only when the
+/// current source fails, isn't configured with a key, or returns nothing.
+pub(crate) struct Fallback {
+    chain: Vec<(Source, Arc<dyn DataSource>)>,
+}
+
+impl Fallback {
+    pub(crate) fn new(chain: Vec<(Source, Arc<dyn DataSource>)>) -> Self {
+        Self { chain }
+    }
+}
+
+#[async_trait]
+impl DataSource for Fallback {
+    async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
+        let mut last = Err(SubError::EmptyResults);
+        for (name, source) in self.chain.iter() {
+            let (source_tx, mut source_rx) = mpsc::channel::<Vec<String>>(1);
+            let mut found = 0;
+            let forward = async {
+                while let Some(results) = source_rx.recv().await {
+                    found += results.len();
+                    let _ = tx.send(results).await;
+                }
+            };
+
+            let (res, _) = futures::join!(source.run(Arc::clone(&host), source_tx), forward);
+            // partial results from a source which then failed still count as coverage
+            if found > 0 {
+                return Ok(());
+            }
+
+            match &res {
+                Ok(()) => debug!(
+                    "{} returned nothing for {}, trying the next source",
+                    name, &host
+                ),
+                Err(e) => info!(
+                    "{} failed for {}: {}, trying the next source",
+                    name, &host, e
+                ),
+            }
+            last = res;
+        }
+
+        last
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matches::matches;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Sends `results`, or fails as if its key wasn't set when there are none
+    struct Mock {
+        results: Option<Vec<String>>,
+        calls: AtomicUsize,
+    }
+
+    impl Mock {
+        fn new(results: Option<Vec<String>>) -> Arc<Self> {
+            Arc::new(Self {
+                results,
+                calls: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl DataSource for Mock {
+        async fn run(&self, _: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match &self.results {
+                Some(v) if !v.is_empty() => {
+                    let _ = tx.send(v.clone()).await;
+                    Ok(())
+                }
+                Some(_) => Ok(()),
+                None => Err(SubError::UnsetKeys(vec!["KEY".into()])),
+            }
+        }
+    }
+
+    async fn collect(fallback: Fallback) -> (Result<()>, Vec<String>) {
+        let (tx, mut rx) = mpsc::channel(10);
+        let res = fallback.run(Arc::new("hackerone.com".into()), tx).await;
+        let mut results = Vec::new();
+        while let Some(v) = rx.recv().await {
+            results.extend(v);
+        }
+
+        (res, results)
+    }
+
+    #[tokio::test]
+    async fn skips_alternates_when_primary_succeeds() {
+        let primary = Mock::new(Some(vec!["a.hackerone.com".into()]));
+        let alternate = Mock::new(Some(vec!["b.hackerone.com".into()]));
+        let fallback = Fallback::new(vec![
+            (
+                Source::SecurityTrails,
+                primary.clone() as Arc<dyn DataSource>,
+            ),
+            (Source::AlienVault, alternate.clone() as Arc<dyn DataSource>),
+        ]);
+
+        let (res, results) = collect(fallback).await;
+        assert!(res.is_ok());
+        assert_eq!(results, vec!["a.hackerone.com"]);
+        assert_eq!(alternate.calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn falls_back_on_error_or_no_results() {
+        let unkeyed = Mock::new(None);
+        let empty = Mock::new(Some(vec![]));
+        let alternate = Mock::new(Some(vec!["b.hackerone.com".into()]));
+        let fallback = Fallback::new(vec![
+            (Source::SecurityTrails, unkeyed as Arc<dyn DataSource>),
+            (Source::PassiveTotal, empty as Arc<dyn DataSource>),
+            (Source::AlienVault, alternate as Arc<dyn DataSource>),
+        ]);
+
+        let (res, results) = collect(fallback).await;
+        assert!(res.is_ok());
+        assert_eq!(results, vec!["b.hackerone.com"]);
+    }
+
+    #[tokio::test]
+    async fn returns_last_error_when_every_source_fails() {
+        let fallback = Fallback::new(vec![
+            (
+                Source::SecurityTrails,
+                Mock::new(None) as Arc<dyn DataSource>,
+            ),
+            (Source::PassiveTotal, Mock::new(None) as Arc<dyn DataSource>),
+        ]);
+
+        let (res, results) = collect(fallback).await;
+        assert!(matches!(res.err().unwrap(), SubError::UnsetKeys(_)));
+        assert!(results.is_empty());
+    }
+}
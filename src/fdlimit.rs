@@ -0,0 +1,162 @@
+use crate::error::Result;
+use std::fmt;
+use std::io;
+use tracing::{info, warn};
+
+/// Files kept free for everything besides source connections, like stdio, outputs, spooled
+/// bodies and dns lookups
+pub const RESERVED: u64 = 64;
+
+/// macOS refuses soft limits above this however high the hard limit is
+#[cfg(target_os = "macos")]
+const CEILING: u64 = 10240;
+#[cfg(not(target_os = "macos"))]
+const CEILING: u64 = u64::MAX;
+
+/// The process limit on open files, `RLIMIT_NOFILE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FdLimit {
+    pub soft: u64,
+    pub hard: u64,
+}
+
+impl FdLimit {
+    /// Reads the limit, `None` on platforms which don't have one
+    pub fn current() -> Option<Self> {
+        get()
+    }
+
+    /// Raises the soft limit as far as the hard limit allows, returning the new limit. A soft
+    /// limit which is already there is left alone.
+    pub fn raise(self) -> Result<Self> {
+        let soft = self.hard.min(CEILING);
+        if soft <= self.soft {
+            return Ok(self);
+        }
+
+        set(soft, self.hard)?;
+        info!(from = self.soft, to = soft, "raised the open file limit");
+        Ok(Self { soft, ..self })
+    }
+
+    /// How many files are left for source connections
+    pub fn available(&self) -> u64 {
+        self.soft.saturating_sub(RESERVED)
+    }
+
+    /// Checks a run which may open `open` connections fits within the limit
+    pub fn check(&self, open: u64) -> Option<Shortfall> {
+        if open <= self.available() {
+            return None;
+        }
+
+        let shortfall = Shortfall {
+            needed: open + RESERVED,
+            soft: self.soft,
+            hard: self.hard,
+        };
+        warn!(
+            needed = shortfall.needed,
+            soft = shortfall.soft,
+            hard = shortfall.hard,
+            "the run may open more files than the limit allows"
+        );
+        Some(shortfall)
+    }
+}
+
+/// A run which may open more files than the limit allows, failing requests with "too many
+/// open files" part way through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shortfall {
+    /// The files the run may open, connections plus `RESERVED`
+    pub needed: u64,
+    pub soft: u64,
+    pub hard: u64,
+}
+
+impl fmt::Display for Shortfall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "concurrency x sources may need {} open files but the limit is {}",
+            self.needed, self.soft
+        )?;
+        if self.hard > self.soft {
+            write!(f, ", --raise-fd-limit can take it up to {}", self.hard)?;
+        }
+        write!(f, ", lower -c or use --auto-tune")
+    }
+}
+
+#[cfg(unix)]
+fn get() -> Option<FdLimit> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // only writes to the struct it's given
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        return None;
+    }
+    Some(FdLimit {
+        soft: limit.rlim_cur as u64,
+        hard: limit.rlim_max as u64,
+    })
+}
+
+#[cfg(unix)]
+fn set(soft: u64, hard: u64) -> io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: soft as libc::rlim_t,
+        rlim_max: hard as libc::rlim_t,
+    };
+    // only reads the struct it's given
+    if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &limit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn get() -> Option<FdLimit> {
+    None
+}
+
+#[cfg(not(unix))]
+fn set(_: u64, _: u64) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "open file limits aren't supported on this platform",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_runs_over_the_limit() {
+        let limit = FdLimit {
+            soft: 1024,
+            hard: 4096,
+        };
+        assert_eq!(limit.check(200 * 4), None);
+
+        let shortfall = limit.check(200 * 20).unwrap();
+        assert_eq!(shortfall.needed, 4000 + RESERVED);
+        assert!(shortfall.to_string().contains("up to 4096"));
+
+        let limit = FdLimit { soft: 32, hard: 32 };
+        assert_eq!(limit.available(), 0);
+        let message = limit.check(1).unwrap().to_string();
+        assert!(!message.contains("--raise-fd-limit"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reads_the_current_limit() {
+        let limit = FdLimit::current().unwrap();
+        assert!(limit.soft <= limit.hard);
+    }
+}
@@ -0,0 +1,53 @@
+use crate::http::Body;
+use crate::postprocessor::{CleanExt, PostProcessor};
+use crate::sources::*;
+use crate::IntoSubdomain;
+use std::sync::Arc;
+
+/// Feeds `body` to the parser of the source `selector` picks, for the targets in `fuzz/`
+/// which can't reach the crate private parsers. Errors are expected, panics aren't.
+pub fn parse(selector: u8, body: &[u8]) -> Vec<String> {
+    let source = Source::ALL[selector as usize % Source::ALL.len()];
+    let parsed = match source {
+        Source::AlienVault => alienvault::parse(body),
+        Source::AnubisDB => anubisdb::parse(body),
+        Source::BinaryEdge => binaryedge::parse(body).map(|r| r.subdomains()),
+        Source::C99 => c99::parse(body),
+        Source::CertSpotter => certspotter::parse(body),
+        Source::Chaos => chaos::parse(body),
+        Source::Crtsh => crtsh::parse(&Body::from_bytes(Some(source), body.to_vec())),
+        Source::Facebook => facebook::parse(body),
+        Source::HackerTarget => Ok(hackertarget::parse(&String::from_utf8_lossy(body))),
+        Source::Intelx => intelx::parse(body),
+        Source::PassiveTotal => passivetotal::parse(body),
+        Source::SecurityTrails => securitytrails::parse(Arc::new("hackerone.com".into()), body),
+        // results come from crobat rather than a body
+        Source::SonarSearch => Ok(Vec::new()),
+        Source::Spyse => spyse::parse(body),
+        Source::Sublister => sublister::parse(body),
+        Source::ThreatCrowd => threatcrowd::parse(body),
+        Source::ThreatMiner => threatminer::parse(body),
+        Source::UrlScan => urlscan::parse(body),
+        Source::VirusTotal => virustotal::parse(body),
+        Source::Wayback => wayback::parse(&Body::from_bytes(Some(source), body.to_vec())),
+    };
+
+    parsed.unwrap_or_default()
+}
+
+/// Runs `results` through a `PostProcessor` for `roots`, both as a whole and against each
+/// root in turn, returning everything which was kept.
+pub fn clean(roots: Vec<String>, results: Vec<String>, subs_only: bool) -> Vec<String> {
+    let mut cleaner = PostProcessor::default();
+    if subs_only {
+        cleaner.any_subdomain(roots.clone());
+    } else {
+        cleaner.any_root(roots.clone());
+    }
+
+    let mut kept: Vec<String> = results.iter().clean(&cleaner).collect();
+    for root in roots.iter() {
+        kept.extend(results.iter().clean_for(&cleaner, root));
+    }
+    kept
+}
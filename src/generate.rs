@@ -0,0 +1,284 @@
+use addr::DomainName;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Generated labels are made of at most this many words
+const MAX_WORDS: usize = 3;
+/// How much of a word's probability comes from the word before it, the rest comes from how
+/// common the word is overall so words can be combined in ways which weren't seen
+const BIGRAM_WEIGHT: f64 = 0.7;
+/// Stands in for a run of digits while training, e.g. `web01` is learnt as `web{n}`
+const NUMBER: &str = "{n}";
+/// Marks the start and end of a label
+const START: &str = "^";
+const END: &str = "$";
+
+/// A markov chain over the words labels are made of, trained on the labels found directly
+/// below a root, used to propose names which look like the ones the target already uses.
+///
+/// Labels are split into words on `-` and runs of digits are generalised, so after seeing
+/// `web1` and `web2` the model proposes `web3`, and after `dev-api` and `staging` it proposes
+/// `staging-api`. Candidates are found with a beam search rather than sampling, so the same
+/// input always gives the same candidates.
+#[derive(Debug, Default)]
+pub struct Model {
+    bigrams: HashMap<String, BTreeMap<String, usize>>,
+    unigrams: BTreeMap<String, usize>,
+    // the digit runs seen for each word containing one, kept as strings to preserve padding
+    numbers: HashMap<String, BTreeSet<String>>,
+    seen: HashSet<String>,
+}
+
+impl Model {
+    /// Trains a model on `labels`, e.g. `api` and `dev` for `api.hackerone.com` and
+    /// `dev.hackerone.com`
+    pub fn train<I, S>(labels: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut model = Self::default();
+        for label in labels {
+            let label = label.as_ref().to_lowercase();
+            if label.is_empty() || !model.seen.insert(label.clone()) {
+                continue;
+            }
+
+            let mut words: Vec<String> = label
+                .split('-')
+                .filter(|w| !w.is_empty())
+                .map(|w| model.generalise(w))
+                .collect();
+            words.push(END.to_string());
+
+            let mut prev = START.to_string();
+            for word in words {
+                *model
+                    .bigrams
+                    .entry(prev)
+                    .or_default()
+                    .entry(word.clone())
+                    .or_insert(0) += 1;
+                *model.unigrams.entry(word.clone()).or_insert(0) += 1;
+                prev = word;
+            }
+        }
+
+        model
+    }
+
+    /// The `n` most likely labels the model produces which it wasn't trained on
+    pub fn candidates(&self, n: usize) -> Vec<String> {
+        let width = (n * 4).max(64);
+        let total: usize = self.unigrams.values().sum();
+        let mut beam: Vec<(Vec<&str>, f64)> = vec![(Vec::new(), 0.0)];
+        let mut finished: Vec<(String, f64)> = Vec::new();
+
+        for _ in 0..=MAX_WORDS {
+            let mut next = Vec::new();
+            for (words, logp) in beam.iter() {
+                let prev = words.last().copied().unwrap_or(START);
+                let following = self.bigrams.get(prev);
+                let following_total: usize = following.map_or(0, |f| f.values().sum());
+
+                for (word, count) in self.unigrams.iter() {
+                    if word != END && words.contains(&word.as_str()) {
+                        continue;
+                    }
+
+                    let bigram = following
+                        .and_then(|f| f.get(word))
+                        .map_or(0.0, |c| *c as f64 / following_total as f64);
+                    let unigram = *count as f64 / total as f64;
+                    let p = BIGRAM_WEIGHT * bigram + (1.0 - BIGRAM_WEIGHT) * unigram;
+                    let logp = logp + p.ln();
+
+                    if word == END {
+                        if !words.is_empty() {
+                            finished.push((words.join("-"), logp));
+                        }
+                    } else if words.len() < MAX_WORDS {
+                        let mut words = words.clone();
+                        words.push(word);
+                        next.push((words, logp));
+                    }
+                }
+            }
+
+            if next.is_empty() {
+                break;
+            }
+            next.sort_by(rank);
+            next.truncate(width);
+            beam = next;
+        }
+
+        finished.sort_by(rank);
+        let mut candidates = Vec::new();
+        let mut proposed = HashSet::new();
+        for (pattern, _) in finished.iter() {
+            for label in self.expand(pattern) {
+                if !self.seen.contains(&label) && proposed.insert(label.clone()) {
+                    candidates.push(label);
+                }
+            }
+            if candidates.len() >= n {
+                break;
+            }
+        }
+
+        candidates.truncate(n);
+        candidates
+    }
+
+    /// Replaces runs of digits in `word` with `{n}`, remembering the digits
+    fn generalise(&mut self, word: &str) -> String {
+        let mut pattern = String::with_capacity(word.len());
+        let mut numbers = Vec::new();
+        let mut digits = String::new();
+        for c in word.chars() {
+            if c.is_ascii_digit() {
+                digits.push(c);
+                continue;
+            }
+
+            if !digits.is_empty() {
+                pattern.push_str(NUMBER);
+                numbers.push(std::mem::take(&mut digits));
+            }
+            pattern.push(c);
+        }
+        if !digits.is_empty() {
+            pattern.push_str(NUMBER);
+            numbers.push(digits);
+        }
+
+        if !numbers.is_empty() {
+            self.numbers
+                .entry(pattern.clone())
+                .or_default()
+                .extend(numbers);
+        }
+        pattern
+    }
+
+    /// Fills in a generated pattern's numbers with those seen for its words, plus the next
+    /// one after the highest. Patterns with several numbers in a word aren't expanded.
+    fn expand(&self, pattern: &str) -> Vec<String> {
+        let mut labels = vec![String::new()];
+        for (i, word) in pattern.split('-').enumerate() {
+            let fills: Vec<String> = match word.matches(NUMBER).count() {
+                0 => vec![word.to_string()],
+                1 => self
+                    .numbers(word)
+                    .into_iter()
+                    .map(|n| word.replace(NUMBER, &n))
+                    .collect(),
+                _ => return Vec::new(),
+            };
+
+            labels = labels
+                .iter()
+                .flat_map(|l| {
+                    fills.iter().map(move |f| match i {
+                        0 => f.clone(),
+                        _ => format!("{}-{}", l, f),
+                    })
+                })
+                .collect();
+        }
+
+        labels
+    }
+
+    /// The numbers seen for `word`, in order, followed by the one after the highest
+    fn numbers(&self, word: &str) -> Vec<String> {
+        let seen = match self.numbers.get(word) {
+            Some(seen) => seen,
+            None => return Vec::new(),
+        };
+
+        let mut numbers: Vec<(u64, String)> = seen
+            .iter()
+            .filter_map(|n| n.parse().ok().map(|v| (v, n.clone())))
+            .collect();
+        numbers.sort();
+        if let Some((max, digits)) = numbers.last().cloned() {
+            if let Some(next) = max.checked_add(1) {
+                numbers.push((next, format!("{:0width$}", next, width = digits.len())));
+            }
+        }
+
+        numbers.into_iter().map(|(_, n)| n).collect()
+    }
+}
+
+/// Proposes up to `n` new hostnames for each root found in `hosts`, trained on the labels
+/// directly below that root.
+pub fn candidates<I, S>(hosts: I, n: usize) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut labels: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for host in hosts {
+        let host = host.as_ref().trim().trim_end_matches('.').to_lowercase();
+        let root = match host.parse::<DomainName>() {
+            Ok(d) => d.root().to_string(),
+            Err(_) => continue,
+        };
+
+        let label = host
+            .strip_suffix(&root)
+            .and_then(|s| s.trim_end_matches('.').rsplit('.').next())
+            .filter(|l| !l.is_empty())
+            .map(|l| l.to_string());
+        if let Some(label) = label {
+            labels.entry(root).or_default().push(label);
+        }
+    }
+
+    labels
+        .into_iter()
+        .flat_map(|(root, labels)| {
+            Model::train(labels)
+                .candidates(n)
+                .into_iter()
+                .map(move |l| format!("{}.{}", l, root))
+        })
+        .collect()
+}
+
+/// Orders from most to least likely, ties alphabetically so results are stable
+fn rank<T: Ord>(a: &(T, f64), b: &(T, f64)) -> std::cmp::Ordering {
+    b.1.partial_cmp(&a.1)
+        .unwrap_or(std::cmp::Ordering::Equal)
+        .then_with(|| a.0.cmp(&b.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proposes_unseen_labels() {
+        let model = Model::train(vec!["web1", "web2", "dev-api", "staging", "api"]);
+        let candidates = model.candidates(20);
+        assert!(candidates.iter().all(|c| !model.seen.contains(c)));
+        assert!(candidates.contains(&"web3".to_string()));
+        assert!(candidates.contains(&"staging-api".to_string()));
+    }
+
+    #[test]
+    fn keeps_padding() {
+        let model = Model::train(vec!["node01", "node09"]);
+        assert_eq!(model.candidates(1), vec!["node10"]);
+    }
+
+    #[test]
+    fn generates_per_root() {
+        let hosts = vec!["dev1.hackerone.com", "dev2.hackerone.com", "hackerone.com"];
+        let candidates = candidates(hosts, 3);
+        assert!(!candidates.is_empty());
+        assert!(candidates.iter().all(|c| c.ends_with(".hackerone.com")));
+    }
+}
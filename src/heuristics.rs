@@ -0,0 +1,100 @@
+/// Words which often show up in the names of hosts worth a closer look
+pub const PATTERNS: &[&str] = &[
+    "admin",
+    "auth",
+    "backup",
+    "backups",
+    "bak",
+    "ci",
+    "confluence",
+    "corp",
+    "dev",
+    "git",
+    "gitlab",
+    "grafana",
+    "internal",
+    "intranet",
+    "jenkins",
+    "jira",
+    "kibana",
+    "sso",
+    "stage",
+    "staging",
+    "stg",
+    "test",
+    "uat",
+    "vault",
+    "vpn",
+];
+
+/// Flags "interesting" hostnames by looking for known words in their labels.
+///
+/// Labels are split on anything which isn't a letter, so `jenkins-01.ci.example.com` matches
+/// `jenkins` and `ci`, while `digital.example.com` doesn't match `git`.
+#[derive(Debug, Clone)]
+pub struct Heuristics {
+    patterns: Vec<String>,
+}
+
+impl Default for Heuristics {
+    fn default() -> Self {
+        Self {
+            patterns: PATTERNS.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+}
+
+impl Heuristics {
+    /// Adds patterns on top of the built in ones
+    pub fn extend<I: IntoIterator<Item = String>>(&mut self, patterns: I) {
+        for p in patterns {
+            let p = p.to_lowercase();
+            if !self.patterns.contains(&p) {
+                self.patterns.push(p);
+            }
+        }
+    }
+
+    /// Returns the patterns `host` matched, in the order they appear in the host
+    pub fn matches<'a>(&'a self, host: &str) -> Vec<&'a str> {
+        let host = host.to_lowercase();
+        let mut matched: Vec<&str> = Vec::new();
+        for word in host.split(|c: char| !c.is_ascii_alphabetic()) {
+            if let Some(p) = self.patterns.iter().find(|p| p.as_str() == word) {
+                if !matched.contains(&p.as_str()) {
+                    matched.push(p);
+                }
+            }
+        }
+
+        matched
+    }
+
+    pub fn is_interesting(&self, host: &str) -> bool {
+        !self.matches(host).is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_interesting_hosts() {
+        let h = Heuristics::default();
+        assert_eq!(
+            h.matches("jenkins-01.ci.hackerone.com"),
+            vec!["jenkins", "ci"]
+        );
+        assert_eq!(h.matches("VPN.hackerone.com"), vec!["vpn"]);
+        assert!(!h.is_interesting("digital.hackerone.com"));
+        assert!(!h.is_interesting("www.hackerone.com"));
+    }
+
+    #[test]
+    fn extends_patterns() {
+        let mut h = Heuristics::default();
+        h.extend(vec!["Payroll".to_string()]);
+        assert_eq!(h.matches("payroll.hackerone.com"), vec!["payroll"]);
+    }
+}
@@ -0,0 +1,193 @@
+use crate::error::Result;
+use crate::http;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::debug;
+use url::Url;
+
+/// What a single run was asked to do and how it went
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// When the run started, in milliseconds since the unix epoch
+    pub id: u64,
+    pub args: Vec<String>,
+    pub duration_ms: u64,
+    pub roots: usize,
+    pub results: usize,
+    pub failures: usize,
+    /// A line per source which failed, with how often it failed and its first error
+    pub errors: Vec<String>,
+}
+
+impl RunRecord {
+    /// Starts a record for a run which began at `started`, with anything in `args` which
+    /// could be secret redacted by `redact_arg`
+    pub fn new(started: SystemTime, args: Vec<String>) -> Self {
+        Self {
+            id: started
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64),
+            args: args.iter().map(|a| redact_arg(a)).collect(),
+            ..Self::default()
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.duration_ms)
+    }
+}
+
+/// Redacts what could be secret in a command line argument: urls keep only their scheme,
+/// host and port, dropping credentials and tokens in their userinfo, path or query, and runs
+/// of characters which look like api keys are replaced.
+pub fn redact_arg(arg: &str) -> String {
+    let scheme = |c: char| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.';
+    match arg.find("://") {
+        Some(i) => {
+            let start = arg[..i].rfind(|c| !scheme(c)).map_or(0, |j| j + 1);
+            format!(
+                "{}{}",
+                http::redact_tokens(&arg[..start]),
+                redact_url(&arg[start..])
+            )
+        }
+        None => http::redact_tokens(arg),
+    }
+}
+
+fn redact_url(url: &str) -> String {
+    let url = match Url::parse(url) {
+        Ok(url) => url,
+        Err(_) => return "[redacted]".into(),
+    };
+
+    let mut kept = format!("{}://{}", url.scheme(), url.host_str().unwrap_or_default());
+    if let Some(port) = url.port() {
+        kept.push_str(&format!(":{}", port));
+    }
+    let dropped = !url.username().is_empty()
+        || url.password().is_some()
+        || url.path() != "/"
+        || url.query().is_some()
+        || url.fragment().is_some();
+    if dropped {
+        kept.push_str("/[redacted]");
+    }
+    kept
+}
+
+/// An append only log of runs, one json object per line, so teams can audit what was
+/// enumerated when and with which arguments.
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// `$XDG_DATA_HOME/sub/runs.jsonl`, or `~/.local/share/sub/runs.jsonl`
+    pub fn default_path() -> Option<PathBuf> {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".local").join("share")))
+            .map(|dir| dir.join("sub").join("runs.jsonl"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends a run to the log
+    pub fn record(&self, run: &RunRecord) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let line = serde_json::to_string(run).map_err(|e| e.to_string())?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+
+    /// Every recorded run, oldest first. Lines which can't be parsed are skipped
+    pub fn runs(&self) -> Result<Vec<RunRecord>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let runs = fs::read_to_string(&self.path)?
+            .lines()
+            .filter_map(|l| match serde_json::from_str(l) {
+                Ok(run) => Some(run),
+                Err(e) => {
+                    debug!("skipping unreadable run in {:?}: {}", &self.path, e);
+                    None
+                }
+            })
+            .collect();
+        Ok(runs)
+    }
+
+    pub fn get(&self, id: u64) -> Result<Option<RunRecord>> {
+        Ok(self.runs()?.into_iter().find(|r| r.id == id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_path;
+
+    #[test]
+    fn records_and_reads_runs() {
+        let path = temp_path("history");
+        let history = History::new(path.join("runs.jsonl"));
+
+        let mut run = RunRecord::new(SystemTime::now(), vec!["-d".into(), "hackerone.com".into()]);
+        run.results = 10;
+        history.record(&run).unwrap();
+        history
+            .record(&RunRecord::new(UNIX_EPOCH, Vec::new()))
+            .unwrap();
+
+        let runs = history.runs().unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(history.get(run.id).unwrap(), Some(run));
+        assert!(history.get(1).unwrap().is_none());
+
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn redacts_secrets_in_args() {
+        let args = vec![
+            "-d".to_string(),
+            "hackerone.com".to_string(),
+            "-o".to_string(),
+            "webhook:https://user:pw@hooks.example.com/T0/B0/abc?token=1".to_string(),
+            "--telemetry-file=https://example.com".to_string(),
+            "a1b2c3d4e5f6a7b8c9d0e1f2a3b4".to_string(),
+        ];
+        let run = RunRecord::new(UNIX_EPOCH, args);
+        assert_eq!(
+            run.args,
+            vec![
+                "-d",
+                "hackerone.com",
+                "-o",
+                "webhook:https://hooks.example.com/[redacted]",
+                "--telemetry-file=https://example.com",
+                "[redacted]",
+            ]
+        );
+    }
+}
@@ -0,0 +1,1018 @@
+use crate::error::{Result, SubError};
+use crate::quota::Quotas;
+use crate::records::Records;
+use crate::sources::Source;
+use bytes::Bytes;
+use dotenv::dotenv;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fmt::{self, Display};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+use tokio::time;
+use tracing::debug;
+use url::Url;
+
+/// How many bytes of a body are kept when it fails to parse
+const SAMPLE_SIZE: usize = 256;
+/// Runs of token characters at least this long are treated as credentials and redacted
+const SECRET_LEN: usize = 24;
+/// The largest body read from a source unless configured otherwise, 256 MiB
+pub const MAX_RESPONSE_SIZE: usize = 256 * 1024 * 1024;
+/// Bodies which grow past this are written to a temporary file rather than kept in memory
+/// by `Response::body`, 32 MiB
+pub const SPOOL_THRESHOLD: usize = 32 * 1024 * 1024;
+
+/// Numbers spooled bodies so concurrent requests don't share a file
+static SPOOLED: AtomicUsize = AtomicUsize::new(0);
+
+/// Which address family connections are made over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpVersion {
+    /// Tries both families, falling back to the other one if the first doesn't connect
+    /// quickly
+    Auto,
+    V4,
+    V6,
+}
+
+impl Default for IpVersion {
+    fn default() -> Self {
+        IpVersion::Auto
+    }
+}
+
+impl IpVersion {
+    /// The unspecified address of the family, binding to it only connects over that family
+    pub fn unspecified(&self) -> Option<IpAddr> {
+        match self {
+            IpVersion::Auto => None,
+            IpVersion::V4 => Some(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            IpVersion::V6 => Some(IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+        }
+    }
+
+    /// Whether connecting from `addr` is allowed by the family
+    pub fn allows(&self, addr: &IpAddr) -> bool {
+        match self {
+            IpVersion::Auto => true,
+            IpVersion::V4 => addr.is_ipv4(),
+            IpVersion::V6 => addr.is_ipv6(),
+        }
+    }
+}
+
+impl FromStr for IpVersion {
+    type Err = SubError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(IpVersion::Auto),
+            "4" | "v4" | "ipv4" => Ok(IpVersion::V4),
+            "6" | "v6" | "ipv6" => Ok(IpVersion::V6),
+            _ => Err(SubError::ConfigError(format!(
+                "{} isn't an ip version, expected 4, 6 or auto",
+                s
+            ))),
+        }
+    }
+}
+
+/// The TLS implementation connections are made with. Each is only available when the crate
+/// is built with its feature, `native-tls` or `rustls`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TlsBackend {
+    /// The platform's TLS library and the operating system's trust store, openssl on linux
+    Native,
+    /// rustls with the Mozilla root certificates built in, for builds which can't link openssl
+    Rustls,
+}
+
+impl TlsBackend {
+    /// Whether the backend was compiled in
+    pub fn is_available(&self) -> bool {
+        match self {
+            TlsBackend::Native => cfg!(feature = "native-tls"),
+            TlsBackend::Rustls => cfg!(feature = "rustls"),
+        }
+    }
+}
+
+impl FromStr for TlsBackend {
+    type Err = SubError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "native" | "native-tls" => Ok(TlsBackend::Native),
+            "rustls" => Ok(TlsBackend::Rustls),
+            _ => Err(SubError::ConfigError(format!(
+                "{} isn't a tls backend, expected native or rustls",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for TlsBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlsBackend::Native => write!(f, "native"),
+            TlsBackend::Rustls => write!(f, "rustls"),
+        }
+    }
+}
+
+/// Reads every PEM encoded certificate in the file at `path`, so a bundle holding a
+/// corporate root and its intermediates can be trusted in one go
+pub fn read_certificates(path: &Path) -> Result<Vec<reqwest::Certificate>> {
+    const END: &str = "-----END CERTIFICATE-----";
+    let pem = fs::read_to_string(path)
+        .map_err(|e| SubError::ConfigError(format!("couldn't read {:?} got {}", path, e)))?;
+
+    let certs: Vec<reqwest::Certificate> = pem
+        .split_inclusive(END)
+        .filter(|block| block.contains(END))
+        .map(|block| {
+            reqwest::Certificate::from_pem(block.trim().as_bytes()).map_err(|e| {
+                let msg = format!("couldn't parse a certificate in {:?} got {}", path, e);
+                SubError::ConfigError(msg)
+            })
+        })
+        .collect::<Result<_>>()?;
+    if certs.is_empty() {
+        return Err(SubError::ConfigError(format!(
+            "no certificates found in {:?}",
+            path
+        )));
+    }
+
+    Ok(certs)
+}
+
+/// The api keys sources read, those set here take the place of the environment variables of
+/// the same name
+#[derive(Clone, Default)]
+pub struct Keys {
+    inner: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl Keys {
+    /// Sets the key `name` in place of the environment variable
+    pub fn set(&self, name: &str, value: &str) {
+        self.inner
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(name.to_string(), value.to_string());
+    }
+
+    /// The key `name`, read from the environment or a .env file when it wasn't set here
+    pub fn get(&self, name: &str) -> Option<String> {
+        let keys = self.inner.read().unwrap_or_else(PoisonError::into_inner);
+        if let Some(value) = keys.get(name) {
+            return Some(value.clone());
+        }
+
+        dotenv().ok();
+        env::var(name).ok()
+    }
+
+    /// Whether every key `source` needs is set
+    pub fn has(&self, source: Source) -> bool {
+        source.keys().iter().all(|k| self.get(k).is_some())
+    }
+}
+
+/// The largest body each source may send, bodies are measured after decompression
+#[derive(Clone)]
+pub struct SizeLimits {
+    inner: Arc<RwLock<(usize, HashMap<Source, usize>)>>,
+}
+
+impl Default for SizeLimits {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(RwLock::new((MAX_RESPONSE_SIZE, HashMap::new()))),
+        }
+    }
+}
+
+impl SizeLimits {
+    /// Sets the limit for sources which don't have their own
+    pub fn set_default(&self, bytes: usize) {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner).0 = bytes;
+    }
+
+    /// Sets the limit for `source`
+    pub fn set(&self, source: Source, bytes: usize) {
+        self.inner
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .1
+            .insert(source, bytes);
+    }
+
+    pub fn get(&self, source: Option<Source>) -> usize {
+        let limits = self.inner.read().unwrap_or_else(PoisonError::into_inner);
+        source
+            .and_then(|s| limits.1.get(&s).copied())
+            .unwrap_or(limits.0)
+    }
+}
+
+/// How large a body may grow before `Response::body` moves it to a temporary file, and the
+/// directory the file is written to
+#[derive(Clone)]
+pub struct Spooling {
+    inner: Arc<RwLock<(usize, PathBuf)>>,
+}
+
+impl Default for Spooling {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(RwLock::new((SPOOL_THRESHOLD, std::env::temp_dir()))),
+        }
+    }
+}
+
+impl Spooling {
+    pub fn set_threshold(&self, bytes: usize) {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner).0 = bytes;
+    }
+
+    pub fn set_dir<P: Into<PathBuf>>(&self, dir: P) {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner).1 = dir.into();
+    }
+
+    pub fn get(&self) -> (usize, PathBuf) {
+        self.inner
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+/// Request timeouts for sources which need a different one than the client's, e.g. slow to
+/// stream archives
+#[derive(Clone, Default)]
+pub struct Timeouts {
+    inner: Arc<RwLock<HashMap<Source, Duration>>>,
+}
+
+impl Timeouts {
+    /// Sets the total time requests from `source` may take
+    pub fn set(&self, source: Source, timeout: Duration) {
+        self.inner
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(source, timeout);
+    }
+
+    pub fn get(&self, source: Option<Source>) -> Option<Duration> {
+        let timeouts = self.inner.read().unwrap_or_else(PoisonError::into_inner);
+        source.and_then(|s| timeouts.get(&s).copied())
+    }
+
+    /// Every timeout which was set, in no particular order
+    pub fn values(&self) -> Vec<Duration> {
+        let timeouts = self.inner.read().unwrap_or_else(PoisonError::into_inner);
+        timeouts.values().copied().collect()
+    }
+}
+
+/// Query parameters added to the urls each source requests, configured by users to tweak
+/// provider specific options the sources don't expose
+#[derive(Clone, Default)]
+pub struct QueryParams {
+    inner: Arc<RwLock<HashMap<Source, Vec<(String, String)>>>>,
+}
+
+impl QueryParams {
+    /// Adds `params` to every url `source` requests
+    pub fn set(&self, source: Source, params: Vec<(String, String)>) {
+        self.inner
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(source, params);
+    }
+
+    /// Adds `params` for `source` without replacing any it already has with the same name
+    pub fn add_missing(&self, source: Source, params: Vec<(String, String)>) {
+        let mut inner = self.inner.write().unwrap_or_else(PoisonError::into_inner);
+        let existing = inner.entry(source).or_insert_with(Vec::new);
+        for (k, v) in params {
+            if !existing.iter().any(|(e, _)| *e == k) {
+                existing.push((k, v));
+            }
+        }
+    }
+
+    /// Merges the parameters for `source` into `url`, replacing any it already has with the
+    /// same name. Urls which can't be parsed are returned as they are.
+    pub fn apply(&self, source: Option<Source>, url: &str) -> String {
+        let params = self.inner.read().unwrap_or_else(PoisonError::into_inner);
+        let extra = match source.and_then(|s| params.get(&s)) {
+            Some(extra) if !extra.is_empty() => extra,
+            _ => return url.to_string(),
+        };
+
+        let mut parsed = match Url::parse(url) {
+            Ok(u) => u,
+            Err(_) => return url.to_string(),
+        };
+        let existing: Vec<(String, String)> = parsed
+            .query_pairs()
+            .filter(|(k, _)| !extra.iter().any(|(e, _)| e == k))
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect();
+        parsed
+            .query_pairs_mut()
+            .clear()
+            .extend_pairs(existing.iter().chain(extra.iter()));
+        parsed.to_string()
+    }
+}
+
+/// Bodies answered in place of the network, by the source asking for them, so a run can go
+/// through every stage without a connection or api keys. Requests from a source without one,
+/// or from no source at all, get a 404.
+#[derive(Debug, Clone, Default)]
+pub struct Fixtures {
+    bodies: Arc<HashMap<Source, Bytes>>,
+}
+
+impl Fixtures {
+    pub fn new(bodies: HashMap<Source, Vec<u8>>) -> Self {
+        let bodies = bodies
+            .into_iter()
+            .map(|(s, b)| (s, Bytes::from(b)))
+            .collect();
+        Self {
+            bodies: Arc::new(bodies),
+        }
+    }
+
+    /// The sources which have a body
+    pub fn sources(&self) -> Vec<Source> {
+        let mut sources: Vec<Source> = self.bodies.keys().copied().collect();
+        sources.sort();
+        sources
+    }
+
+    fn respond(&self, source: Option<Source>) -> Inner {
+        match source.and_then(|s| self.bodies.get(&s)) {
+            Some(body) => Inner::Fixture(StatusCode::OK, Some(body.clone())),
+            None => Inner::Fixture(StatusCode::NOT_FOUND, None),
+        }
+    }
+}
+
+/// A `reqwest::Client` tagged with the source it's making requests for, so problems with a
+/// response can be attributed to the source which received it, and requests can be counted
+/// against the source's budget.
+#[derive(Clone, Default)]
+pub struct Client {
+    inner: reqwest::Client,
+    source: Option<Source>,
+    quotas: Quotas,
+    limits: SizeLimits,
+    params: QueryParams,
+    timeouts: Timeouts,
+    spooling: Spooling,
+    records: Records,
+    keys: Keys,
+    fixtures: Option<Fixtures>,
+}
+
+impl Client {
+    pub fn new(inner: reqwest::Client) -> Self {
+        Self {
+            inner,
+            source: None,
+            quotas: Quotas::default(),
+            limits: SizeLimits::default(),
+            params: QueryParams::default(),
+            timeouts: Timeouts::default(),
+            spooling: Spooling::default(),
+            records: Records::default(),
+            keys: Keys::default(),
+            fixtures: None,
+        }
+    }
+
+    /// Returns a client using `inner` which shares the budgets and size limits of this one
+    pub fn with_inner(&self, inner: reqwest::Client) -> Self {
+        Self {
+            inner,
+            source: self.source,
+            quotas: self.quotas.clone(),
+            limits: self.limits.clone(),
+            params: self.params.clone(),
+            timeouts: self.timeouts.clone(),
+            spooling: self.spooling.clone(),
+            records: self.records.clone(),
+            keys: self.keys.clone(),
+            fixtures: self.fixtures.clone(),
+        }
+    }
+
+    /// Returns a client sharing the same connection pool and budgets, tagged with `source`
+    pub fn for_source(&self, source: Source) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            source: Some(source),
+            quotas: self.quotas.clone(),
+            limits: self.limits.clone(),
+            params: self.params.clone(),
+            timeouts: self.timeouts.clone(),
+            spooling: self.spooling.clone(),
+            records: self.records.clone(),
+            keys: self.keys.clone(),
+            fixtures: self.fixtures.clone(),
+        }
+    }
+
+    /// The request budgets shared by this client and every client created from it
+    pub fn quotas(&self) -> &Quotas {
+        &self.quotas
+    }
+
+    /// The response size limits shared by this client and every client created from it
+    pub fn size_limits(&self) -> &SizeLimits {
+        &self.limits
+    }
+
+    /// Where large bodies are spooled, shared by this client and every client created from it
+    pub fn spooling(&self) -> &Spooling {
+        &self.spooling
+    }
+
+    /// The api keys sources read, shared by this client and every client created from it
+    pub fn keys(&self) -> &Keys {
+        &self.keys
+    }
+
+    /// The dns records sources saw, shared by this client and every client created from it
+    pub fn records(&self) -> &Records {
+        &self.records
+    }
+
+    /// Shares `records` with every client created from this one
+    pub fn with_records(mut self, records: Records) -> Self {
+        self.records = records;
+        self
+    }
+
+    /// Answers every request from `fixtures` rather than the network, along with every
+    /// client created from this one
+    pub fn with_fixtures(mut self, fixtures: Fixtures) -> Self {
+        self.fixtures = Some(fixtures);
+        self
+    }
+
+    /// Whether requests are answered from fixtures rather than the network
+    pub fn is_offline(&self) -> bool {
+        self.fixtures.is_some()
+    }
+
+    /// The extra query parameters shared by this client and every client created from it
+    pub fn query_params(&self) -> &QueryParams {
+        &self.params
+    }
+
+    /// Uses `quotas` in place of the client's own, so budgets are shared with whoever else
+    /// holds them
+    pub fn with_quotas(mut self, quotas: Quotas) -> Self {
+        self.quotas = quotas;
+        self
+    }
+
+    /// The per source request timeouts shared by this client and every client created from it
+    pub fn timeouts(&self) -> &Timeouts {
+        &self.timeouts
+    }
+
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        let url = self.params.apply(self.source, url);
+        self.request(self.inner.get(&url))
+    }
+
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        let url = self.params.apply(self.source, url);
+        self.request(self.inner.post(&url))
+    }
+
+    fn request(&self, inner: reqwest::RequestBuilder) -> RequestBuilder {
+        let inner = match self.timeouts.get(self.source) {
+            Some(timeout) => inner.timeout(timeout),
+            None => inner,
+        };
+        RequestBuilder {
+            inner,
+            source: self.source,
+            quotas: self.quotas.clone(),
+            limit: self.limits.get(self.source),
+            spooling: self.spooling.clone(),
+            fixtures: self.fixtures.clone(),
+        }
+    }
+}
+
+pub struct RequestBuilder {
+    inner: reqwest::RequestBuilder,
+    source: Option<Source>,
+    quotas: Quotas,
+    limit: usize,
+    spooling: Spooling,
+    fixtures: Option<Fixtures>,
+}
+
+impl RequestBuilder {
+    pub fn header<K: AsRef<str>, V: AsRef<str>>(mut self, key: K, value: V) -> Self {
+        self.inner = self.inner.header(key.as_ref(), value.as_ref());
+        self
+    }
+
+    pub fn basic_auth<U: Display, P: Display>(mut self, username: U, password: Option<P>) -> Self {
+        self.inner = self.inner.basic_auth(username, password);
+        self
+    }
+
+    pub fn bearer_auth<T: Display>(mut self, token: T) -> Self {
+        self.inner = self.inner.bearer_auth(token);
+        self
+    }
+
+    pub fn json<T: Serialize + ?Sized>(mut self, json: &T) -> Self {
+        self.inner = self.inner.json(json);
+        self
+    }
+
+    /// Sends the request, failing with `SubError::QuotaExceeded` without sending anything
+    /// once the source has used its budget. Paced sources wait for their turn first.
+    pub async fn send(self) -> Result<Response> {
+        if let Some(source) = self.source {
+            self.quotas.acquire(source)?;
+            if let Some(wait) = self.quotas.wait(source) {
+                time::delay_for(wait).await;
+            }
+        }
+
+        let inner = match &self.fixtures {
+            Some(fixtures) => fixtures.respond(self.source),
+            None => Inner::Live(self.inner.send().await?),
+        };
+        Ok(Response {
+            inner,
+            source: self.source,
+            quotas: self.quotas,
+            limit: self.limit,
+            spooling: self.spooling,
+        })
+    }
+}
+
+/// A response from the network, or one of the fixtures answering in its place
+enum Inner {
+    Live(reqwest::Response),
+    Fixture(StatusCode, Option<Bytes>),
+}
+
+impl Inner {
+    fn status(&self) -> StatusCode {
+        match self {
+            Inner::Live(res) => res.status(),
+            Inner::Fixture(status, _) => *status,
+        }
+    }
+
+    fn content_length(&self) -> Option<u64> {
+        match self {
+            Inner::Live(res) => res.content_length(),
+            Inner::Fixture(_, body) => Some(body.as_ref().map_or(0, |b| b.len() as u64)),
+        }
+    }
+
+    /// The next chunk of the body, a fixture's body comes in one go
+    async fn chunk(&mut self) -> Result<Option<Bytes>> {
+        match self {
+            Inner::Live(res) => Ok(res.chunk().await?),
+            Inner::Fixture(_, body) => Ok(body.take()),
+        }
+    }
+}
+
+pub struct Response {
+    inner: Inner,
+    source: Option<Source>,
+    quotas: Quotas,
+    limit: usize,
+    spooling: Spooling,
+}
+
+impl Response {
+    pub fn status(&self) -> StatusCode {
+        self.inner.status()
+    }
+
+    pub async fn text(self) -> Result<String> {
+        let body = self.bytes().await?;
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    /// Reads the body a chunk at a time, failing with `SubError::ResponseTooLarge` as soon as
+    /// it grows past the limit rather than buffering the whole thing. Compressed bodies are
+    /// decoded as they're streamed so the limit applies to the decoded size. Each chunk counts
+    /// towards the source's bandwidth usage, and reading pauses at the bandwidth ceiling.
+    pub async fn bytes(mut self) -> Result<Vec<u8>> {
+        let limit = self.limit;
+        let too_large = self.too_large();
+
+        let mut body = Vec::new();
+        if let Some(len) = self.inner.content_length() {
+            if len as usize > limit {
+                return Err(too_large);
+            }
+            body.reserve(len as usize);
+        }
+
+        while let Some(chunk) = self.inner.chunk().await? {
+            if let Some(wait) = self.quotas.record(self.source, chunk.len()) {
+                time::delay_for(wait).await;
+            }
+            if body.len() + chunk.len() > limit {
+                return Err(too_large);
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(body)
+    }
+
+    /// Reads the body like `bytes`, but once it grows past the spool threshold it's written
+    /// to a temporary file instead, so sources known for enormous responses can parse them
+    /// from disk. The file is removed when the `Body` is dropped.
+    pub async fn body(mut self) -> Result<Body> {
+        let limit = self.limit;
+        let too_large = self.too_large();
+        if self
+            .inner
+            .content_length()
+            .map_or(false, |len| len as usize > limit)
+        {
+            return Err(too_large);
+        }
+
+        let (threshold, dir) = self.spooling.get();
+        let mut spool = Spool::new(threshold, dir);
+        while let Some(chunk) = self.inner.chunk().await? {
+            if let Some(wait) = self.quotas.record(self.source, chunk.len()) {
+                time::delay_for(wait).await;
+            }
+            if spool.len + chunk.len() > limit {
+                return Err(too_large);
+            }
+            spool.push(&chunk)?;
+        }
+
+        spool.finish(self.source)
+    }
+
+    fn too_large(&self) -> SubError {
+        let source = self
+            .source
+            .map_or_else(|| "unknown".into(), |s| s.to_string());
+        SubError::ResponseTooLarge(source, self.limit)
+    }
+
+    /// Deserializes the body, a body which doesn't match `T` is reported as
+    /// `SubError::SchemaChanged` with a redacted sample of what was received.
+    pub async fn json<T: DeserializeOwned>(self) -> Result<T> {
+        let source = self.source;
+        let body = self.bytes().await?;
+        decode(source, &body)
+    }
+}
+
+/// Collects a body in memory until it passes the threshold, then moves it to a file
+struct Spool {
+    threshold: usize,
+    dir: PathBuf,
+    len: usize,
+    memory: Vec<u8>,
+    file: Option<(BufWriter<File>, Spooled)>,
+}
+
+impl Spool {
+    fn new(threshold: usize, dir: PathBuf) -> Self {
+        Self {
+            threshold,
+            dir,
+            len: 0,
+            memory: Vec::new(),
+            file: None,
+        }
+    }
+
+    fn push(&mut self, chunk: &[u8]) -> Result<()> {
+        self.len += chunk.len();
+        if self.file.is_none() && self.len > self.threshold {
+            fs::create_dir_all(&self.dir)?;
+            let (path, file) = loop {
+                let n = SPOOLED.fetch_add(1, Ordering::Relaxed);
+                let path = self
+                    .dir
+                    .join(format!("sub-body-{}-{}", std::process::id(), n));
+                match create_private(&path) {
+                    Ok(file) => break (path, file),
+                    // left behind by an earlier run with the same pid, or put there by
+                    // someone else, either way it isn't ours to write to
+                    Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                    Err(e) => return Err(e.into()),
+                }
+            };
+            debug!(
+                "spooling a body larger than {} bytes to {:?}",
+                self.threshold, &path
+            );
+            let mut writer = BufWriter::new(file);
+            writer.write_all(&std::mem::take(&mut self.memory))?;
+            self.file = Some((writer, Spooled { path }));
+        }
+
+        match &mut self.file {
+            Some((writer, _)) => writer.write_all(chunk)?,
+            None => self.memory.extend_from_slice(chunk),
+        }
+        Ok(())
+    }
+
+    fn finish(self, source: Option<Source>) -> Result<Body> {
+        let data = match self.file {
+            Some((mut writer, spooled)) => {
+                writer.flush()?;
+                Data::Spooled(spooled)
+            }
+            None => Data::Memory(self.memory),
+        };
+
+        Ok(Body { source, data })
+    }
+}
+
+/// Creates a file only the current user can read, failing rather than following a link or
+/// truncating a file which is already at `path`, as the spool is usually in a shared temp dir
+fn create_private(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+/// A body written to disk, removed once dropped
+struct Spooled {
+    path: PathBuf,
+}
+
+impl Drop for Spooled {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+enum Data {
+    Memory(Vec<u8>),
+    Spooled(Spooled),
+}
+
+/// A body read by `Response::body`, either held in memory or spooled to disk
+pub struct Body {
+    source: Option<Source>,
+    data: Data,
+}
+
+impl Body {
+    /// A body already in memory, e.g. a captured response
+    pub fn from_bytes(source: Option<Source>, body: Vec<u8>) -> Self {
+        Self {
+            source,
+            data: Data::Memory(body),
+        }
+    }
+
+    /// Whether the body was too large to keep in memory
+    pub fn is_spooled(&self) -> bool {
+        matches!(self.data, Data::Spooled(_))
+    }
+
+    /// Deserializes the body the same way `Response::json` does, spooled bodies are parsed
+    /// as they're read back from disk
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T> {
+        let spooled = match &self.data {
+            Data::Memory(body) => return decode(self.source, body),
+            Data::Spooled(spooled) => spooled,
+        };
+
+        let reader = BufReader::new(File::open(&spooled.path)?);
+        serde_json::from_reader(reader).map_err(|e| {
+            // one byte past the sample is enough for it to be marked as truncated
+            let mut start = Vec::with_capacity(SAMPLE_SIZE + 1);
+            if let Ok(file) = File::open(&spooled.path) {
+                let _ = file.take(SAMPLE_SIZE as u64 + 1).read_to_end(&mut start);
+            }
+            schema_changed(self.source, e, &start)
+        })
+    }
+}
+
+/// Deserializes a body `source` returned, the same way `Response::json` does, so sources can
+/// parse bodies which didn't come from a request
+pub(crate) fn decode<T: DeserializeOwned>(source: Option<Source>, body: &[u8]) -> Result<T> {
+    serde_json::from_slice(body).map_err(|e| schema_changed(source, e, body))
+}
+
+fn schema_changed(source: Option<Source>, e: serde_json::Error, body: &[u8]) -> SubError {
+    SubError::SchemaChanged {
+        source: source.map_or_else(|| "unknown".into(), |s| s.to_string()),
+        error: e.to_string(),
+        sample: sample(body),
+    }
+}
+
+/// Takes the start of a body with anything that looks like a key or token redacted
+fn sample(body: &[u8]) -> String {
+    let end = body.len().min(SAMPLE_SIZE);
+    let text = String::from_utf8_lossy(&body[..end]);
+    let mut sample = redact_tokens(&text);
+    if body.len() > SAMPLE_SIZE {
+        sample.push_str("...");
+    }
+
+    sample
+}
+
+/// Replaces the runs of token characters in `text` which look like credentials
+pub(crate) fn redact_tokens(text: &str) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    let mut run = String::new();
+
+    let is_token = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    for c in text.chars() {
+        if is_token(c) {
+            run.push(c);
+            continue;
+        }
+
+        redacted.push_str(redact(&run));
+        run.clear();
+        redacted.push(c);
+    }
+    redacted.push_str(redact(&run));
+    redacted
+}
+
+fn redact(run: &str) -> &str {
+    let has_digit = run.chars().any(|c| c.is_ascii_digit());
+    let has_alpha = run.chars().any(|c| c.is_ascii_alphabetic());
+    if run.len() >= SECRET_LEN && has_digit && has_alpha {
+        "[redacted]"
+    } else {
+        run
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_path;
+
+    #[test]
+    fn parses_ip_versions() {
+        assert_eq!("auto".parse::<IpVersion>().unwrap(), IpVersion::Auto);
+        assert_eq!("4".parse::<IpVersion>().unwrap(), IpVersion::V4);
+        assert_eq!("IPv6".parse::<IpVersion>().unwrap(), IpVersion::V6);
+        assert!("5".parse::<IpVersion>().is_err());
+        assert!(IpVersion::V4.allows(&IpVersion::V4.unspecified().unwrap()));
+        assert!(!IpVersion::V6.allows(&"127.0.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn parses_tls_backends() {
+        assert_eq!("Native".parse::<TlsBackend>().unwrap(), TlsBackend::Native);
+        assert_eq!("rustls".parse::<TlsBackend>().unwrap(), TlsBackend::Rustls);
+        assert!("openssl".parse::<TlsBackend>().is_err());
+        assert!(TlsBackend::Native.is_available() || TlsBackend::Rustls.is_available());
+    }
+
+    #[test]
+    fn rejects_files_without_certificates() {
+        let path = temp_path("certs.pem");
+        fs::write(&path, "not a certificate").unwrap();
+        assert!(read_certificates(&path).is_err());
+        fs::remove_file(&path).unwrap();
+        assert!(read_certificates(&path).is_err());
+    }
+
+    #[tokio::test]
+    async fn answers_from_fixtures() {
+        let mut bodies = HashMap::new();
+        bodies.insert(Source::Crtsh, b"[\"api.hackerone.com\"]".to_vec());
+        let client = Client::default().with_fixtures(Fixtures::new(bodies));
+
+        let crtsh = client.for_source(Source::Crtsh);
+        let res = crtsh
+            .get("https://crt.sh/?q=hackerone.com")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let hosts: Vec<String> = res.json().await.unwrap();
+        assert_eq!(hosts, vec!["api.hackerone.com"]);
+
+        let res = client
+            .get("https://crt.sh/robots.txt")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert!(res.bytes().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn creates_private_files_once() {
+        let path = temp_path("private");
+        let _ = fs::remove_file(&path);
+        create_private(&path).unwrap();
+        let err = create_private(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn spools_large_bodies() {
+        let dir = temp_path("spool");
+        let mut spool = Spool::new(8, dir.clone());
+        spool.push(b"[\"api.hackerone.com\",").unwrap();
+        spool.push(b" \"www.hackerone.com\"]").unwrap();
+        let body = spool.finish(Some(Source::Crtsh)).unwrap();
+        assert!(body.is_spooled());
+
+        let hosts: Vec<String> = body.decode().unwrap();
+        assert_eq!(hosts, vec!["api.hackerone.com", "www.hackerone.com"]);
+        assert!(body.decode::<Vec<u32>>().is_err());
+        drop(body);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir(dir).unwrap();
+
+        let mut spool = Spool::new(64, std::env::temp_dir());
+        spool.push(b"[]").unwrap();
+        assert!(!spool.finish(None).unwrap().is_spooled());
+    }
+
+    #[test]
+    fn redacts_tokens() {
+        let body = br#"{"error": "bad key", "key": "a1b2c3d4e5f6a7b8c9d0e1f2a3b4"}"#;
+        assert_eq!(sample(body), r#"{"error": "bad key", "key": "[redacted]"}"#);
+    }
+
+    #[test]
+    fn truncates_samples() {
+        let body = "a".repeat(SAMPLE_SIZE * 2);
+        let sample = sample(body.as_bytes());
+        assert!(sample.ends_with("..."));
+        assert_eq!(sample.len(), SAMPLE_SIZE + 3);
+    }
+
+    #[test]
+    fn merges_query_params() {
+        let params = QueryParams::default();
+        params.set(Source::UrlScan, vec![("size".into(), "10000".into())]);
+
+        let url = "https://urlscan.io/api/v1/search/?q=domain:hackerone.com&size=100";
+        assert_eq!(
+            params.apply(Some(Source::UrlScan), url),
+            "https://urlscan.io/api/v1/search/?q=domain%3Ahackerone.com&size=10000"
+        );
+        assert_eq!(params.apply(Some(Source::Crtsh), url), url);
+        assert_eq!(params.apply(None, url), url);
+    }
+}
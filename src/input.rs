@@ -0,0 +1,212 @@
+use crate::error::{Result, SubError};
+use crate::profile::Profile;
+use crate::sources::Source;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::str::FromStr;
+
+/// A root to enumerate along with any settings it has in place of the run's
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct RootSpec {
+    pub root: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The profile to enumerate the root with
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// The only sources queried for the root
+    #[serde(default)]
+    pub sources: Option<Vec<String>>,
+}
+
+impl RootSpec {
+    pub fn new<S: Into<String>>(root: S) -> Self {
+        Self {
+            root: root.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Splits a line like `example.com,prod,eu` into the root and its tags
+    pub fn from_line(line: &str) -> Self {
+        let mut parts = line.split(',').map(|p| p.trim().to_string());
+        Self {
+            root: parts.next().unwrap_or_default(),
+            tags: parts.filter(|t| !t.is_empty()).collect(),
+            ..Self::default()
+        }
+    }
+
+    pub fn profile(&self) -> Result<Option<Profile>> {
+        self.profile
+            .as_deref()
+            .map(|name| {
+                Profile::from_str(name)
+                    .map_err(|_| SubError::ConfigError(format!("unknown profile {}", name)))
+            })
+            .transpose()
+    }
+
+    /// The sources the root asked for sorted and deduplicated, or `None` to use the run's
+    pub fn sources(&self) -> Result<Option<Vec<Source>>> {
+        let names = match &self.sources {
+            Some(names) => names,
+            None => return Ok(None),
+        };
+
+        let mut sources = Vec::with_capacity(names.len());
+        for name in names {
+            let source = Source::from_name(name).ok_or_else(|| {
+                SubError::ConfigError(format!("{} asked for unknown source {}", self.root, name))
+            })?;
+            sources.push(source);
+        }
+        sources.sort();
+        sources.dedup();
+        Ok(Some(sources))
+    }
+
+    /// Adds the tags of `other`, and takes its profile and sources where it sets them
+    fn merge(&mut self, other: RootSpec) {
+        self.tags.extend(other.tags);
+        if other.profile.is_some() {
+            self.profile = other.profile;
+        }
+        if other.sources.is_some() {
+            self.sources = other.sources;
+        }
+    }
+}
+
+/// Reads roots in any of the input formats:
+///
+/// * a root per line, optionally followed by comma separated tags: `example.com,prod`
+/// * a json array of `{"root": "example.com", "tags": ["prod"], "profile": "fast",
+///   "sources": ["crtsh"]}` objects, or the same objects one per line
+/// * csv with a header naming its columns out of `root`, `tags`, `profile` and `sources`.
+///   Tags and sources are separated by `;` within their column, fields can't be quoted.
+///
+/// Only `root` is required, everything else falls back to the run's settings. Roots given
+/// more than once are merged.
+pub fn read<R: Read>(mut reader: R) -> Result<Vec<RootSpec>> {
+    let mut input = String::new();
+    reader.read_to_string(&mut input)?;
+    merge(parse(&input)?)
+}
+
+fn parse(input: &str) -> Result<Vec<RootSpec>> {
+    if input.trim_start().starts_with('[') {
+        return serde_json::from_str(input)
+            .map_err(|e| SubError::ConfigError(format!("couldn't parse json input got {}", e)));
+    }
+
+    let mut lines = input
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .peekable();
+    let is_csv = lines.peek().map_or(false, |l| {
+        l.split(',').next().map(str::trim) == Some("root")
+    });
+    if is_csv {
+        let header: Vec<&str> = lines.next().unwrap_or_default().split(',').collect();
+        return lines.map(|l| parse_csv(&header, l)).collect();
+    }
+
+    lines
+        .map(|line| {
+            // json lines let orchestration tools give each root its own settings
+            if line.starts_with('{') {
+                serde_json::from_str(line).map_err(|e| {
+                    SubError::ConfigError(format!("couldn't parse input line {} got {}", line, e))
+                })
+            } else {
+                Ok(RootSpec::from_line(line))
+            }
+        })
+        .collect()
+}
+
+fn parse_csv(header: &[&str], line: &str) -> Result<RootSpec> {
+    let list = |field: &str| -> Vec<String> {
+        field
+            .split(';')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string())
+            .collect()
+    };
+    let set = |field: &str| Some(field.to_string()).filter(|f| !f.is_empty());
+
+    let mut spec = RootSpec::default();
+    for (column, field) in header.iter().zip(line.split(',').map(str::trim)) {
+        match column.trim() {
+            "root" => spec.root = field.to_string(),
+            "tags" => spec.tags = list(field),
+            "profile" => spec.profile = set(field),
+            "sources" => spec.sources = set(field).map(|f| list(&f)),
+            c => return Err(SubError::ConfigError(format!("unknown input column {}", c))),
+        }
+    }
+
+    if spec.root.is_empty() {
+        return Err(SubError::ConfigError(format!(
+            "input line {} has no root",
+            line
+        )));
+    }
+    Ok(spec)
+}
+
+/// Merges roots given more than once, keeping the order they were first given in
+fn merge(specs: Vec<RootSpec>) -> Result<Vec<RootSpec>> {
+    let mut merged: Vec<RootSpec> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for spec in specs {
+        match index.get(&spec.root) {
+            Some(i) => merged[*i].merge(spec),
+            None => {
+                index.insert(spec.root.clone(), merged.len());
+                merged.push(spec);
+            }
+        }
+    }
+
+    // check every root's settings up front rather than part way through a run
+    for spec in merged.iter() {
+        spec.profile()?;
+        spec.sources()?;
+    }
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_lines_and_json_lines() {
+        let input = "hackerone.com,prod\n\n{\"root\": \"hackerone.net\", \"profile\": \"fast\"}\n\
+                     hackerone.com,eu\n";
+        let specs = read(input.as_bytes()).unwrap();
+        assert_eq!(specs.len(), 2);
+        assert_eq!(specs[0].tags, vec!["prod", "eu"]);
+        assert_eq!(specs[1].profile().unwrap(), Some(Profile::Fast));
+    }
+
+    #[test]
+    fn reads_csv() {
+        let input = "root,tags,sources\nhackerone.com,prod;eu,crtsh;Wayback;crtsh\ngoogle.com,,\n";
+        let specs = read(input.as_bytes()).unwrap();
+        assert_eq!(specs[0].tags, vec!["prod", "eu"]);
+        assert_eq!(
+            specs[0].sources().unwrap(),
+            Some(vec![Source::Crtsh, Source::Wayback])
+        );
+        assert_eq!(specs[1], RootSpec::new("google.com"));
+
+        assert!(read("root,depth\nhackerone.com,2\n".as_bytes()).is_err());
+        assert!(read("root,sources\nhackerone.com,nope\n".as_bytes()).is_err());
+    }
+}
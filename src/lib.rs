@@ -2,15 +2,69 @@
 
 pub use crate::subdomain::Runner;
 use async_trait::async_trait;
+pub use checkpoint::{Chunk, RunState};
+pub use dates::{Date, DateRange};
+pub use dedup::{Dedup, Overflow};
+pub use engagement::Engagement;
+pub use enrich::{Enricher, Enrichment};
 use error::Result;
+pub use event::{Event, SkipReason, Skipped};
+pub use heuristics::Heuristics;
+pub use history::{History, RunRecord};
+pub use http::{Fixtures, IpVersion, TlsBackend};
+pub use output::{Fanout, OutputOptions, OutputSink, OutputSpec, Subdomain, Summary, Template};
+pub use policy::ScrapePolicy;
 pub use postprocessor::{CleanExt, PostProcessor, PostProcessorIter};
+pub use profile::{Profile, ProfileSettings};
+pub use quota::{Quotas, Usage};
+pub use records::{DnsRecord, RecordType, Records};
+pub use settings::Settings;
+pub use sources::Source;
 use std::sync::Arc;
 use tokio::sync::mpsc;
+pub use units::{ByteSize, TimeSpan};
 
+pub mod analyze;
+pub mod cdn;
+pub mod checkpoint;
+pub mod dates;
+pub mod dedup;
+pub mod demo;
+pub mod dnscache;
+pub mod engagement;
+pub mod enrich;
 pub mod error;
+pub mod event;
+pub mod failures;
+pub mod fallback;
+pub mod fdlimit;
+#[cfg(feature = "fuzz")]
+#[doc(hidden)]
+pub mod fuzz;
+pub mod generate;
+pub mod heuristics;
+pub mod history;
+pub mod http;
+pub mod input;
+pub mod output;
+pub mod policy;
 pub mod postprocessor;
+pub mod preflight;
+pub mod profile;
+pub mod quota;
+pub mod records;
+pub mod settings;
 pub mod sources;
 pub mod subdomain;
+pub mod telemetry;
+#[cfg(test)]
+mod testing;
+pub mod units;
+#[cfg(feature = "worker")]
+pub mod worker;
+
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+compile_error!("at least one of the native-tls and rustls features is needed to make requests");
 
 pub (crate) const QUEUE_SIZE: usize = 1024;
 
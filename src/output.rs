@@ -0,0 +1,855 @@
+use crate::error::{Result, SubError};
+use crate::event::Skipped;
+use crate::records::DnsRecord;
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::warn;
+
+/// Results are posted to webhooks in batches of this many
+const WEBHOOK_BATCH: usize = 500;
+
+/// How many per root files are kept open at once, the least recently written is closed to
+/// open another so runs over thousands of roots don't run out of file descriptors
+const MAX_OPEN_ROOT_FILES: usize = 64;
+
+/// A result along with the root it was found for and anything known about it
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Subdomain {
+    #[serde(rename = "subdomain")]
+    pub name: String,
+    pub root: Option<String>,
+    pub tags: Vec<String>,
+    /// The sources which had returned the name when it was written, only tracked when asked
+    /// for
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
+    /// The heuristic patterns the name matched
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub interesting: Vec<String>,
+    /// The records passive DNS sources saw for the name
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub records: Vec<DnsRecord>,
+    /// What enrichers added, keyed by enricher
+    #[serde(flatten)]
+    pub enrichments: BTreeMap<String, Value>,
+}
+
+impl Subdomain {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+}
+
+/// What happened during a run, handed to every sink once it's finished
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Summary {
+    pub roots: usize,
+    pub results: usize,
+    pub failures: usize,
+    pub elapsed: Duration,
+    /// The sources which didn't give full coverage, the delta to a run using every source
+    pub skipped: Vec<Skipped>,
+}
+
+/// Somewhere results are written to. Sinks decide how results are presented, the runner and
+/// cli only produce them.
+#[async_trait]
+pub trait OutputSink: Send {
+    async fn write(&mut self, subdomain: &Subdomain) -> Result<()>;
+
+    /// Makes sure everything written so far has reached its destination
+    async fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once after the last result has been written
+    async fn finalize(&mut self, _summary: &Summary) -> Result<()> {
+        self.flush().await
+    }
+}
+
+/// Lists of values are joined with this when rendered into a template
+const TEMPLATE_SEPARATOR: &str = ";";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Field(String),
+}
+
+/// A line format with fields in braces, e.g. `{host},{root},{ips}`, rendered for each result.
+///
+/// `host` and `root` are the result and the root it was found for, `sources` the sources
+/// which returned it, `tags` and `interesting` the root's tags and the patterns the name
+/// matched, and `ips` the addresses added by resolving. Any other field is looked up in what
+/// enrichers added, so `{cdn}` is the CDN the host is behind. Fields without a value render
+/// as nothing, lists are joined with `;` and `{{` and `}}` are literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    /// Whether the template has `field` in it
+    pub fn uses(&self, field: &str) -> bool {
+        self.segments
+            .iter()
+            .any(|s| matches!(s, Segment::Field(f) if f == field))
+    }
+
+    pub fn render(&self, subdomain: &Subdomain) -> String {
+        let mut line = String::new();
+        for segment in self.segments.iter() {
+            match segment {
+                Segment::Literal(s) => line.push_str(s),
+                Segment::Field(f) => line.push_str(&Self::field(subdomain, f)),
+            }
+        }
+
+        line
+    }
+
+    fn field(subdomain: &Subdomain, field: &str) -> String {
+        let value = match field {
+            "host" | "subdomain" | "name" => return subdomain.name.clone(),
+            "root" => return subdomain.root.clone().unwrap_or_default(),
+            "tags" => return subdomain.tags.join(TEMPLATE_SEPARATOR),
+            "sources" => return subdomain.sources.join(TEMPLATE_SEPARATOR),
+            "interesting" => return subdomain.interesting.join(TEMPLATE_SEPARATOR),
+            "ips" => subdomain.enrichments.get("addresses"),
+            f => subdomain.enrichments.get(f),
+        };
+
+        let text = |v: &Value| match v {
+            Value::String(s) => s.clone(),
+            Value::Null => String::new(),
+            v => v.to_string(),
+        };
+        match value {
+            Some(Value::Array(values)) => values
+                .iter()
+                .map(text)
+                .collect::<Vec<_>>()
+                .join(TEMPLATE_SEPARATOR),
+            Some(v) => text(v),
+            None => String::new(),
+        }
+    }
+}
+
+impl FromStr for Template {
+    type Err = SubError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |why: &str| SubError::ConfigError(format!("template {:?} {}", s, why));
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut field = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some('{') | None => return Err(invalid("has an unclosed field")),
+                            Some(c) => field.push(c),
+                        }
+                    }
+                    if field.trim().is_empty() {
+                        return Err(invalid("has an empty field"));
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Field(field.trim().to_string()));
+                }
+                '}' => return Err(invalid("has an unopened }, use }} for a literal one")),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        if !segments.iter().any(|s| matches!(s, Segment::Field(_))) {
+            return Err(invalid("has no fields, e.g. {host}"));
+        }
+
+        Ok(Self { segments })
+    }
+}
+
+/// Writes one name per line
+pub struct LineSink {
+    out: Box<dyn Write + Send>,
+    prefix_root: bool,
+    template: Option<Template>,
+    terminator: char,
+}
+
+impl LineSink {
+    pub fn new(out: Box<dyn Write + Send>) -> Self {
+        Self {
+            out,
+            prefix_root: false,
+            template: None,
+            terminator: '\n',
+        }
+    }
+
+    /// Ends each result with a NUL rather than a newline, for `xargs -0` and the like
+    pub fn null_terminated(mut self) -> Self {
+        self.terminator = '\0';
+        self
+    }
+
+    /// Writes each result rendered with `template` rather than just its name
+    pub fn template(mut self, template: Template) -> Self {
+        self.template = Some(template);
+        self
+    }
+
+    /// Prefixes each line with the root the result was found for and a tab, so results from
+    /// different roots can be told apart when they're interleaved
+    pub fn prefix_root(mut self) -> Self {
+        self.prefix_root = true;
+        self
+    }
+
+    pub fn stdout() -> Self {
+        Self::new(Box::new(io::stdout()))
+    }
+
+    pub fn file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(Box::new(create(path.as_ref(), false)?)))
+    }
+
+    /// Appends to the file at `path` rather than replacing it
+    pub fn append<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(Box::new(create(path.as_ref(), true)?)))
+    }
+}
+
+#[async_trait]
+impl OutputSink for LineSink {
+    async fn write(&mut self, subdomain: &Subdomain) -> Result<()> {
+        let t = self.terminator;
+        match (&self.template, &subdomain.root) {
+            (Some(template), _) => write!(self.out, "{}{}", template.render(subdomain), t)?,
+            (None, Some(root)) if self.prefix_root => {
+                write!(self.out, "{}\t{}{}", root, subdomain.name, t)?
+            }
+            _ => write!(self.out, "{}{}", subdomain.name, t)?,
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Writes each result as a json object on its own line
+pub struct JsonlSink {
+    out: Box<dyn Write + Send>,
+    terminator: char,
+}
+
+impl JsonlSink {
+    pub fn new(out: Box<dyn Write + Send>) -> Self {
+        Self {
+            out,
+            terminator: '\n',
+        }
+    }
+
+    /// Ends each object with a NUL rather than a newline
+    pub fn null_terminated(mut self) -> Self {
+        self.terminator = '\0';
+        self
+    }
+
+    pub fn stdout() -> Self {
+        Self::new(Box::new(io::stdout()))
+    }
+
+    pub fn file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(Box::new(create(path.as_ref(), false)?)))
+    }
+
+    /// Appends to the file at `path` rather than replacing it
+    pub fn append<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(Box::new(create(path.as_ref(), true)?)))
+    }
+}
+
+#[async_trait]
+impl OutputSink for JsonlSink {
+    async fn write(&mut self, subdomain: &Subdomain) -> Result<()> {
+        let line = serde_json::to_string(subdomain).map_err(|e| e.to_string())?;
+        write!(self.out, "{}{}", line, self.terminator)?;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Posts results to a url as json arrays in batches, followed by the summary once finished
+pub struct WebhookSink {
+    url: String,
+    client: reqwest::Client,
+    batch: Vec<Subdomain>,
+}
+
+#[derive(Serialize)]
+struct Batch<'a> {
+    results: &'a [Subdomain],
+}
+
+#[derive(Serialize)]
+struct Finished<'a> {
+    summary: &'a Summary,
+}
+
+impl WebhookSink {
+    /// Posts to `url` with `client`, usually built from the runner's network settings
+    pub fn new<S: Into<String>>(url: S, client: reqwest::Client) -> Self {
+        Self {
+            url: url.into(),
+            client,
+            batch: Vec::with_capacity(WEBHOOK_BATCH),
+        }
+    }
+
+    async fn post<T: Serialize + Sync>(&self, body: &T) -> Result<()> {
+        let failed = |reason: String| SubError::SinkError(self.url.clone(), reason);
+        let resp = self
+            .client
+            .post(&self.url)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| failed(e.to_string()))?;
+        if !resp.status().is_success() {
+            warn!(
+                "got status: {} posting to {}",
+                resp.status().as_str(),
+                &self.url
+            );
+            return Err(failed(format!("got status {}", resp.status().as_str())));
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OutputSink for WebhookSink {
+    async fn write(&mut self, subdomain: &Subdomain) -> Result<()> {
+        self.batch.push(subdomain.clone());
+        if self.batch.len() >= WEBHOOK_BATCH {
+            self.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+
+        self.post(&Batch {
+            results: &self.batch,
+        })
+        .await?;
+        self.batch.clear();
+        Ok(())
+    }
+
+    async fn finalize(&mut self, summary: &Summary) -> Result<()> {
+        self.flush().await?;
+        self.post(&Finished { summary }).await
+    }
+}
+
+/// Writes the results for each root to their own file in a directory, `<root>.txt`, or
+/// `<root>.jsonl` for json lines. Results which couldn't be attributed to a root are written
+/// to `unattributed`. Files are written like any other output with `options`, and only the
+/// most recently written are kept open.
+pub struct RootFilesSink {
+    dir: PathBuf,
+    options: OutputOptions,
+    files: HashMap<String, Box<dyn OutputSink>>,
+    // the roots with an open file, least recently written first
+    recent: VecDeque<String>,
+    // the roots whose file was created by this run, reopened files are appended to
+    created: HashSet<String>,
+}
+
+impl RootFilesSink {
+    pub fn new<P: Into<PathBuf>>(dir: P, options: &OutputOptions) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("tried to create output directory {:?} got {}", &dir, e))?;
+        Ok(Self {
+            dir,
+            options: options.clone(),
+            files: HashMap::new(),
+            recent: VecDeque::new(),
+            created: HashSet::new(),
+        })
+    }
+
+    async fn open(&mut self, root: &str) -> Result<()> {
+        if self.files.len() >= MAX_OPEN_ROOT_FILES {
+            if let Some(mut oldest) = self.recent.pop_front().and_then(|r| self.files.remove(&r)) {
+                oldest.flush().await?;
+            }
+        }
+
+        let ext = if self.options.json { "jsonl" } else { "txt" };
+        let path = self.dir.join(format!("{}.{}", root, ext));
+        let append = self.options.append || !self.created.insert(root.to_string());
+        let sink: Box<dyn OutputSink> = match (self.options.json, append) {
+            (true, true) => Box::new(jsonl(JsonlSink::append(path)?, &self.options)),
+            (true, false) => Box::new(jsonl(JsonlSink::file(path)?, &self.options)),
+            (false, true) => Box::new(line(LineSink::append(path)?, &self.options)),
+            (false, false) => Box::new(line(LineSink::file(path)?, &self.options)),
+        };
+        self.files.insert(root.to_string(), sink);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OutputSink for RootFilesSink {
+    async fn write(&mut self, subdomain: &Subdomain) -> Result<()> {
+        // roots are hostnames, but make sure a malformed one can't escape the directory
+        let root = subdomain
+            .root
+            .as_deref()
+            .filter(|r| !r.is_empty() && !r.contains(|c| c == '/' || c == '\\') && *r != "..")
+            .unwrap_or("unattributed");
+
+        if self.files.contains_key(root) {
+            if self.recent.back().map_or(true, |r| r != root) {
+                self.recent.retain(|r| r != root);
+                self.recent.push_back(root.to_string());
+            }
+        } else {
+            self.open(root).await?;
+            self.recent.push_back(root.to_string());
+        }
+
+        self.files.get_mut(root).unwrap().write(subdomain).await
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        for sink in self.files.values_mut() {
+            sink.flush().await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes every result to each of several sinks
+#[derive(Default)]
+pub struct Fanout {
+    sinks: Vec<Box<dyn OutputSink>>,
+}
+
+impl Fanout {
+    pub fn push(&mut self, sink: Box<dyn OutputSink>) {
+        self.sinks.push(sink);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+}
+
+#[async_trait]
+impl OutputSink for Fanout {
+    async fn write(&mut self, subdomain: &Subdomain) -> Result<()> {
+        for sink in self.sinks.iter_mut() {
+            sink.write(subdomain).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        for sink in self.sinks.iter_mut() {
+            sink.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn finalize(&mut self, summary: &Summary) -> Result<()> {
+        for sink in self.sinks.iter_mut() {
+            sink.finalize(summary).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The formats an `OutputSpec` can ask for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Jsonl,
+    Webhook,
+    /// A file per root in the destination directory
+    Roots,
+}
+
+/// How outputs are written when their spec doesn't say
+#[derive(Debug, Clone, Default)]
+pub struct OutputOptions {
+    /// Write json lines rather than text when no format was given
+    pub json: bool,
+    /// Prefix text lines with the root the result was found for
+    pub prefix_root: bool,
+    /// Render text lines with a template, takes precedence over `prefix_root`
+    pub template: Option<Template>,
+    /// End results written to files or stdout with a NUL rather than a newline
+    pub null: bool,
+    /// Append to files which already exist rather than replacing them
+    pub append: bool,
+    /// The client webhooks post with, so they connect through the same proxy and tls
+    /// settings as the sources
+    pub client: reqwest::Client,
+}
+
+/// Where results are written and how, parsed from `[format:]destination`.
+///
+/// The format is one of `text`, `jsonl`, `webhook` or `roots`, which writes a file per root
+/// into a directory, and can be left out for files. A
+/// destination of `-` writes to stdout, so `jsonl:-` prints json lines while `results.txt`
+/// writes whichever format the run defaults to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputSpec {
+    pub format: Option<Format>,
+    pub destination: String,
+}
+
+impl OutputSpec {
+    /// Opens the sink, using json lines rather than text when no format was given and
+    /// `options.json` is set.
+    pub fn open(&self, options: &OutputOptions) -> Result<Box<dyn OutputSink>> {
+        let stdout = self.destination == "-";
+        let format = self.format.unwrap_or(if options.json {
+            Format::Jsonl
+        } else {
+            Format::Text
+        });
+        let path = self.destination.as_str();
+        let sink: Box<dyn OutputSink> = match format {
+            Format::Text if stdout => Box::new(line(LineSink::stdout(), options)),
+            Format::Text if options.append => Box::new(line(LineSink::append(path)?, options)),
+            Format::Text => Box::new(line(LineSink::file(path)?, options)),
+            Format::Jsonl if stdout => Box::new(jsonl(JsonlSink::stdout(), options)),
+            Format::Jsonl if options.append => Box::new(jsonl(JsonlSink::append(path)?, options)),
+            Format::Jsonl => Box::new(jsonl(JsonlSink::file(path)?, options)),
+            Format::Webhook => Box::new(WebhookSink::new(path, options.client.clone())),
+            Format::Roots => Box::new(RootFilesSink::new(path, options)?),
+        };
+
+        Ok(sink)
+    }
+}
+
+impl FromStr for OutputSpec {
+    type Err = SubError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (format, destination) = match s.split_once(':') {
+            Some((f, d)) => match f.to_lowercase().as_str() {
+                "text" | "txt" => (Some(Format::Text), d),
+                "jsonl" | "json" => (Some(Format::Jsonl), d),
+                "webhook" => (Some(Format::Webhook), d),
+                "roots" => (Some(Format::Roots), d),
+                _ => (None, s),
+            },
+            None => (None, s),
+        };
+
+        if destination.is_empty() {
+            return Err(SubError::ConfigError(format!(
+                "output {:?} has no destination",
+                s
+            )));
+        }
+        if format == Some(Format::Webhook) && destination == "-" {
+            return Err(SubError::ConfigError("webhook outputs need a url".into()));
+        }
+        if format == Some(Format::Roots) && destination == "-" {
+            return Err(SubError::ConfigError(
+                "roots outputs need a directory".into(),
+            ));
+        }
+
+        Ok(Self {
+            format,
+            destination: destination.to_string(),
+        })
+    }
+}
+
+/// Applies the text options to a line sink
+fn line(sink: LineSink, options: &OutputOptions) -> LineSink {
+    let sink = match (&options.template, options.prefix_root) {
+        (Some(template), _) => sink.template(template.clone()),
+        (None, true) => sink.prefix_root(),
+        (None, false) => sink,
+    };
+    match options.null {
+        true => sink.null_terminated(),
+        false => sink,
+    }
+}
+
+/// Applies the options json lines take to a jsonl sink
+fn jsonl(sink: JsonlSink, options: &OutputOptions) -> JsonlSink {
+    match options.null {
+        true => sink.null_terminated(),
+        false => sink,
+    }
+}
+
+fn create(path: &Path, append: bool) -> Result<BufWriter<File>> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .map_err(|e| format!("tried to create output file {:?} got {}", path, e))?;
+    Ok(BufWriter::new(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_path;
+    use std::sync::{Arc, Mutex};
+
+    /// A writer which can be read back after being handed to a sink
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn writes_jsonl() {
+        let out = Shared::default();
+        let mut sink = JsonlSink::new(Box::new(out.clone()));
+        let mut result = Subdomain::new("api.hackerone.com");
+        result.root = Some("hackerone.com".into());
+
+        sink.write(&result).await.unwrap();
+        sink.finalize(&Summary::default()).await.unwrap();
+
+        let written = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(
+            written,
+            "{\"subdomain\":\"api.hackerone.com\",\"root\":\"hackerone.com\",\"tags\":[]}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn prefixes_lines_with_roots() {
+        let out = Shared::default();
+        let mut sink = LineSink::new(Box::new(out.clone())).prefix_root();
+        let mut result = Subdomain::new("api.hackerone.com");
+        result.root = Some("hackerone.com".into());
+
+        sink.write(&result).await.unwrap();
+        sink.write(&Subdomain::new("unknown.example.com"))
+            .await
+            .unwrap();
+        sink.finalize(&Summary::default()).await.unwrap();
+
+        let written = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(
+            written,
+            "hackerone.com\tapi.hackerone.com\nunknown.example.com\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn terminates_with_nul() {
+        let out = Shared::default();
+        let mut sink = LineSink::new(Box::new(out.clone())).null_terminated();
+        sink.write(&Subdomain::new("api.hackerone.com"))
+            .await
+            .unwrap();
+        sink.write(&Subdomain::new("odd\nname.hackerone.com"))
+            .await
+            .unwrap();
+        sink.finalize(&Summary::default()).await.unwrap();
+
+        let written = out.0.lock().unwrap().clone();
+        assert_eq!(
+            written,
+            b"api.hackerone.com\0odd\nname.hackerone.com\0".to_vec()
+        );
+    }
+
+    #[tokio::test]
+    async fn renders_templates() {
+        let template: Template = "{host},{root},{{{tags}}},{ips},{cdn},{sources}"
+            .parse()
+            .unwrap();
+        assert!(template.uses("sources"));
+        let out = Shared::default();
+        let mut sink = LineSink::new(Box::new(out.clone())).template(template);
+        let mut result = Subdomain::new("api.hackerone.com");
+        result.root = Some("hackerone.com".into());
+        result.tags = vec!["prod".into(), "web".into()];
+        result.sources = vec!["crtsh".into(), "wayback".into()];
+        result.enrichments.insert(
+            "addresses".into(),
+            serde_json::json!(["104.16.99.52", "104.16.100.52"]),
+        );
+
+        sink.write(&result).await.unwrap();
+        sink.finalize(&Summary::default()).await.unwrap();
+
+        let written = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(
+            written,
+            "api.hackerone.com,hackerone.com,{prod;web},104.16.99.52;104.16.100.52,,crtsh;wayback\n"
+        );
+        assert!("{host".parse::<Template>().is_err());
+        assert!("host}".parse::<Template>().is_err());
+        assert!("host".parse::<Template>().is_err());
+    }
+
+    #[tokio::test]
+    async fn writes_a_file_per_root() {
+        let dir = temp_path("roots");
+        let mut sink = RootFilesSink::new(&dir, &OutputOptions::default()).unwrap();
+        for (name, root) in &[
+            ("api.hackerone.com", Some("hackerone.com")),
+            ("www.hackerone.com", Some("hackerone.com")),
+            ("mail.google.com", Some("google.com")),
+            ("other.example.com", None),
+        ] {
+            let mut result = Subdomain::new(*name);
+            result.root = root.map(|r| r.to_string());
+            sink.write(&result).await.unwrap();
+        }
+        sink.finalize(&Summary::default()).await.unwrap();
+
+        let read = |f: &str| std::fs::read_to_string(dir.join(f)).unwrap();
+        assert_eq!(
+            read("hackerone.com.txt"),
+            "api.hackerone.com\nwww.hackerone.com\n"
+        );
+        assert_eq!(read("google.com.txt"), "mail.google.com\n");
+        assert_eq!(read("unattributed.txt"), "other.example.com\n");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reopens_closed_root_files() {
+        let dir = temp_path("roots-closed");
+        let options = OutputOptions {
+            null: true,
+            ..OutputOptions::default()
+        };
+        let mut sink = RootFilesSink::new(&dir, &options).unwrap();
+        let result = |name: String, root: String| {
+            let mut result = Subdomain::new(name);
+            result.root = Some(root);
+            result
+        };
+        for i in 0..=MAX_OPEN_ROOT_FILES {
+            let root = format!("root{}.com", i);
+            sink.write(&result(format!("www.{}", root), root))
+                .await
+                .unwrap();
+        }
+        assert_eq!(sink.files.len(), MAX_OPEN_ROOT_FILES);
+        sink.write(&result("api.root0.com".into(), "root0.com".into()))
+            .await
+            .unwrap();
+        sink.finalize(&Summary::default()).await.unwrap();
+
+        let written = std::fs::read_to_string(dir.join("root0.com.txt")).unwrap();
+        assert_eq!(written, "www.root0.com\0api.root0.com\0");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn appends_to_existing_files() {
+        let path = temp_path("append.txt");
+        std::fs::write(&path, "api.hackerone.com\n").unwrap();
+
+        let mut sink = LineSink::append(&path).unwrap();
+        sink.write(&Subdomain::new("www.hackerone.com"))
+            .await
+            .unwrap();
+        sink.finalize(&Summary::default()).await.unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written, "api.hackerone.com\nwww.hackerone.com\n");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn parses_output_specs() {
+        let spec = OutputSpec::from_str("jsonl:results.json").unwrap();
+        assert_eq!(spec.format, Some(Format::Jsonl));
+        assert_eq!(spec.destination, "results.json");
+
+        let spec = OutputSpec::from_str("webhook:https://hooks.example.com/sub").unwrap();
+        assert_eq!(spec.format, Some(Format::Webhook));
+        assert_eq!(spec.destination, "https://hooks.example.com/sub");
+
+        let spec = OutputSpec::from_str("results.txt").unwrap();
+        assert_eq!(spec.format, None);
+        assert_eq!(spec.destination, "results.txt");
+
+        assert!(OutputSpec::from_str("text:").is_err());
+        assert!(OutputSpec::from_str("webhook:-").is_err());
+        assert!(OutputSpec::from_str("roots:-").is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use crate::http::Client;
+use crate::sources::Source;
+use serde::Deserialize;
+
+/// The name rules in robots.txt are matched against
+pub const AGENT: &str = "subdomaingather";
+
+/// Sources which read a website's own pages, or the private endpoints its frontend uses,
+/// rather than a published api, along with the path they request
+const SCRAPED: &[(Source, &str)] = &[
+    (Source::Crtsh, "/?q="),
+    (Source::VirusTotal, "/ui/domains/"),
+];
+
+/// Whether `source` scrapes a website rather than using a published api
+pub fn is_scraper(source: Source) -> bool {
+    SCRAPED.iter().any(|(s, _)| *s == source)
+}
+
+/// Every source which scrapes a website
+pub fn scrapers() -> Vec<Source> {
+    SCRAPED.iter().map(|(source, _)| *source).collect()
+}
+
+/// What sources which scrape websites are allowed to do, read from the `[policy]` section of
+/// the config file. Sources with a published api aren't affected.
+///
+/// ```toml
+/// [policy]
+/// # skip every scraping source, for organisations which only approve api use
+/// api_only = false
+/// allow = ["crtsh"]
+/// deny = ["virustotal"]
+/// robots = true
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ScrapePolicy {
+    /// Skips every scraping source
+    pub api_only: bool,
+    /// The only scraping sources used when set, by name in any case
+    pub allow: Vec<String>,
+    /// Scraping sources which are never used, by name in any case
+    pub deny: Vec<String>,
+    /// Checks each scraping source's robots.txt before the run, skipping those which
+    /// disallow the path they request
+    pub robots: bool,
+}
+
+impl ScrapePolicy {
+    /// Whether the policy lets `source` be queried
+    pub fn permits(&self, source: Source) -> bool {
+        if !is_scraper(source) {
+            return true;
+        }
+        let named = |names: &[String]| names.iter().any(|n| Source::from_name(n) == Some(source));
+
+        !self.api_only && !named(&self.deny) && (self.allow.is_empty() || named(&self.allow))
+    }
+
+    /// Names in `allow` or `deny` which don't match a source
+    pub fn unknown(&self) -> Vec<String> {
+        self.allow
+            .iter()
+            .chain(self.deny.iter())
+            .filter(|n| Source::from_name(n).is_none())
+            .cloned()
+            .collect()
+    }
+}
+
+/// Fetches the robots.txt of the site `source` scrapes, returning why it may not be
+/// scraped if it can't. A missing robots.txt allows everything, one which can't be read
+/// for any other reason allows nothing.
+pub async fn check_robots(client: &Client, source: Source) -> Option<String> {
+    let path = SCRAPED.iter().find(|(s, _)| *s == source)?.1;
+    let host = source.endpoint()?;
+
+    let url = format!("https://{}/robots.txt", host);
+    let res = match client.get(&url).send().await {
+        Ok(res) => res,
+        Err(e) => return Some(format!("couldn't fetch {} got {}", url, e)),
+    };
+    let status = res.status();
+    if status.is_client_error() {
+        return None;
+    }
+    if !status.is_success() {
+        return Some(format!("couldn't fetch {} got {}", url, status));
+    }
+
+    match res.text().await {
+        Ok(robots) if disallows(&robots, AGENT, path) => {
+            Some(format!("{} disallows {}", url, path))
+        }
+        Ok(_) => None,
+        Err(e) => Some(format!("couldn't read {} got {}", url, e)),
+    }
+}
+
+/// Whether the rules in `robots` stop `agent` from fetching `path`. A group naming the agent
+/// is used in place of the `*` group, and within it the longest rule matching the start of
+/// the path wins, with `Allow` winning ties. Wildcards within rules aren't supported.
+pub fn disallows(robots: &str, agent: &str, path: &str) -> bool {
+    let agent = agent.to_lowercase();
+    let names_agent = |name: &str| !name.is_empty() && name != "*" && agent.contains(name);
+    let mut named: Vec<(bool, String)> = Vec::new();
+    let mut any: Vec<(bool, String)> = Vec::new();
+    let mut is_named = false;
+
+    // consecutive user-agent lines share the rules which follow them
+    let mut agents: Vec<String> = Vec::new();
+    let mut in_rules = false;
+    for line in robots.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+            None => continue,
+        };
+
+        match key.as_str() {
+            "user-agent" => {
+                if in_rules {
+                    agents.clear();
+                    in_rules = false;
+                }
+                let name = value.to_lowercase();
+                is_named |= names_agent(&name);
+                agents.push(name);
+            }
+            "allow" | "disallow" => {
+                in_rules = true;
+                // an empty disallow allows everything, which is the default anyway
+                if value.is_empty() {
+                    continue;
+                }
+                let rule = (key == "allow", value.trim_end_matches('*').to_string());
+                if agents.iter().any(|a| names_agent(a)) {
+                    named.push(rule.clone());
+                }
+                if agents.iter().any(|a| a == "*") {
+                    any.push(rule);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let rules = if is_named { named } else { any };
+    rules
+        .iter()
+        .filter(|(_, prefix)| path.starts_with(prefix.as_str()))
+        .max_by_key(|(allow, prefix)| (prefix.len(), *allow))
+        .map_or(false, |(allow, _)| !allow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permits_scrapers_by_policy() {
+        let policy = ScrapePolicy {
+            deny: vec!["virustotal".into()],
+            ..ScrapePolicy::default()
+        };
+        assert!(policy.permits(Source::Crtsh));
+        assert!(!policy.permits(Source::VirusTotal));
+        assert!(policy.permits(Source::AlienVault));
+
+        let policy = ScrapePolicy {
+            api_only: true,
+            allow: vec!["crtsh".into(), "nope".into()],
+            ..ScrapePolicy::default()
+        };
+        assert!(!policy.permits(Source::Crtsh));
+        assert!(policy.permits(Source::Wayback));
+        assert_eq!(policy.unknown(), vec!["nope"]);
+    }
+
+    #[test]
+    fn reads_robots_rules() {
+        let robots = "User-agent: *\nDisallow: /ui/\nAllow: /ui/domains/\n\n\
+                      User-agent: BadBot\nUser-agent: subdomaingather\nDisallow: /\n";
+        assert!(disallows(robots, AGENT, "/?q="));
+        assert!(!disallows(robots, "other", "/ui/domains/"));
+        assert!(disallows(robots, "other", "/ui/search"));
+        assert!(!disallows(robots, "other", "/?q="));
+        assert!(!disallows("User-agent: *\nDisallow:\n", AGENT, "/?q="));
+    }
+}
@@ -8,6 +8,8 @@ enum Filter {
     SubOnly,
     /// Return any result that has the same root domain
     RootOnly,
+    /// Return any result that is one of the hosts or beneath them
+    Within,
 }
 
 impl Default for Filter {
@@ -20,6 +22,8 @@ impl Default for Filter {
 #[derive(Default)]
 pub struct PostProcessor {
     roots: HashSet<String>,
+    excluded: HashSet<String>,
+    known: HashSet<String>,
     filter: Filter,
 }
 
@@ -41,17 +45,59 @@ impl PostProcessor {
         self
     }
 
+    /// Sets the `PostProcessor` to return any result which is one of `hosts` or beneath them,
+    /// for runs which enumerate the whole registrable domain but only want part of it
+    pub fn within<I: IntoIterator<Item = String>>(&mut self, hosts: I) -> &mut Self {
+        self.roots.extend(hosts);
+        self.filter = Filter::Within;
+        self
+    }
+
+    /// Drops any result which is one of `hosts` or beneath them, e.g. out of scope hosts.
+    /// Hosts are cleaned like results, so they match however they're cased.
+    pub fn exclude<I: IntoIterator<Item = String>>(&mut self, hosts: I) -> &mut Self {
+        let hosts = hosts.into_iter().map(|h| Self::strip_invalid(h.trim()));
+        self.excluded.extend(hosts);
+        self
+    }
 
+    /// Drops any result which is exactly one of `hosts`, e.g. those already in an inventory.
+    /// Unlike `exclude` hosts beneath them are still kept.
+    pub fn known<I, S>(&mut self, hosts: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let hosts = hosts
+            .into_iter()
+            .map(|h| Self::strip_invalid(h.as_ref().trim()));
+        self.known.extend(hosts);
+        self
+    }
+
+    fn is_excluded(&self, result: &str) -> bool {
+        self.known.contains(result)
+            || self
+                .excluded
+                .iter()
+                .any(|e| result == e || is_beneath(result, e))
+    }
+
+    /// Removes the quotes, escapes and wildcards sources wrap names in, along with any leading
+    /// or trailing dots, and lowercases what's left. Cleaning a name twice changes nothing.
     fn strip_invalid<T: AsRef<str> + std::fmt::Display>(domain: T) -> String {
         domain
             .as_ref()
-            .strip_prefix('.')
-            .unwrap_or_else(|| domain.as_ref())
             .replace(&['"', '\"', '\\', '*'][..], "")
+            .trim_matches('.')
             .to_lowercase()
     }
 
     fn is_relevant<T: AsRef<str>>(&self, result: T) -> bool {
+        if self.is_excluded(result.as_ref()) || !is_hostname(result.as_ref()) {
+            return false;
+        }
+
         match self.filter {
             Filter::RootOnly => {
                 if let Ok(d) = result.as_ref().parse::<DomainName>() {
@@ -63,9 +109,60 @@ impl PostProcessor {
             Filter::SubOnly => self
                 .roots
                 .iter()
-                .any(|root| result.as_ref().ends_with(root) && !result.as_ref().eq(root)),
+                .any(|root| is_beneath(result.as_ref(), root)),
+            Filter::Within => self
+                .roots
+                .iter()
+                .any(|root| result.as_ref() == root || is_beneath(result.as_ref(), root)),
         }
     }
+
+    /// Like `is_relevant`, but only against `origin`, the root the result was returned for,
+    /// so a result for one root which happens to mention another isn't kept because of it.
+    /// Falls back to every root when `origin` isn't one of them.
+    fn is_relevant_to<T: AsRef<str>>(&self, result: T, origin: &str) -> bool {
+        let result = result.as_ref();
+        if self.is_excluded(result) || !is_hostname(result) {
+            return false;
+        }
+
+        match self.filter {
+            Filter::RootOnly => {
+                let root = |d: &str| d.parse::<DomainName>().ok().map(|d| d.root().to_string());
+                match root(origin).filter(|o| self.roots.contains(o)) {
+                    Some(origin) => root(result).map_or(false, |r| r == origin),
+                    None => self.is_relevant(result),
+                }
+            }
+            Filter::SubOnly if self.roots.contains(origin) => is_beneath(result, origin),
+            Filter::SubOnly => self.is_relevant(result),
+            Filter::Within if self.roots.contains(origin) => {
+                result == origin || is_beneath(result, origin)
+            }
+            Filter::Within => self.is_relevant(result),
+        }
+    }
+}
+
+/// Whether `name` is a subdomain of `root`, e.g. `api.hackerone.com` of `hackerone.com` but
+/// not `evilhackerone.com`
+fn is_beneath(name: &str, root: &str) -> bool {
+    name.len() > root.len()
+        && name.ends_with(root)
+        && name[..name.len() - root.len()].ends_with('.')
+}
+
+/// Whether a cleaned name could be a hostname, dot separated labels of lowercase letters,
+/// digits, `-` and `_`
+fn is_hostname(name: &str) -> bool {
+    name.len() <= 253
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_')
+        })
 }
 
 pub struct PostProcessorIter<'a, I>
@@ -74,6 +171,7 @@ where
 {
     cleaner: &'a PostProcessor,
     inner: I,
+    origin: Option<&'a str>,
 }
 
 impl<'a, I> Iterator for PostProcessorIter<'a, I>
@@ -86,7 +184,11 @@ where
     fn next(&mut self) -> Option<Self::Item> {
         while let Some(d) = self.inner.next() {
             let cleaned = PostProcessor::strip_invalid(d.as_ref());
-            if self.cleaner.is_relevant(&cleaned) {
+            let relevant = match self.origin {
+                Some(origin) => self.cleaner.is_relevant_to(&cleaned, origin),
+                None => self.cleaner.is_relevant(&cleaned),
+            };
+            if relevant {
                 return Some(cleaned);
             }
         }
@@ -103,8 +205,163 @@ pub trait CleanExt: Iterator {
         PostProcessorIter {
             cleaner: postprocessor,
             inner: self,
+            origin: None,
+        }
+    }
+
+    /// Like `clean`, but only keeps results which belong to `origin`, the root they were
+    /// returned for
+    fn clean_for<'a>(
+        self,
+        postprocessor: &'a PostProcessor,
+        origin: &'a str,
+    ) -> PostProcessorIter<'a, Self>
+    where
+        Self::Item: Hash + Eq + AsRef<str>,
+        Self: Sized,
+    {
+        PostProcessorIter {
+            cleaner: postprocessor,
+            inner: self,
+            origin: Some(origin),
         }
     }
 }
 
 impl<I: Iterator> CleanExt for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const ROOTS: &[&str] = &["hackerone.com", "api.hackerone.com", "bbc.co.uk"];
+
+    /// Names which are mostly beneath one of `ROOTS`, with the junk sources return mixed in
+    fn names() -> impl Strategy<Value = String> {
+        prop_oneof![
+            ("[a-zA-Z0-9*\"\\\\._ -]{0,24}", prop::sample::select(ROOTS))
+                .prop_map(|(prefix, root)| format!("{}.{}", prefix, root)),
+            "[a-zA-Z0-9*\"\\\\._ -]{0,40}",
+            any::<String>(),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn cleaning_is_idempotent(name in names()) {
+            let once = PostProcessor::strip_invalid(&name);
+            prop_assert_eq!(PostProcessor::strip_invalid(&once), once);
+        }
+
+        #[test]
+        fn kept_results_belong_to_a_root(
+            results in prop::collection::vec(names(), 0..32),
+            subs_only: bool,
+        ) {
+            let roots: Vec<String> = ROOTS.iter().map(|r| r.to_string()).collect();
+            let mut cleaner = PostProcessor::default();
+            if subs_only {
+                cleaner.any_subdomain(roots);
+            } else {
+                cleaner.any_root(roots);
+            }
+
+            for kept in results.iter().clean(&cleaner) {
+                prop_assert!(is_hostname(&kept), "{} isn't a hostname", kept);
+                prop_assert!(
+                    cleaner
+                        .roots
+                        .iter()
+                        .any(|r| is_beneath(&kept, r) || (!subs_only && kept == *r)),
+                    "{} isn't beneath any root",
+                    kept
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn filters_against_the_originating_root() {
+        let roots = vec!["hackerone.com".to_string(), "google.com".to_string()];
+        let mut cleaner = PostProcessor::default();
+        cleaner.any_root(roots.clone());
+
+        let results = vec!["api.hackerone.com", "mail.google.com"];
+        let all: Vec<String> = results.iter().clean(&cleaner).collect();
+        assert_eq!(all.len(), 2);
+        let mine: Vec<String> = results
+            .iter()
+            .clean_for(&cleaner, "hackerone.com")
+            .collect();
+        assert_eq!(mine, vec!["api.hackerone.com"]);
+
+        let mut cleaner = PostProcessor::default();
+        cleaner.any_subdomain(vec![
+            "api.hackerone.com".to_string(),
+            "hackerone.com".into(),
+        ]);
+        let results = vec![
+            "dev.api.hackerone.com",
+            "www.hackerone.com",
+            "evilapi.hackerone.com",
+        ];
+        let mine: Vec<String> = results
+            .iter()
+            .clean_for(&cleaner, "api.hackerone.com")
+            .collect();
+        assert_eq!(mine, vec!["dev.api.hackerone.com"]);
+
+        // results for a host which wasn't asked for are checked against every root
+        let other: Vec<String> = results.iter().clean_for(&cleaner, "example.com").collect();
+        assert_eq!(other.len(), 3);
+    }
+
+    #[test]
+    fn filters_within_hosts() {
+        let mut cleaner = PostProcessor::default();
+        cleaner.within(vec!["api.hackerone.com".to_string()]);
+
+        // results come back for the registrable domain, which isn't one of the hosts
+        let results = vec![
+            "api.hackerone.com",
+            "dev.api.hackerone.com",
+            "www.hackerone.com",
+        ];
+        let kept: Vec<String> = results
+            .iter()
+            .clean_for(&cleaner, "hackerone.com")
+            .collect();
+        assert_eq!(kept, vec!["api.hackerone.com", "dev.api.hackerone.com"]);
+    }
+
+    #[test]
+    fn drops_known_results() {
+        let mut cleaner = PostProcessor::default();
+        cleaner.any_root(vec!["hackerone.com".to_string()]);
+        cleaner.known(vec!["API.hackerone.com", "hackerone.com."]);
+
+        let results = vec![
+            "api.hackerone.com",
+            "dev.api.hackerone.com",
+            "hackerone.com",
+        ];
+        let new: Vec<String> = results.iter().clean(&cleaner).collect();
+        assert_eq!(new, vec!["dev.api.hackerone.com"]);
+    }
+
+    #[test]
+    fn excludes_hosts_ignoring_case() {
+        let mut cleaner = PostProcessor::default();
+        cleaner.any_root(vec!["hackerone.com".to_string()]);
+        cleaner.exclude(vec!["Corp.HackerOne.com".to_string()]);
+
+        let results = vec![
+            "corp.hackerone.com",
+            "vpn.corp.hackerone.com",
+            "api.hackerone.com",
+        ];
+        let kept: Vec<String> = results.iter().clean(&cleaner).collect();
+        assert_eq!(kept, vec!["api.hackerone.com"]);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::error::{Result, SubError};
+use crate::sources::Source;
+use futures::stream::{FuturesUnordered, StreamExt};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::net::{self, TcpStream};
+use tokio::{task, time};
+use tracing::debug;
+
+/// Every source endpoint is served over https
+const PORT: u16 = 443;
+
+/// Resolves the endpoint of each source and opens a TCP connection to it from `local`, the
+/// address the run's connections are bound to, so dead providers can be reported before a
+/// large run instead of timing out for every root. Returns the sources which couldn't be
+/// reached, sources without a fixed endpoint are skipped.
+pub async fn check(
+    sources: &[Source],
+    timeout: Duration,
+    local: Option<IpAddr>,
+) -> Vec<(Source, SubError)> {
+    let mut checks: FuturesUnordered<_> = sources
+        .iter()
+        .filter_map(|s| s.endpoint().map(|host| (*s, host)))
+        .map(|(source, host)| async move { (source, reach(host, timeout, local).await) })
+        .collect();
+
+    let mut unreachable = Vec::new();
+    while let Some((source, res)) = checks.next().await {
+        match res {
+            Ok(()) => debug!("{} is reachable", source),
+            Err(e) => unreachable.push((source, e)),
+        }
+    }
+
+    unreachable.sort_by(|a, b| a.0.cmp(&b.0));
+    unreachable
+}
+
+async fn reach(host: &str, timeout: Duration, local: Option<IpAddr>) -> Result<()> {
+    let unreachable = |reason: String| SubError::Unreachable(host.into(), reason);
+    let check = async {
+        // only addresses of the bound address's family can be connected to
+        let addr = net::lookup_host((host, PORT))
+            .await
+            .map_err(|e| unreachable(format!("lookup failed: {}", e)))?
+            .find(|addr| local.map_or(true, |l| l.is_ipv4() == addr.is_ipv4()))
+            .ok_or_else(|| unreachable("no addresses found".into()))?;
+
+        connect(addr, local, timeout)
+            .await
+            .map_err(|e| unreachable(e.to_string()))
+    };
+
+    time::timeout(timeout, check)
+        .await
+        .map_err(|_| unreachable("timed out".into()))?
+}
+
+/// Opens a TCP connection to `addr`, bound to `local` when it's given the way reqwest binds
+/// the run's connections. tokio can't bind a socket before connecting it, so bound
+/// connections are made on the blocking pool.
+pub(crate) async fn connect(
+    addr: SocketAddr,
+    local: Option<IpAddr>,
+    timeout: Duration,
+) -> io::Result<()> {
+    let local = match local {
+        Some(local) => local,
+        None => {
+            return match time::timeout(timeout, TcpStream::connect(addr)).await {
+                Ok(res) => res.map(|_| ()),
+                Err(_) => Err(io::ErrorKind::TimedOut.into()),
+            }
+        }
+    };
+
+    task::spawn_blocking(move || {
+        let domain = if addr.is_ipv4() {
+            Domain::ipv4()
+        } else {
+            Domain::ipv6()
+        };
+        let socket = Socket::new(domain, Type::stream(), Some(Protocol::tcp()))?;
+        socket.bind(&SocketAddr::new(local, 0).into())?;
+        socket.connect_timeout(&addr.into(), timeout)
+    })
+    .await
+    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[ignore]
+    #[tokio::test]
+    async fn reaches_source_endpoints() {
+        let unreachable = check(&[Source::Crtsh], Duration::from_secs(10), None).await;
+        assert!(unreachable.is_empty());
+    }
+}
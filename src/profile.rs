@@ -0,0 +1,129 @@
+use serde::Deserialize;
+use strum_macros::{Display, EnumString};
+
+/// Named bundles of source selection and timeouts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display)]
+pub enum Profile {
+    /// Free sources, skipping slow ones like Wayback, with short timeouts
+    #[strum(to_string = "fast", serialize = "passive-fast")]
+    Fast,
+    /// Every free source with generous timeouts
+    #[strum(to_string = "thorough", serialize = "passive-thorough")]
+    Thorough,
+    /// Every source including those which need API keys
+    #[strum(to_string = "full")]
+    Full,
+}
+
+/// The settings a profile applies to the `Runner`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileSettings {
+    pub all_sources: bool,
+    pub exclude: Vec<String>,
+    pub timeout: u64,
+    pub concurrency: usize,
+    /// Stops each source once it has returned this many results for a root, which caps how
+    /// many pages paginated sources fetch
+    pub max_results: Option<usize>,
+}
+
+/// Overrides for a profile read from the config file, unset fields keep the built in value
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ProfileOverride {
+    pub all_sources: Option<bool>,
+    pub exclude: Option<Vec<String>>,
+    /// Seconds, or a duration like `"30s"`
+    #[serde(deserialize_with = "crate::units::seconds")]
+    pub timeout: Option<u64>,
+    pub concurrency: Option<usize>,
+    pub max_results: Option<usize>,
+}
+
+impl Profile {
+    /// The built in settings for the profile
+    pub fn settings(self) -> ProfileSettings {
+        match self {
+            Profile::Fast => ProfileSettings {
+                all_sources: false,
+                exclude: vec!["Wayback".into(), "SonarSearch".into()],
+                timeout: 10,
+                concurrency: 200,
+                max_results: Some(1000),
+            },
+            Profile::Thorough => ProfileSettings {
+                all_sources: false,
+                exclude: Vec::new(),
+                timeout: 30,
+                concurrency: 200,
+                max_results: None,
+            },
+            Profile::Full => ProfileSettings {
+                all_sources: true,
+                exclude: Vec::new(),
+                timeout: 60,
+                concurrency: 200,
+                max_results: None,
+            },
+        }
+    }
+}
+
+impl ProfileSettings {
+    /// Replaces any setting which is set in `overrides`
+    pub fn merge(mut self, overrides: &ProfileOverride) -> Self {
+        if let Some(all) = overrides.all_sources {
+            self.all_sources = all;
+        }
+
+        if let Some(exclude) = &overrides.exclude {
+            self.exclude = exclude.clone();
+        }
+
+        if let Some(timeout) = overrides.timeout {
+            self.timeout = timeout;
+        }
+
+        if let Some(concurrency) = overrides.concurrency {
+            self.concurrency = concurrency;
+        }
+
+        if let Some(max) = overrides.max_results {
+            self.max_results = Some(max);
+        }
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn parses_aliases() {
+        assert_eq!(Profile::from_str("fast").unwrap(), Profile::Fast);
+        assert_eq!(Profile::from_str("passive-fast").unwrap(), Profile::Fast);
+        assert_eq!(
+            Profile::from_str("passive-thorough").unwrap(),
+            Profile::Thorough
+        );
+        assert!(Profile::from_str("slow").is_err());
+    }
+
+    #[test]
+    fn merges_overrides() {
+        let overrides = ProfileOverride {
+            timeout: Some(5),
+            exclude: Some(vec!["Crtsh".into()]),
+            ..ProfileOverride::default()
+        };
+
+        let settings = Profile::Fast.settings().merge(&overrides);
+        assert_eq!(settings.timeout, 5);
+        assert_eq!(settings.exclude, vec!["Crtsh".to_string()]);
+        assert_eq!(settings.max_results, Some(1000));
+        assert!(!settings.all_sources);
+    }
+}
@@ -0,0 +1,296 @@
+use crate::error::{Result, SubError};
+use crate::sources::Source;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// How many requests a source has made during a run, how many it's allowed to make, and how
+/// many bytes of responses it has downloaded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub requests: usize,
+    pub max_requests: Option<usize>,
+    pub bytes: u64,
+}
+
+impl Usage {
+    /// Whether the source has used its whole budget
+    pub fn exhausted(&self) -> bool {
+        self.max_requests.map_or(false, |max| self.requests >= max)
+    }
+}
+
+/// Request budgets per source, shared by every client created from the same `Runner` so
+/// users on limited API plans can cap how much a single run uses.
+#[derive(Clone, Default)]
+pub struct Quotas {
+    inner: Arc<Mutex<HashMap<Source, Usage>>>,
+    throttle: Arc<Mutex<Option<Throttle>>>,
+    pacing: Arc<Mutex<Option<Pacing>>>,
+    // seeds the gaps of `pace` when set, rather than the clock
+    seed: Arc<Mutex<Option<u64>>>,
+}
+
+/// A bucket of bytes which refills at the bandwidth ceiling, holding at most a second's worth
+/// so an idle period can't be spent all at once
+#[derive(Debug)]
+struct Throttle {
+    rate: u64,
+    available: f64,
+    updated: Instant,
+}
+
+impl Throttle {
+    fn new(rate: u64) -> Self {
+        Self {
+            rate,
+            available: rate as f64,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Takes `bytes` from the bucket, returning how long to wait for it to be refilled when
+    /// it's overdrawn
+    fn consume(&mut self, bytes: usize, now: Instant) -> Option<Duration> {
+        let refilled = now.saturating_duration_since(self.updated).as_secs_f64() * self.rate as f64;
+        self.available = (self.available + refilled).min(self.rate as f64) - bytes as f64;
+        self.updated = now;
+
+        if self.available < 0.0 {
+            Some(Duration::from_secs_f64(-self.available / self.rate as f64))
+        } else {
+            None
+        }
+    }
+}
+
+/// Spaces out the requests of some sources, with each gap picked at random between half and
+/// one and a half intervals so requests don't arrive like clockwork
+#[derive(Debug)]
+struct Pacing {
+    interval: Duration,
+    // when each paced source may send its next request
+    next: HashMap<Source, Instant>,
+    rng: u64,
+}
+
+impl Pacing {
+    fn new(sources: &[Source], interval: Duration, seed: u64, now: Instant) -> Self {
+        Self {
+            interval,
+            next: sources.iter().map(|s| (*s, now)).collect(),
+            rng: seed,
+        }
+    }
+
+    /// Takes the next slot for a request from `source`, returning how long to wait for it.
+    /// Sources which aren't paced never wait.
+    fn reserve(&mut self, source: Source, now: Instant) -> Option<Duration> {
+        let gap = self.interval.mul_f64(0.5 + self.random());
+        let next = self.next.get_mut(&source)?;
+        let slot = (*next).max(now);
+        *next = slot + gap;
+
+        Some(slot - now).filter(|wait| *wait > Duration::from_secs(0))
+    }
+
+    /// A number in `[0, 1)`, from splitmix64
+    fn random(&mut self) -> f64 {
+        self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl Quotas {
+    /// Limits `source` to `max` requests
+    pub fn limit(&self, source: Source, max: usize) {
+        let mut usage = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        usage.entry(source).or_default().max_requests = Some(max);
+    }
+
+    /// Records a request for `source`, failing with `SubError::QuotaExceeded` once its budget
+    /// has been used up.
+    pub(crate) fn acquire(&self, source: Source) -> Result<()> {
+        let mut usage = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        let usage = usage.entry(source).or_default();
+        if usage.exhausted() {
+            warn!(
+                "{} has used its budget of {} requests",
+                source, usage.requests
+            );
+            return Err(SubError::QuotaExceeded(source.to_string(), usage.requests));
+        }
+
+        usage.requests += 1;
+        Ok(())
+    }
+
+    /// Caps how many bytes per second every source together downloads, responses are read
+    /// more slowly once it's reached. The ceiling is shared by every clone.
+    pub fn max_bandwidth(&self, bytes_per_second: u64) {
+        let mut throttle = self.throttle.lock().unwrap_or_else(PoisonError::into_inner);
+        *throttle = Some(Throttle::new(bytes_per_second));
+    }
+
+    /// The bandwidth ceiling in bytes per second, if one is set
+    pub fn bandwidth(&self) -> Option<u64> {
+        let throttle = self.throttle.lock().unwrap_or_else(PoisonError::into_inner);
+        throttle.as_ref().map(|t| t.rate)
+    }
+
+    /// Records `bytes` of a response `source` downloaded, returning how long to wait before
+    /// reading more to stay under the bandwidth ceiling
+    pub(crate) fn record(&self, source: Option<Source>, bytes: usize) -> Option<Duration> {
+        if let Some(source) = source {
+            let mut usage = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+            usage.entry(source).or_default().bytes += bytes as u64;
+        }
+
+        let mut throttle = self.throttle.lock().unwrap_or_else(PoisonError::into_inner);
+        throttle
+            .as_mut()
+            .and_then(|t| t.consume(bytes, Instant::now()))
+    }
+
+    /// Spaces the requests of each of `sources` about `interval` apart, e.g. sources which
+    /// scrape websites. Every clone waits on the same schedule.
+    pub fn pace(&self, sources: &[Source], interval: Duration) {
+        let seed = *self.seed.lock().unwrap_or_else(PoisonError::into_inner);
+        let seed = seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64)
+        });
+        let mut pacing = self.pacing.lock().unwrap_or_else(PoisonError::into_inner);
+        *pacing = Some(Pacing::new(sources, interval, seed, Instant::now()));
+    }
+
+    /// Derives the random gaps of `pace` from `seed`, so runs with the same seed wait the same
+    /// way. Applies to pacing set before or after.
+    pub fn seed(&self, seed: u64) {
+        *self.seed.lock().unwrap_or_else(PoisonError::into_inner) = Some(seed);
+        let mut pacing = self.pacing.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(pacing) = pacing.as_mut() {
+            pacing.rng = seed;
+        }
+    }
+
+    /// How long a request from `source` has to wait to keep to its pace
+    pub(crate) fn wait(&self, source: Source) -> Option<Duration> {
+        let mut pacing = self.pacing.lock().unwrap_or_else(PoisonError::into_inner);
+        pacing
+            .as_mut()
+            .and_then(|p| p.reserve(source, Instant::now()))
+    }
+
+    /// The usage of every source which made a request or has a budget, ordered by source
+    pub fn usage(&self) -> Vec<(Source, Usage)> {
+        let usage = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut usage: Vec<(Source, Usage)> = usage.iter().map(|(s, u)| (*s, *u)).collect();
+        usage.sort_by(|a, b| a.0.cmp(&b.0));
+        usage
+    }
+
+    /// Every source which has used its whole budget, ordered by source
+    pub fn exhausted(&self) -> Vec<Source> {
+        self.usage()
+            .into_iter()
+            .filter(|(_, u)| u.exhausted())
+            .map(|(s, _)| s)
+            .collect()
+    }
+
+    /// Whether any source has a budget set
+    pub fn is_limited(&self) -> bool {
+        let usage = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        usage.values().any(|u| u.max_requests.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matches::matches;
+
+    #[test]
+    fn enforces_budget() {
+        let quotas = Quotas::default();
+        quotas.limit(Source::VirusTotal, 2);
+        assert!(quotas.acquire(Source::VirusTotal).is_ok());
+        assert!(quotas.acquire(Source::VirusTotal).is_ok());
+        assert!(matches!(
+            quotas.acquire(Source::VirusTotal).err().unwrap(),
+            SubError::QuotaExceeded(_, 2)
+        ));
+
+        assert_eq!(quotas.exhausted(), vec![Source::VirusTotal]);
+
+        // unlimited sources are only counted
+        for _ in 0..5 {
+            assert!(quotas.acquire(Source::Crtsh).is_ok());
+        }
+
+        let usage = quotas.usage();
+        assert_eq!(usage[0].0, Source::Crtsh);
+        assert_eq!(usage[0].1.requests, 5);
+        assert!(usage[1].1.exhausted());
+    }
+
+    #[test]
+    fn paces_requests() {
+        let start = Instant::now();
+        let mut pacing = Pacing::new(&[Source::Crtsh], Duration::from_secs(1), 7, start);
+        assert_eq!(pacing.reserve(Source::Crtsh, start), None);
+        assert_eq!(pacing.reserve(Source::Wayback, start), None);
+
+        // each gap is between half and one and a half intervals
+        let mut waited = Duration::from_secs(0);
+        for _ in 0..10 {
+            let wait = pacing.reserve(Source::Crtsh, start).unwrap();
+            assert!(wait > waited + Duration::from_millis(499));
+            assert!(wait < waited + Duration::from_millis(1501));
+            waited = wait;
+        }
+    }
+
+    #[test]
+    fn seeds_the_pace() {
+        let gaps = |seed: u64| {
+            let quotas = Quotas::default();
+            quotas.pace(&[Source::Crtsh], Duration::from_secs(1));
+            quotas.seed(seed);
+            let mut pacing = quotas.pacing.lock().unwrap();
+            let pacing = pacing.as_mut().unwrap();
+            (0..5).map(|_| pacing.random()).collect::<Vec<f64>>()
+        };
+        assert_eq!(gaps(42), gaps(42));
+        assert_ne!(gaps(42), gaps(43));
+    }
+
+    #[test]
+    fn counts_and_throttles_bandwidth() {
+        let quotas = Quotas::default();
+        assert_eq!(quotas.record(Some(Source::Crtsh), 4096), None);
+        assert_eq!(quotas.record(None, 4096), None);
+        assert_eq!(quotas.usage()[0].1.bytes, 4096);
+
+        let mut throttle = Throttle::new(1000);
+        let start = throttle.updated;
+        assert_eq!(throttle.consume(1000, start), None);
+        assert_eq!(
+            throttle.consume(500, start),
+            Some(Duration::from_millis(500))
+        );
+        // waiting refills the bucket, but never past a second's worth
+        assert_eq!(throttle.consume(500, start + Duration::from_secs(1)), None);
+        assert_eq!(
+            throttle.consume(1500, start + Duration::from_secs(10)),
+            Some(Duration::from_millis(500))
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use crate::error::{Result, SubError};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// The types of record passive DNS sources report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum RecordType {
+    A,
+    Aaaa,
+    Cname,
+    Mx,
+    Ns,
+    Ptr,
+    Soa,
+    Srv,
+    Txt,
+}
+
+impl FromStr for RecordType {
+    type Err = SubError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_uppercase().as_str() {
+            "A" => Ok(RecordType::A),
+            "AAAA" => Ok(RecordType::Aaaa),
+            "CNAME" => Ok(RecordType::Cname),
+            "MX" => Ok(RecordType::Mx),
+            "NS" => Ok(RecordType::Ns),
+            "PTR" => Ok(RecordType::Ptr),
+            "SOA" => Ok(RecordType::Soa),
+            "SRV" => Ok(RecordType::Srv),
+            "TXT" => Ok(RecordType::Txt),
+            _ => Err(SubError::ConfigError(format!(
+                "{} isn't a dns record type",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for RecordType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RecordType::A => "A",
+            RecordType::Aaaa => "AAAA",
+            RecordType::Cname => "CNAME",
+            RecordType::Mx => "MX",
+            RecordType::Ns => "NS",
+            RecordType::Ptr => "PTR",
+            RecordType::Soa => "SOA",
+            RecordType::Srv => "SRV",
+            RecordType::Txt => "TXT",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A record a passive DNS source saw for a name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DnsRecord {
+    #[serde(rename = "type")]
+    pub record_type: RecordType,
+    /// What the record pointed to, e.g. an address for `A` records
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// When the source last saw the record, in the format the source gave it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<String>,
+}
+
+/// The records passive DNS sources returned along with names, keyed by name. Shared by every
+/// source of a run like the request budgets, so results can pick up their records once
+/// they reach the output.
+#[derive(Debug, Clone)]
+pub struct Records {
+    // `None` when records aren't kept
+    inner: Option<Arc<Mutex<HashMap<String, Vec<DnsRecord>>>>>,
+}
+
+impl Default for Records {
+    fn default() -> Self {
+        Self {
+            inner: Some(Arc::default()),
+        }
+    }
+}
+
+impl Records {
+    /// A handle which drops every record, for runs whose output never shows them, so a
+    /// large run doesn't hold every name in memory for nothing
+    pub fn untracked() -> Self {
+        Self { inner: None }
+    }
+
+    /// Records `record` for `name`. A record of the same type and value seen before is only
+    /// kept once, with the latest last seen time.
+    pub fn add(&self, name: &str, record: DnsRecord) {
+        let mut inner = match &self.inner {
+            Some(inner) => inner.lock().unwrap_or_else(PoisonError::into_inner),
+            None => return,
+        };
+        let records = inner.entry(key(name)).or_default();
+        let existing = records
+            .iter_mut()
+            .find(|r| r.record_type == record.record_type && r.value == record.value);
+        match existing {
+            Some(r) if r.last_seen < record.last_seen => r.last_seen = record.last_seen,
+            Some(_) => {}
+            None => records.push(record),
+        }
+    }
+
+    /// The records seen for `name`, ordered by type
+    pub fn get(&self, name: &str) -> Vec<DnsRecord> {
+        let inner = match &self.inner {
+            Some(inner) => inner.lock().unwrap_or_else(PoisonError::into_inner),
+            None => return Vec::new(),
+        };
+        let mut records = inner.get(&key(name)).cloned().unwrap_or_default();
+        records.sort_by(|a, b| a.record_type.cmp(&b.record_type));
+        records
+    }
+}
+
+/// Names are matched the way results are cleaned
+fn key(name: &str) -> String {
+    name.trim().trim_end_matches('.').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(record_type: RecordType, value: &str, last_seen: &str) -> DnsRecord {
+        DnsRecord {
+            record_type,
+            value: Some(value.into()),
+            last_seen: Some(last_seen.into()),
+        }
+    }
+
+    #[test]
+    fn keeps_the_latest_of_each_record() {
+        let records = Records::default();
+        records.add(
+            "WWW.hackerone.com.",
+            record(RecordType::Cname, "h1.net", "2020-01-01"),
+        );
+        records.add(
+            "www.hackerone.com",
+            record(RecordType::A, "1.1.1.1", "2020-01-01"),
+        );
+        records.add(
+            "www.hackerone.com",
+            record(RecordType::A, "1.1.1.1", "2020-06-01"),
+        );
+        records.add(
+            "www.hackerone.com",
+            record(RecordType::A, "1.1.1.1", "2020-03-01"),
+        );
+
+        let seen = records.get("www.hackerone.com");
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[0], record(RecordType::A, "1.1.1.1", "2020-06-01"));
+        assert_eq!(seen[1].record_type, RecordType::Cname);
+        assert!(records.get("api.hackerone.com").is_empty());
+    }
+
+    #[test]
+    fn untracked_records_are_dropped() {
+        let records = Records::untracked();
+        records.add(
+            "www.hackerone.com",
+            record(RecordType::A, "1.1.1.1", "2020-01-01"),
+        );
+        assert!(records.get("www.hackerone.com").is_empty());
+    }
+
+    #[test]
+    fn parses_record_types() {
+        assert_eq!("cname".parse::<RecordType>().unwrap(), RecordType::Cname);
+        assert_eq!(" AAAA".parse::<RecordType>().unwrap(), RecordType::Aaaa);
+        assert!("ANY".parse::<RecordType>().is_err());
+        assert_eq!(RecordType::Aaaa.to_string(), "AAAA");
+    }
+}
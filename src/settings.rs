@@ -0,0 +1,322 @@
+use crate::error::{Result, SubError};
+use crate::http::TlsBackend;
+use crate::policy::ScrapePolicy;
+use crate::profile::ProfileOverride;
+use crate::sources::Source;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Settings read from the config file.
+///
+/// ```toml
+/// [profiles.fast]
+/// exclude = ["Wayback", "UrlScan"]
+/// timeout = 5
+///
+/// [sources]
+/// virustotal.max_requests = 100
+/// wayback.max_response_size = "100MiB"
+/// wayback.timeout = "2m"
+/// securitytrails.enabled = true
+/// crtsh.connect_timeout = 3
+/// urlscan.extra_params = { size = "10000" }
+///
+/// [heuristics]
+/// patterns = ["payroll", "vault"]
+///
+/// [policy]
+/// api_only = true
+///
+/// [tls]
+/// backend = "rustls"
+/// ca_certs = ["/etc/ssl/certs/corporate.pem"]
+///
+/// [telemetry]
+/// endpoint = "https://telemetry.example.com/sub"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Overrides for the built in profiles, keyed by profile name
+    pub profiles: HashMap<String, ProfileOverride>,
+    /// Settings for individual sources, keyed by source name in any case
+    pub sources: HashMap<String, SourceSettings>,
+    /// Extra patterns used to flag interesting results
+    pub heuristics: HeuristicSettings,
+    pub telemetry: TelemetrySettings,
+    /// What sources which scrape websites may do
+    pub policy: ScrapePolicy,
+    pub tls: TlsSettings,
+}
+
+/// Where `--telemetry` sends its reports
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TelemetrySettings {
+    pub endpoint: Option<String>,
+}
+
+/// How connections are secured, flags take precedence over the backend set here
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TlsSettings {
+    pub backend: Option<TlsBackend>,
+    /// PEM files of certificates trusted on top of the backend's roots
+    pub ca_certs: Vec<PathBuf>,
+}
+
+/// Patterns added to the built in set used to flag interesting results
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HeuristicSettings {
+    pub patterns: Vec<String>,
+}
+
+/// Settings for a single source
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SourceSettings {
+    /// The most requests the source may make in a single run
+    pub max_requests: Option<usize>,
+    /// The largest response read from the source, in bytes or as a size like `"100MiB"`
+    #[serde(deserialize_with = "crate::units::bytes")]
+    pub max_response_size: Option<usize>,
+    /// Whether the source is used without `--all`. Sources needing keys are only enabled
+    /// when they're set
+    pub enabled: Option<bool>,
+    /// Seconds requests to the source may take in total, in place of the request timeout.
+    /// Durations like `"2m"` are accepted too
+    #[serde(deserialize_with = "crate::units::seconds")]
+    pub timeout: Option<u64>,
+    /// Seconds connecting to the source may take, in place of the connect timeout
+    #[serde(deserialize_with = "crate::units::seconds")]
+    pub connect_timeout: Option<u64>,
+    /// Query parameters added to every url requested from the source, replacing any the
+    /// source sets itself
+    pub extra_params: BTreeMap<String, toml::Value>,
+}
+
+impl Settings {
+    /// Reads the settings from the file at `path`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let contents = fs::read_to_string(path.as_ref()).map_err(|e| {
+            SubError::ConfigError(format!("couldn't read {:?} got {}", path.as_ref(), e))
+        })?;
+
+        toml::from_str(&contents).map_err(|e| SubError::ConfigError(e.to_string()))
+    }
+
+    /// Reads the settings from `path`, or from the default location if no path is given.
+    /// A missing file at the default location isn't an error.
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        if let Some(p) = path {
+            return Self::from_path(p);
+        }
+
+        match Self::default_path() {
+            Some(p) if p.exists() => {
+                debug!("reading config from {:?}", &p);
+                Self::from_path(p)
+            }
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// The request budget of every source which has one
+    pub fn budgets(&self) -> Result<Vec<(Source, usize)>> {
+        self.per_source(|s| s.max_requests)
+    }
+
+    /// The response size limit of every source which has one
+    pub fn size_limits(&self) -> Result<Vec<(Source, usize)>> {
+        self.per_source(|s| s.max_response_size)
+    }
+
+    /// Whether each source with a policy is used in runs without `--all`
+    pub fn enabled(&self) -> Result<Vec<(Source, bool)>> {
+        self.per_source(|s| s.enabled)
+    }
+
+    /// The request timeout of every source which has its own
+    pub fn timeouts(&self) -> Result<Vec<(Source, u64)>> {
+        self.per_source(|s| s.timeout)
+    }
+
+    /// The connect timeout of every source which has its own
+    pub fn connect_timeouts(&self) -> Result<Vec<(Source, u64)>> {
+        self.per_source(|s| s.connect_timeout)
+    }
+
+    /// The extra query parameters of every source which has any, values which aren't
+    /// strings are written the way they appear in the file
+    pub fn extra_params(&self) -> Result<Vec<(Source, Vec<(String, String)>)>> {
+        self.per_source(|s| {
+            let params: Vec<(String, String)> = s
+                .extra_params
+                .iter()
+                .map(|(k, v)| match v {
+                    toml::Value::String(s) => (k.clone(), s.clone()),
+                    v => (k.clone(), v.to_string()),
+                })
+                .collect();
+            Some(params).filter(|p| !p.is_empty())
+        })
+    }
+
+    fn per_source<T, F>(&self, field: F) -> Result<Vec<(Source, T)>>
+    where
+        F: Fn(&SourceSettings) -> Option<T>,
+    {
+        let mut values = Vec::new();
+        for (name, settings) in self.sources.iter() {
+            let source = Source::from_name(name)
+                .ok_or_else(|| SubError::ConfigError(format!("unknown source {}", name)))?;
+            if let Some(value) = field(settings) {
+                values.push((source, value));
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// `$XDG_CONFIG_HOME/sub/config.toml`, falling back to `$HOME/.config/sub/config.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+            .map(|dir| dir.join("sub").join("config.toml"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_profile_overrides() {
+        let settings: Settings = toml::from_str(
+            r#"
+            [profiles.fast]
+            exclude = ["Wayback", "UrlScan"]
+            timeout = 5
+            "#,
+        )
+        .unwrap();
+
+        let fast = &settings.profiles["fast"];
+        assert_eq!(fast.timeout, Some(5));
+        assert_eq!(fast.concurrency, None);
+        assert_eq!(fast.exclude.as_ref().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn parses_source_budgets() {
+        let settings: Settings = toml::from_str(
+            r#"
+            [sources]
+            virustotal.max_requests = 100
+            Wayback.max_response_size = 1024
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.budgets().unwrap(), vec![(Source::VirusTotal, 100)]);
+        assert_eq!(
+            settings.size_limits().unwrap(),
+            vec![(Source::Wayback, 1024)]
+        );
+    }
+
+    #[test]
+    fn parses_source_policy() {
+        let settings: Settings = toml::from_str(
+            r#"
+            [sources]
+            securitytrails.enabled = true
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            settings.enabled().unwrap(),
+            vec![(Source::SecurityTrails, true)]
+        );
+    }
+
+    #[test]
+    fn parses_scrape_policy() {
+        let settings: Settings = toml::from_str(
+            r#"
+            [policy]
+            deny = ["VirusTotal"]
+            robots = true
+            "#,
+        )
+        .unwrap();
+
+        assert!(!settings.policy.permits(Source::VirusTotal));
+        assert!(!settings.policy.api_only);
+        assert!(settings.policy.robots);
+    }
+
+    #[test]
+    fn parses_tls_settings() {
+        let settings: Settings = toml::from_str(
+            r#"
+            [tls]
+            backend = "rustls"
+            ca_certs = ["corporate.pem"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.tls.backend, Some(TlsBackend::Rustls));
+        assert_eq!(settings.tls.ca_certs, vec![PathBuf::from("corporate.pem")]);
+        assert!(toml::from_str::<Settings>("[tls]\nbackend = \"openssl\"").is_err());
+    }
+
+    #[test]
+    fn parses_source_timeouts() {
+        let settings: Settings = toml::from_str(
+            r#"
+            [sources]
+            wayback.timeout = "2m"
+            crtsh.connect_timeout = 3
+            crtsh.max_response_size = "1KiB"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.timeouts().unwrap(), vec![(Source::Wayback, 120)]);
+        assert_eq!(
+            settings.connect_timeouts().unwrap(),
+            vec![(Source::Crtsh, 3)]
+        );
+        assert_eq!(settings.size_limits().unwrap(), vec![(Source::Crtsh, 1024)]);
+        assert!(toml::from_str::<Settings>("[sources]\ncrtsh.timeout = \"soon\"").is_err());
+    }
+
+    #[test]
+    fn parses_extra_params() {
+        let settings: Settings = toml::from_str(
+            r#"
+            [sources.urlscan]
+            extra_params = { size = "10000", from = 2020 }
+            "#,
+        )
+        .unwrap();
+
+        let params = vec![
+            ("from".to_string(), "2020".to_string()),
+            ("size".to_string(), "10000".to_string()),
+        ];
+        assert_eq!(
+            settings.extra_params().unwrap(),
+            vec![(Source::UrlScan, params)]
+        );
+    }
+}
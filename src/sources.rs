@@ -1,3 +1,6 @@
+use serde::Serialize;
+use strum_macros::{Display, EnumString};
+
 pub mod alienvault;
 pub mod anubisdb;
 pub mod binaryedge;
@@ -18,3 +21,139 @@ pub mod threatminer;
 pub mod urlscan;
 pub mod virustotal;
 pub mod wayback;
+
+/// Every source data can be collected from
+#[derive(
+    Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, EnumString, Display, Serialize,
+)]
+pub enum Source {
+    AlienVault,
+    AnubisDB,
+    BinaryEdge,
+    C99,
+    CertSpotter,
+    Chaos,
+    Crtsh,
+    Facebook,
+    HackerTarget,
+    Intelx,
+    PassiveTotal,
+    SecurityTrails,
+    SonarSearch,
+    Spyse,
+    Sublister,
+    ThreatCrowd,
+    ThreatMiner,
+    UrlScan,
+    VirusTotal,
+    Wayback,
+}
+
+impl Source {
+    pub const ALL: [Source; 20] = [
+        Source::AlienVault,
+        Source::AnubisDB,
+        Source::BinaryEdge,
+        Source::C99,
+        Source::CertSpotter,
+        Source::Chaos,
+        Source::Crtsh,
+        Source::Facebook,
+        Source::HackerTarget,
+        Source::Intelx,
+        Source::PassiveTotal,
+        Source::SecurityTrails,
+        Source::SonarSearch,
+        Source::Spyse,
+        Source::Sublister,
+        Source::ThreatCrowd,
+        Source::ThreatMiner,
+        Source::UrlScan,
+        Source::VirusTotal,
+        Source::Wayback,
+    ];
+
+    /// The host the source's api is served from, `None` when it's configured at runtime
+    pub fn endpoint(&self) -> Option<&'static str> {
+        match self {
+            Source::AlienVault => Some("otx.alienvault.com"),
+            Source::AnubisDB => Some("jldc.me"),
+            Source::BinaryEdge => Some("api.binaryedge.io"),
+            Source::C99 => Some("api.c99.nl"),
+            Source::CertSpotter => Some("api.certspotter.com"),
+            Source::Chaos => Some("dns.projectdiscovery.io"),
+            Source::Crtsh => Some("crt.sh"),
+            Source::Facebook => Some("graph.facebook.com"),
+            Source::HackerTarget => Some("api.hackertarget.com"),
+            Source::Intelx => None,
+            Source::PassiveTotal => Some("api.passivetotal.org"),
+            Source::SecurityTrails => Some("api.securitytrails.com"),
+            Source::SonarSearch => Some("crobat-rpc.omnisint.io"),
+            Source::Spyse => Some("api.spyse.com"),
+            Source::Sublister => Some("api.sublist3r.com"),
+            Source::ThreatCrowd => Some("www.threatcrowd.org"),
+            Source::ThreatMiner => Some("api.threatminer.org"),
+            Source::UrlScan => Some("urlscan.io"),
+            Source::VirusTotal => Some("www.virustotal.com"),
+            Source::Wayback => Some("web.archive.org"),
+        }
+    }
+
+    /// The environment variables the source needs to be set
+    pub fn keys(&self) -> &'static [&'static str] {
+        match self {
+            Source::BinaryEdge => &["BINARYEDGE_TOKEN"],
+            Source::C99 => &["C99_KEY"],
+            Source::Chaos => &["CHAOS_KEY"],
+            Source::Facebook => &["FB_APP_ID", "FB_APP_SECRET"],
+            Source::Intelx => &["INTELX_KEY", "INTELX_URL"],
+            Source::PassiveTotal => &["PASSIVETOTAL_KEY", "PASSIVETOTAL_SECRET"],
+            Source::SecurityTrails => &["SECURITY_TRAILS_KEY"],
+            Source::Spyse => &["SPYSE_TOKEN"],
+            _ => &[],
+        }
+    }
+
+    /// Looks up a source by name ignoring case, so config files can use `virustotal`
+    pub fn from_name(name: &str) -> Option<Source> {
+        Source::ALL
+            .iter()
+            .copied()
+            .find(|s| s.to_string().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Sanitized responses captured from each source, kept in `tests/fixtures` along with what
+/// their parsers are expected to return, so parsers can be checked without the network.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn path(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join(name)
+    }
+
+    /// The captured response `name`
+    pub fn response(name: &str) -> Vec<u8> {
+        fs::read(path(name)).unwrap_or_else(|e| panic!("couldn't read fixture {}: {}", name, e))
+    }
+
+    /// Compares what a parser returned with the golden file `name`, a json array of names.
+    /// Run with `UPDATE_GOLDEN=1` set to rewrite the golden file after an intended change.
+    pub fn assert_golden(name: &str, parsed: Vec<String>) {
+        if env::var_os("UPDATE_GOLDEN").is_some() {
+            let golden = serde_json::to_string_pretty(&parsed).unwrap();
+            fs::write(path(name), golden + "\n").unwrap();
+            return;
+        }
+
+        let golden: Vec<String> = serde_json::from_slice(&response(name))
+            .unwrap_or_else(|e| panic!("{} isn't a json array of names: {}", name, e));
+        assert_eq!(parsed, golden, "parsed names don't match {}", name);
+    }
+}
@@ -1,15 +1,39 @@
 use crate::error::{Result, SubError};
+use crate::http::{decode, Client};
+use crate::records::{DnsRecord, RecordType};
+use crate::sources::Source;
 use crate::{DataSource, IntoSubdomain};
 use async_trait::async_trait;
-use reqwest::Client;
 use serde::Deserialize;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
-use tracing::{info, trace, warn};
+use tracing::{debug, info, trace, warn};
 
 #[derive(Deserialize, Debug)]
 struct Subdomain {
     hostname: String,
+    record_type: Option<String>,
+    address: Option<String>,
+    last: Option<String>,
+}
+
+impl Subdomain {
+    fn record(&self) -> Option<DnsRecord> {
+        let record_type = self.record_type.as_deref()?;
+        let record_type = match record_type.parse::<RecordType>() {
+            Ok(t) => t,
+            Err(_) => {
+                debug!("skipping {} record for {}", record_type, &self.hostname);
+                return None;
+            }
+        };
+
+        Some(DnsRecord {
+            record_type,
+            value: self.address.clone(),
+            last_seen: self.last.clone(),
+        })
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -27,6 +51,28 @@ impl IntoSubdomain for AlienvaultResult {
     }
 }
 
+/// Extracts the hostnames from an AlienVault passive dns response
+pub(crate) fn parse(body: &[u8]) -> Result<Vec<String>> {
+    Ok(parse_records(body)?
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect())
+}
+
+/// Extracts each hostname along with the record it was seen in, if it's of a known type
+pub(crate) fn parse_records(body: &[u8]) -> Result<Vec<(String, Option<DnsRecord>)>> {
+    let resp: AlienvaultResult = decode(Some(Source::AlienVault), body)?;
+    if resp.count == 0 {
+        return Ok(Vec::new());
+    }
+
+    Ok(resp
+        .passive_dns
+        .iter()
+        .map(|s| (s.hostname.to_owned(), s.record()))
+        .collect())
+}
+
 #[derive(Default, Clone)]
 pub struct AlienVault {
     client: Client,
@@ -50,10 +96,16 @@ impl DataSource for AlienVault {
     async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
         trace!("fetching data from alienvault for: {}", &host);
         let uri = self.build_url(&host);
-        let resp: AlienvaultResult = self.client.get(&uri).send().await?.json().await?;
+        let body = self.client.get(&uri).send().await?.bytes().await?;
+        let mut subdomains = Vec::new();
+        for (name, record) in parse_records(&body)? {
+            if let Some(record) = record {
+                self.client.records().add(&name, record);
+            }
+            subdomains.push(name);
+        }
 
-        if resp.count != 0 {
-            let subdomains = resp.subdomains();
+        if !subdomains.is_empty() {
             info!("Discovered {} results for {}", &subdomains.len(), &host);
             let _ = tx.send(subdomains).await;
             return Ok(());
@@ -67,9 +119,30 @@ impl DataSource for AlienVault {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sources::fixtures;
     use matches::matches;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = fixtures::response("alienvault.json");
+        fixtures::assert_golden("alienvault.golden.json", parse(&body).unwrap());
+    }
+
+    #[test]
+    fn parses_records() {
+        let body = fixtures::response("alienvault.json");
+        let records: Vec<DnsRecord> = parse_records(&body)
+            .unwrap()
+            .into_iter()
+            .filter_map(|(_, r)| r)
+            .collect();
+        assert_eq!(records[0].record_type, RecordType::A);
+        assert_eq!(records[0].value.as_deref(), Some("104.16.99.52"));
+        assert_eq!(records[0].last_seen.as_deref(), Some("2020-11-30T08:01:24"));
+        assert!(records.iter().any(|r| r.record_type == RecordType::Cname));
+    }
+
     #[test]
     fn url_builder() {
         let correct_uri = "https://otx.alienvault.com/api/v1/indicators/domain/\
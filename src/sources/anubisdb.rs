@@ -1,7 +1,8 @@
 use crate::error::{Result, SubError};
+use crate::http::{decode, Client};
+use crate::sources::Source;
 use crate::{DataSource, IntoSubdomain};
 use async_trait::async_trait;
-use reqwest::Client;
 use serde_json::value::Value;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
@@ -20,12 +21,23 @@ impl AnubisResult {
 impl IntoSubdomain for AnubisResult {
     fn subdomains(&self) -> Vec<String> {
         match self.results.as_array() {
-            Some(array) => array.iter().map(|s| s.to_string()).collect(),
+            Some(array) => array
+                .iter()
+                .filter_map(|s| s.as_str().map(|s| s.to_string()))
+                .collect(),
             None => Vec::new(),
         }
     }
 }
 
+/// Extracts the names from an AnubisDB response, a json array of hostnames
+pub(crate) fn parse(body: &[u8]) -> Result<Vec<String>> {
+    let resp: Option<Value> = decode(Some(Source::AnubisDB), body)?;
+    Ok(resp
+        .map(|r| AnubisResult::new(r).subdomains())
+        .unwrap_or_default())
+}
+
 #[derive(Default, Clone)]
 pub struct AnubisDB {
     client: Client,
@@ -46,15 +58,13 @@ impl DataSource for AnubisDB {
     async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
         trace!("fetching data from anubisdb for: {}", &host);
         let uri = self.build_url(&host);
-        let resp: Option<Value> = self.client.get(&uri).send().await?.json().await?;
+        let body = self.client.get(&uri).send().await?.bytes().await?;
+        let subdomains = parse(&body)?;
 
-        if let Some(data) = resp {
-            let subdomains = AnubisResult::new(data).subdomains();
-            if !subdomains.is_empty() {
-                info!("Discovered {} results for: {}", &subdomains.len(), &host);
-                let _ = tx.send(subdomains).await;
-                return Ok(());
-            }
+        if !subdomains.is_empty() {
+            info!("Discovered {} results for: {}", &subdomains.len(), &host);
+            let _ = tx.send(subdomains).await;
+            return Ok(());
         }
 
         warn!("No results for {} from AnubisDB", &host);
@@ -65,9 +75,16 @@ impl DataSource for AnubisDB {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sources::fixtures;
     use matches::matches;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = fixtures::response("anubisdb.json");
+        fixtures::assert_golden("anubisdb.golden.json", parse(&body).unwrap());
+    }
+
     #[test]
     fn url_builder() {
         let correct_uri = "https://jldc.me/anubis/subdomains/hackerone.com";
@@ -1,11 +1,10 @@
 use crate::error::Result;
 use crate::error::SubError;
+use crate::http::{decode, Client, Keys};
+use crate::sources::Source;
 use crate::{DataSource, IntoSubdomain};
 use async_trait::async_trait;
-use dotenv::dotenv;
-use reqwest::Client;
 use serde::Deserialize;
-use std::env;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tracing::{info, trace, warn};
@@ -15,17 +14,16 @@ struct Creds {
 }
 
 impl Creds {
-    pub fn read_creds() -> Result<Self> {
-        dotenv().ok();
-        match env::var("BINARYEDGE_TOKEN") {
-            Ok(token) => Ok(Self { token }),
-            Err(_) => Err(SubError::UnsetKeys(vec!["BINARYEDGE_TOKEN".into()])),
+    pub fn read_creds(keys: &Keys) -> Result<Self> {
+        match keys.get("BINARYEDGE_TOKEN") {
+            Some(token) => Ok(Self { token }),
+            None => Err(SubError::UnsetKeys(vec!["BINARYEDGE_TOKEN".into()])),
         }
     }
 }
 
 #[derive(Deserialize)]
-struct BinaryEdgeResponse {
+pub(crate) struct BinaryEdgeResponse {
     page: i32,
     pagesize: i32,
     total: i32,
@@ -38,6 +36,11 @@ impl IntoSubdomain for BinaryEdgeResponse {
     }
 }
 
+/// Parses a page of BinaryEdge results
+pub(crate) fn parse(body: &[u8]) -> Result<BinaryEdgeResponse> {
+    decode(Some(Source::BinaryEdge), body)
+}
+
 #[derive(Default, Clone)]
 pub struct BinaryEdge {
     client: Client,
@@ -114,7 +117,7 @@ async fn next_page(
 ) -> Result<BinaryEdgeResponse> {
     trace!("fetching a page from binaryedge for: {}", &host);
     let uri = BinaryEdge::default().build_url(&host, page);
-    let token = match Creds::read_creds() {
+    let token = match Creds::read_creds(client.keys()) {
         Ok(creds) => creds.token,
         Err(e) => return Err(e),
     };
@@ -122,8 +125,7 @@ async fn next_page(
     let resp = client.get(&uri).header("X-Key", token).send().await?;
 
     if resp.status().is_success() {
-        let be: BinaryEdgeResponse = resp.json().await?;
-        return Ok(be);
+        return parse(&resp.bytes().await?);
     }
 
     info!("binaryedge returned authentication error");
@@ -133,9 +135,16 @@ async fn next_page(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sources::fixtures;
     use matches::matches;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = fixtures::response("binaryedge.json");
+        fixtures::assert_golden("binaryedge.golden.json", parse(&body).unwrap().subdomains());
+    }
+
     // Tests passed locally, ignoring for now.
     #[tokio::test]
     #[ignore]
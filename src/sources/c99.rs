@@ -1,10 +1,9 @@
 use crate::error::{Result, SubError};
+use crate::http::{decode, Client, Keys};
+use crate::sources::Source;
 use crate::{DataSource, IntoSubdomain};
 use async_trait::async_trait;
-use dotenv::dotenv;
-use reqwest::Client;
 use serde::Deserialize;
-use std::env;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tracing::{info, trace, warn};
@@ -14,11 +13,10 @@ struct Creds {
 }
 
 impl Creds {
-    pub fn read_creds() -> Result<Self> {
-        dotenv().ok();
-        match env::var("C99_KEY") {
-            Ok(key) => Ok(Self { key }),
-            Err(_) => Err(SubError::UnsetKeys(vec!["C99_KEY".into()])),
+    pub fn read_creds(keys: &Keys) -> Result<Self> {
+        match keys.get("C99_KEY") {
+            Some(key) => Ok(Self { key }),
+            None => Err(SubError::UnsetKeys(vec!["C99_KEY".into()])),
         }
     }
 }
@@ -43,6 +41,12 @@ impl IntoSubdomain for C99Result {
     }
 }
 
+/// Extracts the names from a C99 subdomain finder response
+pub(crate) fn parse(body: &[u8]) -> Result<Vec<String>> {
+    let resp: C99Result = decode(Some(Source::C99), body)?;
+    Ok(resp.subdomains())
+}
+
 #[derive(Default, Clone)]
 pub struct C99 {
     client: Client,
@@ -65,7 +69,7 @@ impl C99 {
 impl DataSource for C99 {
     async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
         trace!("fetching data from C99 for: {}", &host);
-        let api_key = match Creds::read_creds() {
+        let api_key = match Creds::read_creds(self.client.keys()) {
             Ok(creds) => creds.key,
             Err(e) => return Err(e),
         };
@@ -74,8 +78,7 @@ impl DataSource for C99 {
         let resp = self.client.get(&uri).send().await?;
 
         if resp.status().is_success() {
-            let resp: C99Result = resp.json().await?;
-            let subdomains = resp.subdomains();
+            let subdomains = parse(&resp.bytes().await?)?;
             if !subdomains.is_empty() {
                 info!("Discovered {} results for {}", &subdomains.len(), &host);
                 let _ = tx.send(subdomains).await;
@@ -91,9 +94,16 @@ impl DataSource for C99 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sources::fixtures;
     use matches::matches;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = fixtures::response("c99.json");
+        fixtures::assert_golden("c99.golden.json", parse(&body).unwrap());
+    }
+
     #[ignore]
     #[tokio::test]
     async fn returns_results() {
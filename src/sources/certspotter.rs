@@ -1,7 +1,8 @@
 use crate::error::{Result, SubError};
+use crate::http::{decode, Client};
+use crate::sources::Source;
 use crate::{DataSource, IntoSubdomain};
 use async_trait::async_trait;
-use reqwest::Client;
 use serde::Deserialize;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
@@ -18,6 +19,12 @@ impl IntoSubdomain for Vec<CertSpotterResult> {
     }
 }
 
+/// Extracts the names from a CertSpotter issuances response
+pub(crate) fn parse(body: &[u8]) -> Result<Vec<String>> {
+    let resp: Option<Vec<CertSpotterResult>> = decode(Some(Source::CertSpotter), body)?;
+    Ok(resp.map(|r| r.subdomains()).unwrap_or_default())
+}
+
 #[derive(Default, Clone)]
 pub struct CertSpotter {
     client: Client,
@@ -42,16 +49,13 @@ impl DataSource for CertSpotter {
     async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
         trace!("fetching data from certspotter for: {}", &host);
         let uri = self.build_url(&host);
-        let resp: Option<Vec<CertSpotterResult>> =
-            self.client.get(&uri).send().await?.json().await?;
+        let body = self.client.get(&uri).send().await?.bytes().await?;
+        let subdomains = parse(&body)?;
 
-        if let Some(data) = resp {
-            let subdomains = data.subdomains();
-            if !subdomains.is_empty() {
-                info!("Discovered {} results for: {}", &subdomains.len(), &host);
-                let _ = tx.send(subdomains).await;
-                return Ok(());
-            }
+        if !subdomains.is_empty() {
+            info!("Discovered {} results for: {}", &subdomains.len(), &host);
+            let _ = tx.send(subdomains).await;
+            return Ok(());
         }
 
         warn!("no results for {} from CertSpotter", &host);
@@ -62,9 +66,16 @@ impl DataSource for CertSpotter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sources::fixtures;
     use matches::matches;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = fixtures::response("certspotter.json");
+        fixtures::assert_golden("certspotter.golden.json", parse(&body).unwrap());
+    }
+
     #[test]
     fn url_builder() {
         let correct_uri = "https://api.certspotter.com/v1/issuances?domain=hackerone.com\
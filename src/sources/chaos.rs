@@ -1,11 +1,10 @@
 use crate::error::{Result, SubError};
+use crate::http::{decode, Client, Keys};
+use crate::sources::Source;
 use crate::{DataSource, IntoSubdomain};
 use async_trait::async_trait;
-use dotenv::dotenv;
 use reqwest::header::AUTHORIZATION;
-use reqwest::Client;
 use serde::Deserialize;
-use std::env;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tracing::{info, trace, warn};
@@ -15,11 +14,10 @@ struct Creds {
 }
 
 impl Creds {
-    pub fn read_creds() -> Result<Self> {
-        dotenv().ok();
-        match env::var("CHAOS_KEY") {
-            Ok(key) => Ok(Self { key }),
-            Err(_) => Err(SubError::UnsetKeys(vec!["CHAOS_KEY".into()])),
+    pub fn read_creds(keys: &Keys) -> Result<Self> {
+        match keys.get("CHAOS_KEY") {
+            Some(key) => Ok(Self { key }),
+            None => Err(SubError::UnsetKeys(vec!["CHAOS_KEY".into()])),
         }
     }
 }
@@ -40,6 +38,12 @@ impl IntoSubdomain for ChaosResult {
     }
 }
 
+/// Extracts the names from a Chaos response, which lists labels below the domain
+pub(crate) fn parse(body: &[u8]) -> Result<Vec<String>> {
+    let resp: ChaosResult = decode(Some(Source::Chaos), body)?;
+    Ok(resp.subdomains())
+}
+
 #[derive(Default, Clone)]
 pub struct Chaos {
     client: Client,
@@ -59,7 +63,7 @@ impl Chaos {
 impl DataSource for Chaos {
     async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
         trace!("fetching data from projectdiscovery choas for: {}", &host);
-        let api_key = match Creds::read_creds() {
+        let api_key = match Creds::read_creds(self.client.keys()) {
             Ok(creds) => creds.key,
             Err(e) => return Err(e),
         };
@@ -75,8 +79,7 @@ impl DataSource for Chaos {
             warn!("got status: {} from chaos", resp.status().as_str());
             return Err(SubError::AuthError("Chaos".into()));
         } else {
-            let resp: ChaosResult = resp.json().await?;
-            let subdomains = resp.subdomains();
+            let subdomains = parse(&resp.bytes().await?)?;
             if !subdomains.is_empty() {
                 info!("Discovered {} results for: {}", &subdomains.len(), &host);
                 let _ = tx.send(subdomains).await;
@@ -92,9 +95,16 @@ impl DataSource for Chaos {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sources::fixtures;
     use matches::matches;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = fixtures::response("chaos.json");
+        fixtures::assert_golden("chaos.golden.json", parse(&body).unwrap());
+    }
+
     // Ignore, passed locally.
     #[ignore]
     #[tokio::test]
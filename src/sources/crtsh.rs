@@ -1,7 +1,7 @@
 use crate::error::{Result, SubError};
+use crate::http::{Body, Client};
 use crate::{DataSource, IntoSubdomain};
 use async_trait::async_trait;
-use reqwest::Client;
 use serde::Deserialize;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
@@ -18,6 +18,12 @@ impl IntoSubdomain for Vec<CrtshResult> {
     }
 }
 
+/// Extracts the names from a crt.sh response
+pub(crate) fn parse(body: &Body) -> Result<Vec<String>> {
+    let resp: Option<Vec<CrtshResult>> = body.decode()?;
+    Ok(resp.map(|r| r.subdomains()).unwrap_or_default())
+}
+
 #[derive(Default, Clone)]
 pub struct Crtsh {
     client: Client,
@@ -38,15 +44,14 @@ impl DataSource for Crtsh {
     async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
         trace!("fetching data from crt.sh for: {}", &host);
         let uri = self.build_url(&host);
-        let resp: Option<Vec<CrtshResult>> = self.client.get(&uri).send().await?.json().await?;
+        // every certificate ever logged for the root is returned, large bodies are spooled
+        let body = self.client.get(&uri).send().await?.body().await?;
+        let subdomains = parse(&body)?;
 
-        if let Some(data) = resp {
-            let subdomains = data.subdomains();
-            if !subdomains.is_empty() {
-                info!("Discovered {} results for: {}", subdomains.len(), &host);
-                let _ = tx.send(subdomains).await;
-                return Ok(());
-            }
+        if !subdomains.is_empty() {
+            info!("Discovered {} results for: {}", subdomains.len(), &host);
+            let _ = tx.send(subdomains).await;
+            return Ok(());
         }
 
         warn!("no results for {} from Crt.sh", &host);
@@ -57,9 +62,16 @@ impl DataSource for Crtsh {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sources::{fixtures, Source};
     use matches::matches;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = Body::from_bytes(Some(Source::Crtsh), fixtures::response("crtsh.json"));
+        fixtures::assert_golden("crtsh.golden.json", parse(&body).unwrap());
+    }
+
     #[test]
     fn url_builder() {
         let correct_uri = "https://crt.sh/?q=%.hackerone.com&output=json";
@@ -1,10 +1,9 @@
 use crate::error::{Result, SubError};
+use crate::http::{decode, Client, Keys};
+use crate::sources::Source;
 use crate::{DataSource, IntoSubdomain};
 use async_trait::async_trait;
-use dotenv::dotenv;
-use reqwest::Client;
 use serde::Deserialize;
-use std::env;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tracing::{info, warn};
@@ -16,13 +15,12 @@ struct Creds {
 }
 
 impl Creds {
-    pub fn read_creds() -> Result<Self> {
-        dotenv().ok();
-        let app_id = env::var("FB_APP_ID");
-        let app_secret = env::var("FB_APP_SECRET");
+    pub fn read_creds(keys: &Keys) -> Result<Self> {
+        let app_id = keys.get("FB_APP_ID");
+        let app_secret = keys.get("FB_APP_SECRET");
 
         match (app_id, app_secret) {
-            (Ok(id), Ok(secret)) => Ok(Self {
+            (Some(id), Some(secret)) => Ok(Self {
                 app_id: id,
                 app_secret: secret,
             }),
@@ -74,6 +72,12 @@ impl IntoSubdomain for FacebookResult {
     }
 }
 
+/// Extracts the names from a Facebook certificate search response
+pub(crate) fn parse(body: &[u8]) -> Result<Vec<String>> {
+    let resp: Option<FacebookResult> = decode(Some(Source::Facebook), body)?;
+    Ok(resp.map(|r| r.subdomains()).unwrap_or_default())
+}
+
 #[derive(Default, Clone)]
 pub struct Facebook {
     client: Client,
@@ -95,21 +99,19 @@ impl Facebook {
 #[async_trait]
 impl DataSource for Facebook {
     async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
-        let access_token = match Creds::read_creds() {
+        let access_token = match Creds::read_creds(self.client.keys()) {
             Ok(c) => c.authenticate(self.client.clone()).await?,
             Err(e) => return Err(e),
         };
 
         let uri = self.build_url(&host, &access_token);
-        let resp: Option<FacebookResult> = self.client.get(&uri).send().await?.json().await?;
-
-        if let Some(data) = resp {
-            let subdomains = data.subdomains();
-            if !subdomains.is_empty() {
-                info!("Discovered {} results for {}", &subdomains.len(), &host);
-                let _ = tx.send(subdomains).await;
-                return Ok(());
-            }
+        let body = self.client.get(&uri).send().await?.bytes().await?;
+        let subdomains = parse(&body)?;
+
+        if !subdomains.is_empty() {
+            info!("Discovered {} results for {}", &subdomains.len(), &host);
+            let _ = tx.send(subdomains).await;
+            return Ok(());
         }
 
         warn!("got no results for {} from Facebook", host);
@@ -120,9 +122,18 @@ impl DataSource for Facebook {
 mod tests {
     use super::*;
     use crate::client;
+    use crate::sources::fixtures;
+    use dotenv::dotenv;
     use matches::matches;
+    use std::env;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = fixtures::response("facebook.json");
+        fixtures::assert_golden("facebook.golden.json", parse(&body).unwrap());
+    }
+
 
     #[ignore]
     #[test]
@@ -131,14 +142,14 @@ mod tests {
         let app_id = env::var("FB_APP_ID").unwrap();
         let app_secret = env::var("FB_APP_SECRET").unwrap();
         let creds: Creds = Creds { app_id, app_secret };
-        assert_eq!(creds, Creds::read_creds().unwrap());
+        assert_eq!(creds, Creds::read_creds(&Keys::default()).unwrap());
     }
 
     #[ignore]
     #[test]
     fn get_no_creds() {
         assert!(matches!(
-            Creds::read_creds().err().unwrap(),
+            Creds::read_creds(&Keys::default()).err().unwrap(),
             SubError::UnsetKeys(_)
         ));
     }
@@ -147,8 +158,8 @@ mod tests {
     #[ignore]
     #[tokio::test]
     async fn auth() {
-        let client = client!();
-        let token = Creds::read_creds()
+        let client = Client::new(client!());
+        let token = Creds::read_creds(&Keys::default())
             .unwrap()
             .authenticate(client)
             .await
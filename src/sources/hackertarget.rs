@@ -1,7 +1,7 @@
 use crate::error::{Result, SubError};
+use crate::http::Client;
 use crate::{DataSource, IntoSubdomain};
 use async_trait::async_trait;
-use reqwest::Client;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tracing::{info, trace, warn};
@@ -22,11 +22,18 @@ impl IntoSubdomain for HTResult {
     fn subdomains(&self) -> Vec<String> {
         self.items
             .lines()
-            .map(|s| s.split(',').collect::<Vec<&str>>()[0].to_owned())
+            .filter_map(|s| s.split(',').next())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_owned())
             .collect()
     }
 }
 
+/// Extracts the names from a HackerTarget host search, a `host,address` pair per line
+pub(crate) fn parse(body: &str) -> Vec<String> {
+    HTResult::new(body.to_string()).subdomains()
+}
+
 #[derive(Default, Clone)]
 pub struct HackerTarget {
     client: Client,
@@ -50,7 +57,7 @@ impl DataSource for HackerTarget {
         let resp: String = self.client.get(&uri).send().await?.text().await?;
 
         if resp != API_ERROR {
-            let subdomains = HTResult::new(resp).subdomains();
+            let subdomains = parse(&resp);
             info!("Discovered {} results for: {}", &subdomains.len(), &host);
             let _ = tx.send(subdomains).await;
             return Ok(());
@@ -64,9 +71,19 @@ impl DataSource for HackerTarget {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sources::fixtures;
     use matches::matches;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = fixtures::response("hackertarget.txt");
+        fixtures::assert_golden(
+            "hackertarget.golden.json",
+            parse(&String::from_utf8_lossy(&body)),
+        );
+    }
+
     #[tokio::test]
     async fn returns_results() {
         let (tx, mut rx) = channel(1);
@@ -1,10 +1,9 @@
 use crate::error::{Result, SubError};
+use crate::http::{decode, Client, Keys};
+use crate::sources::Source;
 use crate::{DataSource, IntoSubdomain};
 use async_trait::async_trait;
-use dotenv::dotenv;
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::env;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tracing::{debug, info, trace, warn};
@@ -15,13 +14,12 @@ struct Creds {
 }
 
 impl Creds {
-    fn read_creds() -> Result<Self> {
-        dotenv().ok();
-        let api_key = env::var("INTELX_KEY");
-        let url = env::var("INTELX_URL");
+    fn read_creds(keys: &Keys) -> Result<Self> {
+        let api_key = keys.get("INTELX_KEY");
+        let url = keys.get("INTELX_URL");
 
         match (api_key, url) {
-            (Ok(k), Ok(u)) => Ok(Self { url: u, api_key: k }),
+            (Some(k), Some(u)) => Ok(Self { url: u, api_key: k }),
             _ => Err(SubError::UnsetKeys(vec![
                 "INTELX_URL".into(),
                 "INTELX_KEY".into(),
@@ -76,6 +74,12 @@ impl IntoSubdomain for IntelxResults {
     }
 }
 
+/// Extracts the names from the results of an Intelx phonebook search
+pub(crate) fn parse(body: &[u8]) -> Result<Vec<String>> {
+    let resp: IntelxResults = decode(Some(Source::Intelx), body)?;
+    Ok(resp.subdomains())
+}
+
 #[derive(Default, Clone)]
 pub struct Intelx {
     client: Client,
@@ -100,14 +104,14 @@ impl Intelx {
                 "https://{}/phonebook/search/result?k={}&id={}&limit=100000",
                 intelx_url,
                 api_key,
-                search_id.unwrap()
+                search_id.unwrap_or_default()
             )
         }
     }
 
     async fn get_searchid(&self, host: Arc<String>) -> Result<String> {
         trace!("getting intelx searchid");
-        let creds = match Creds::read_creds() {
+        let creds = match Creds::read_creds(self.client.keys()) {
             Ok(c) => c,
             Err(e) => return Err(e),
         };
@@ -132,7 +136,7 @@ impl Intelx {
 impl DataSource for Intelx {
     async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
         trace!("fetching data from intelx for: {}", &host);
-        let creds = match Creds::read_creds() {
+        let creds = match Creds::read_creds(self.client.keys()) {
             Ok(creds) => creds,
             Err(e) => return Err(e),
         };
@@ -145,8 +149,7 @@ impl DataSource for Intelx {
             warn!("got status: {} for intelx", resp.status().as_str());
             return Err(SubError::AuthError("Intelx".into()));
         } else {
-            let resp: IntelxResults = resp.json().await?;
-            let subdomains = resp.subdomains();
+            let subdomains = parse(&resp.bytes().await?)?;
             if !subdomains.is_empty() {
                 info!("Discovered {} results for: {}", &subdomains.len(), &host);
                 let _ = tx.send(subdomains).await;
@@ -162,9 +165,16 @@ impl DataSource for Intelx {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sources::fixtures;
     use matches::matches;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = fixtures::response("intelx.json");
+        fixtures::assert_golden("intelx.golden.json", parse(&body).unwrap());
+    }
+
     #[tokio::test]
     #[ignore]
     async fn search_id() {
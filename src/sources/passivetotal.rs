@@ -1,11 +1,10 @@
 use crate::error::{Result, SubError};
+use crate::http::{decode, Client, Keys};
+use crate::sources::Source;
 use crate::{DataSource, IntoSubdomain};
 use async_trait::async_trait;
-use dotenv::dotenv;
 use reqwest::header::ACCEPT;
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::env;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tracing::{info, trace, warn};
@@ -16,12 +15,11 @@ struct Creds {
 }
 
 impl Creds {
-    fn from_env() -> Result<Self> {
-        dotenv().ok();
-        let key = env::var("PASSIVETOTAL_KEY");
-        let secret = env::var("PASSIVETOTAL_SECRET");
+    fn read_creds(keys: &Keys) -> Result<Self> {
+        let key = keys.get("PASSIVETOTAL_KEY");
+        let secret = keys.get("PASSIVETOTAL_SECRET");
         match (key, secret) {
-            (Ok(k), Ok(s)) => Ok(Self { key: k, secret: s }),
+            (Some(k), Some(s)) => Ok(Self { key: k, secret: s }),
             _ => Err(SubError::UnsetKeys(vec![
                 "PASSIVETOTAL_KEY".into(),
                 "PASSIVETOTAL_SECRET".into(),
@@ -60,6 +58,12 @@ impl IntoSubdomain for PassiveTotalResult {
     }
 }
 
+/// Extracts the names from a PassiveTotal response, which lists labels below the domain
+pub(crate) fn parse(body: &[u8]) -> Result<Vec<String>> {
+    let resp: PassiveTotalResult = decode(Some(Source::PassiveTotal), body)?;
+    Ok(resp.subdomains())
+}
+
 #[derive(Default, Clone)]
 pub struct PassiveTotal {
     client: Client,
@@ -79,7 +83,7 @@ impl PassiveTotal {
 impl DataSource for PassiveTotal {
     async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
         trace!("fetching data from passivetotal for: {}", &host);
-        let creds = match Creds::from_env() {
+        let creds = match Creds::read_creds(self.client.keys()) {
             Ok(c) => c,
             Err(e) => return Err(e),
         };
@@ -99,8 +103,7 @@ impl DataSource for PassiveTotal {
             warn!("got status: {} from passivetotal", resp.status().as_str());
             return Err(SubError::AuthError("Passivetotal".into()));
         } else {
-            let resp: PassiveTotalResult = resp.json().await?;
-            let subdomains = resp.subdomains();
+            let subdomains = parse(&resp.bytes().await?)?;
 
             if !subdomains.is_empty() {
                 info!("Discovered {} results for: {}", &subdomains.len(), &host);
@@ -117,9 +120,16 @@ impl DataSource for PassiveTotal {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sources::fixtures;
     use matches::matches;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = fixtures::response("passivetotal.json");
+        fixtures::assert_golden("passivetotal.golden.json", parse(&body).unwrap());
+    }
+
     #[tokio::test]
     #[ignore]
     async fn returns_results() {
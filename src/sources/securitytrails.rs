@@ -1,10 +1,9 @@
 use crate::error::{Result, SubError};
+use crate::http::{decode, Client, Keys};
+use crate::sources::Source;
 use crate::{DataSource, IntoSubdomain};
 use async_trait::async_trait;
-use dotenv::dotenv;
-use reqwest::Client;
 use serde::Deserialize;
-use std::env;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tracing::{info, trace, warn};
@@ -14,11 +13,10 @@ struct Creds {
 }
 
 impl Creds {
-    pub fn read_creds() -> Result<Self> {
-        dotenv().ok();
-        match env::var("SECURITY_TRAILS_KEY") {
-            Ok(api_key) => Ok(Self { api_key }),
-            Err(_) => Err(SubError::UnsetKeys(vec!["SECURITY_TRAILS_KEY".into()])),
+    pub fn read_creds(keys: &Keys) -> Result<Self> {
+        match keys.get("SECURITY_TRAILS_KEY") {
+            Some(api_key) => Ok(Self { api_key }),
+            None => Err(SubError::UnsetKeys(vec!["SECURITY_TRAILS_KEY".into()])),
         }
     }
 }
@@ -39,6 +37,14 @@ impl IntoSubdomain for SecTrailsResult {
     }
 }
 
+/// Extracts the names from a SecurityTrails response, which lists labels below `host`
+pub(crate) fn parse(host: Arc<String>, body: &[u8]) -> Result<Vec<String>> {
+    let resp: Option<SecTrailsResult> = decode(Some(Source::SecurityTrails), body)?;
+    Ok(resp
+        .map(|r| SecTrailsResult { host, ..r }.subdomains())
+        .unwrap_or_default())
+}
+
 #[derive(Default, Clone)]
 pub struct SecurityTrails {
     client: Client,
@@ -62,7 +68,7 @@ impl DataSource for SecurityTrails {
     async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
         trace!("fetching data from securitytrails for: {}", &host);
 
-        let api_key = match Creds::read_creds() {
+        let api_key = match Creds::read_creds(self.client.keys()) {
             Ok(creds) => creds.api_key,
             Err(e) => return Err(e),
         };
@@ -82,9 +88,8 @@ impl DataSource for SecurityTrails {
             );
             return Err(SubError::AuthError("SecurityTrails".into()));
         } else {
-            let resp: Option<SecTrailsResult> = resp.json().await?;
-            if let Some(data) = resp {
-                let subdomains = data.subdomains();
+            let subdomains = parse(host.clone(), &resp.bytes().await?)?;
+            if !subdomains.is_empty() {
                 info!("Discovered {} results for: {}", &subdomains.len(), &host);
                 let _ = tx.send(subdomains).await;
                 return Ok(());
@@ -99,9 +104,17 @@ impl DataSource for SecurityTrails {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sources::fixtures;
     use matches::matches;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = fixtures::response("securitytrails.json");
+        let parsed = parse(Arc::new("hackerone.com".into()), &body).unwrap();
+        fixtures::assert_golden("securitytrails.golden.json", parsed);
+    }
+
     #[test]
     fn url_builder() {
         let correct_uri = "https://api.securitytrails.com/v1/domain/hackerone.com/subdomains";
@@ -1,9 +1,9 @@
 use crate::error::{Result, SubError};
+use crate::http::Client;
 use crate::{DataSource, QUEUE_SIZE};
 use async_trait::async_trait;
 use crobat::Crobat;
 use futures::StreamExt;
-use reqwest::Client;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tracing::{debug, info};
@@ -23,12 +23,14 @@ impl SonarSearch {
 impl DataSource for SonarSearch {
     async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
         let mut results = Vec::with_capacity(QUEUE_SIZE);
+        let mut found = 0;
         let mut client = Crobat::connect().await?;
         let mut subs = client.get_subs(host.clone()).await?;
 
         while let Some(r) = subs.next().await {
             let domain = r.map(|d| d.domain).map_err(|_| SubError::CrobatError)?;
             results.push(domain);
+            found += 1;
 
             if results.len() == QUEUE_SIZE {
                 debug!("sonarsearch queue is full, sending across channel",);
@@ -45,6 +47,11 @@ impl DataSource for SonarSearch {
             let _ = tx.send(results.drain(..).collect()).await;
         }
 
+        if found > 0 {
+            info!("Discovered {} results for: {}", found, &host);
+            return Ok(());
+        }
+
         Err(SubError::SourceError("SonarSearch".into()))
     }
 }
@@ -1,11 +1,10 @@
 use crate::error::{Result, SubError};
+use crate::http::{decode, Client, Keys};
+use crate::sources::Source;
 use crate::{DataSource, IntoSubdomain};
 use async_trait::async_trait;
-use dotenv::dotenv;
 use reqwest::header::ACCEPT;
-use reqwest::Client;
 use serde::Deserialize;
-use std::env;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tracing::{info, trace, warn};
@@ -15,11 +14,10 @@ struct Creds {
 }
 
 impl Creds {
-    pub fn read_creds() -> Result<Self> {
-        dotenv().ok();
-        match env::var("SPYSE_TOKEN") {
-            Ok(token) => Ok(Self { token }),
-            Err(_) => Err(SubError::UnsetKeys(vec!["SPYSE_TOKEN".into()])),
+    pub fn read_creds(keys: &Keys) -> Result<Self> {
+        match keys.get("SPYSE_TOKEN") {
+            Some(token) => Ok(Self { token }),
+            None => Err(SubError::UnsetKeys(vec!["SPYSE_TOKEN".into()])),
         }
     }
 }
@@ -45,6 +43,12 @@ impl IntoSubdomain for SpyseResult {
     }
 }
 
+/// Extracts the names from a Spyse subdomain search
+pub(crate) fn parse(body: &[u8]) -> Result<Vec<String>> {
+    let resp: Option<SpyseResult> = decode(Some(Source::Spyse), body)?;
+    Ok(resp.map(|r| r.subdomains()).unwrap_or_default())
+}
+
 #[derive(Default, Clone)]
 pub struct Spyse {
     client: Client,
@@ -67,7 +71,7 @@ impl Spyse {
 impl DataSource for Spyse {
     async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
         trace!("fetching data from spyse for: {}", &host);
-        let token = match Creds::read_creds() {
+        let token = match Creds::read_creds(self.client.keys()) {
             Ok(creds) => creds.token,
             Err(e) => return Err(e),
         };
@@ -85,9 +89,8 @@ impl DataSource for Spyse {
             warn!("got status: {} from spyse", resp.status().as_str());
             return Err(SubError::AuthError("Spyse".into()));
         } else {
-            let resp: Option<SpyseResult> = resp.json().await?;
-            if let Some(data) = resp {
-                let subdomains = data.subdomains();
+            let subdomains = parse(&resp.bytes().await?)?;
+            if !subdomains.is_empty() {
                 info!("Discovered {} results for {}", &subdomains.len(), &host);
                 let _ = tx.send(subdomains).await;
                 return Ok(());
@@ -102,9 +105,16 @@ impl DataSource for Spyse {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sources::fixtures;
     use matches::matches;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = fixtures::response("spyse.json");
+        fixtures::assert_golden("spyse.golden.json", parse(&body).unwrap());
+    }
+
     #[test]
     fn url_builder() {
         let correct_uri =
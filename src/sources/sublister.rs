@@ -1,7 +1,8 @@
 use crate::error::{Result, SubError};
+use crate::http::{decode, Client};
+use crate::sources::Source;
 use crate::{DataSource, IntoSubdomain};
 use async_trait::async_trait;
-use reqwest::Client;
 use serde_json::value::Value;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
@@ -17,16 +18,24 @@ impl SublisterResult {
     }
 }
 
-//TODO: can this just be collected without the map?
 impl IntoSubdomain for SublisterResult {
     fn subdomains(&self) -> Vec<String> {
         self.items
             .iter()
-            .map(|s| s.as_str().unwrap().to_owned())
+            .filter_map(|s| s.as_str().map(|s| s.to_owned()))
             .collect()
     }
 }
 
+/// Extracts the names from a Sublist3r response, a json array of hostnames
+pub(crate) fn parse(body: &[u8]) -> Result<Vec<String>> {
+    let resp: Option<Value> = decode(Some(Source::Sublister), body)?;
+    Ok(resp
+        .and_then(|r| r.as_array().cloned())
+        .map(|items| SublisterResult::new(items).subdomains())
+        .unwrap_or_default())
+}
+
 #[derive(Default, Clone)]
 pub struct Sublister {
     client: Client,
@@ -47,15 +56,13 @@ impl DataSource for Sublister {
     async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
         trace!("fetching data from sublister for: {}", &host);
         let uri = self.build_url(&host);
-        let resp: Option<Value> = self.client.get(&uri).send().await?.json().await?;
+        let body = self.client.get(&uri).send().await?.bytes().await?;
+        let subdomains = parse(&body)?;
 
-        if let Some(data) = resp {
-            let subdomains = SublisterResult::new(data.as_array().unwrap().to_owned()).subdomains();
-            if !subdomains.is_empty() {
-                info!("Discovered {} results for {}", &subdomains.len(), &host);
-                let _ = tx.send(subdomains).await;
-                return Ok(());
-            }
+        if !subdomains.is_empty() {
+            info!("Discovered {} results for {}", &subdomains.len(), &host);
+            let _ = tx.send(subdomains).await;
+            return Ok(());
         }
 
         warn!("no results for {} from Sublist3r", &host);
@@ -66,9 +73,16 @@ impl DataSource for Sublister {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sources::fixtures;
     use matches::matches;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = fixtures::response("sublister.json");
+        fixtures::assert_golden("sublister.golden.json", parse(&body).unwrap());
+    }
+
     #[test]
     fn url_builder() {
         let correct_uri = "https://api.sublist3r.com/search.php?domain=hackerone.com";
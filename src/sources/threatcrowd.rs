@@ -1,7 +1,8 @@
 use crate::error::{Result, SubError};
+use crate::http::{decode, Client};
+use crate::sources::Source;
 use crate::{DataSource, IntoSubdomain};
 use async_trait::async_trait;
-use reqwest::Client;
 use serde::Deserialize;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
@@ -22,6 +23,12 @@ impl IntoSubdomain for ThreatCrowdResult {
     }
 }
 
+/// Extracts the subdomains from a ThreatCrowd domain report
+pub(crate) fn parse(body: &[u8]) -> Result<Vec<String>> {
+    let resp: ThreatCrowdResult = decode(Some(Source::ThreatCrowd), body)?;
+    Ok(resp.subdomains())
+}
+
 #[derive(Default, Clone)]
 pub struct ThreatCrowd {
     client: Client,
@@ -45,8 +52,8 @@ impl DataSource for ThreatCrowd {
     async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
         trace!("fetching data from threatcrowd for: {}", &host);
         let uri = self.build_url(&host);
-        let resp: ThreatCrowdResult = self.client.get(&uri).send().await?.json().await?;
-        let subdomains = resp.subdomains();
+        let body = self.client.get(&uri).send().await?.bytes().await?;
+        let subdomains = parse(&body)?;
 
         if !subdomains.is_empty() {
             info!("Discovered {} results for {}", &subdomains.len(), &host);
@@ -62,9 +69,16 @@ impl DataSource for ThreatCrowd {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sources::fixtures;
     use matches::matches;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = fixtures::response("threatcrowd.json");
+        fixtures::assert_golden("threatcrowd.golden.json", parse(&body).unwrap());
+    }
+
     #[tokio::test]
     async fn returns_results() {
         let (tx, mut rx) = channel(1);
@@ -1,7 +1,8 @@
 use crate::error::{Result, SubError};
+use crate::http::{decode, Client};
+use crate::sources::Source;
 use crate::{DataSource, IntoSubdomain};
 use async_trait::async_trait;
-use reqwest::Client;
 use serde::Deserialize;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
@@ -19,6 +20,12 @@ impl IntoSubdomain for ThreatminerResult {
     }
 }
 
+/// Extracts the subdomains from a ThreatMiner domain report
+pub(crate) fn parse(body: &[u8]) -> Result<Vec<String>> {
+    let resp: Option<ThreatminerResult> = decode(Some(Source::ThreatMiner), body)?;
+    Ok(resp.map(|r| r.subdomains()).unwrap_or_default())
+}
+
 #[derive(Default, Clone)]
 pub struct ThreatMiner {
     client: Client,
@@ -42,15 +49,13 @@ impl DataSource for ThreatMiner {
     async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
         trace!("fetching data from threatminer for: {}", &host);
         let uri = self.build_url(&host);
-        let resp: Option<ThreatminerResult> = self.client.get(&uri).send().await?.json().await?;
+        let body = self.client.get(&uri).send().await?.bytes().await?;
+        let subdomains = parse(&body)?;
 
-        if let Some(data) = resp {
-            let subdomains = data.subdomains();
-            if !subdomains.is_empty() {
-                info!("Discovered {} results for: {}", &subdomains.len(), &host);
-                let _ = tx.send(subdomains).await;
-                return Ok(());
-            }
+        if !subdomains.is_empty() {
+            info!("Discovered {} results for: {}", &subdomains.len(), &host);
+            let _ = tx.send(subdomains).await;
+            return Ok(());
         }
 
         warn!("no results found for {} from ThreatMiner", &host);
@@ -61,9 +66,16 @@ impl DataSource for ThreatMiner {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sources::fixtures;
     use matches::matches;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = fixtures::response("threatminer.json");
+        fixtures::assert_golden("threatminer.golden.json", parse(&body).unwrap());
+    }
+
     #[test]
     fn url_builder() {
         let correct_uri = "https://api.threatminer.org/v2/domain.php?q=hackerone.com&api=True&rt=5";
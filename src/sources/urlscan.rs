@@ -1,7 +1,8 @@
 use crate::error::{Result, SubError};
+use crate::http::{decode, Client};
+use crate::sources::Source;
 use crate::{DataSource, IntoSubdomain};
 use async_trait::async_trait;
-use reqwest::Client;
 use serde::Deserialize;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
@@ -31,6 +32,12 @@ impl IntoSubdomain for UrlScanResult {
     }
 }
 
+/// Extracts the domains of the pages in a UrlScan search
+pub(crate) fn parse(body: &[u8]) -> Result<Vec<String>> {
+    let resp: Option<UrlScanResult> = decode(Some(Source::UrlScan), body)?;
+    Ok(resp.map(|r| r.subdomains()).unwrap_or_default())
+}
+
 #[derive(Default, Clone)]
 pub struct UrlScan {
     client: Client,
@@ -51,15 +58,13 @@ impl DataSource for UrlScan {
     async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
         trace!("fetching data from urlscan for: {}", &host);
         let uri = self.build_url(&host);
-        let resp: Option<UrlScanResult> = self.client.get(&uri).send().await?.json().await?;
-
-        if let Some(data) = resp {
-            let subdomains = data.subdomains();
-            if !subdomains.is_empty() {
-                info!("Discovered {} results for: {}", &subdomains.len(), &host);
-                let _ = tx.send(subdomains).await;
-                return Ok(());
-            }
+        let body = self.client.get(&uri).send().await?.bytes().await?;
+        let subdomains = parse(&body)?;
+
+        if !subdomains.is_empty() {
+            info!("Discovered {} results for: {}", &subdomains.len(), &host);
+            let _ = tx.send(subdomains).await;
+            return Ok(());
         }
 
         warn!("no results found for {} from UrlScan", &host);
@@ -70,9 +75,16 @@ impl DataSource for UrlScan {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sources::fixtures;
     use matches::matches;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = fixtures::response("urlscan.json");
+        fixtures::assert_golden("urlscan.golden.json", parse(&body).unwrap());
+    }
+
     #[test]
     fn url_builder() {
         let correct_uri = "https://urlscan.io/api/v1/search/?q=domain:hackerone.com";
@@ -1,7 +1,8 @@
 use crate::error::{Result, SubError};
+use crate::http::{decode, Client};
+use crate::sources::Source;
 use crate::{DataSource, IntoSubdomain};
 use async_trait::async_trait;
-use reqwest::Client;
 use serde::Deserialize;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
@@ -27,6 +28,12 @@ impl IntoSubdomain for VirustotalResult {
     }
 }
 
+/// Extracts the names from a VirusTotal subdomains listing
+pub(crate) fn parse(body: &[u8]) -> Result<Vec<String>> {
+    let resp: VirustotalResult = decode(Some(Source::VirusTotal), body)?;
+    Ok(resp.subdomains())
+}
+
 #[derive(Default, Clone)]
 pub struct VirusTotal {
     client: Client,
@@ -53,9 +60,9 @@ impl DataSource for VirusTotal {
     async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
         trace!("fetching data from virustotal for: {}", &host);
         let uri = self.build_url(&host);
-        let resp: VirustotalResult = self.client.get(&uri).send().await?.json().await?;
+        let body = self.client.get(&uri).send().await?.bytes().await?;
 
-        let subdomains = resp.subdomains();
+        let subdomains = parse(&body)?;
         if !subdomains.is_empty() {
             info!("Discovered {} results for {}", &subdomains.len(), &host);
             let _ = tx.send(subdomains).await;
@@ -70,9 +77,16 @@ impl DataSource for VirusTotal {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sources::fixtures;
     use matches::matches;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = fixtures::response("virustotal.json");
+        fixtures::assert_golden("virustotal.golden.json", parse(&body).unwrap());
+    }
+
     // IGNORE by default since we have limited api calls.
     #[tokio::test]
     #[ignore]
@@ -1,38 +1,85 @@
 use crate::error::{Result, SubError};
-use crate::{DataSource, IntoSubdomain};
+use crate::http::{Body, Client};
+use crate::DataSource;
 use async_trait::async_trait;
-use reqwest::Client;
+use serde::de::{Deserialize, Deserializer, IgnoredAny, SeqAccess, Visitor};
 use serde_json::value::Value;
+use std::fmt;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tracing::{info, trace, warn};
 use url::Url;
 
+/// The hosts of the urls in a CDX response. The response is read a row at a time and only
+/// the hosts are kept, as responses for popular roots are too large to hold as a `Value`.
 struct WaybackResult {
-    data: Value,
+    hosts: Vec<String>,
 }
 
-impl WaybackResult {
-    fn new(data: Value) -> Self {
-        Self { data }
+impl<'de> Deserialize<'de> for WaybackResult {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_seq(Rows)
     }
 }
 
-//TODO: this could be cleaned up, to avoid creating the extra vec `vecs`
-impl IntoSubdomain for WaybackResult {
-    fn subdomains(&self) -> Vec<String> {
-        let arr = self.data.as_array().unwrap();
-        let vecs: Vec<&str> = arr.iter().map(|s| s[0].as_str().unwrap()).collect();
-        vecs.into_iter()
-            .filter_map(|a| {
-                Url::parse(a)
-                    .ok()
-                    .and_then(|u| u.host_str().map(|h| h.into()))
-            })
-            .collect()
+struct Rows;
+
+impl<'de> Visitor<'de> for Rows {
+    type Value = WaybackResult;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list of CDX rows")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut rows: A,
+    ) -> std::result::Result<Self::Value, A::Error> {
+        let mut hosts = Vec::new();
+        while let Some(Row(original)) = rows.next_element()? {
+            let host = original
+                .as_ref()
+                .and_then(Value::as_str)
+                .and_then(|u| Url::parse(u).ok())
+                .and_then(|u| u.host_str().map(|h| h.to_string()));
+            hosts.extend(host);
+        }
+
+        Ok(WaybackResult { hosts })
+    }
+}
+
+/// A CDX row, an array with the original url first, the rest of the row is skipped
+struct Row(Option<Value>);
+
+impl<'de> Deserialize<'de> for Row {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_seq(Fields)
+    }
+}
+
+struct Fields;
+
+impl<'de> Visitor<'de> for Fields {
+    type Value = Row;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a CDX row")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut fields: A) -> std::result::Result<Row, A::Error> {
+        let original = fields.next_element()?;
+        while fields.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(Row(original))
     }
 }
 
+/// Extracts the hosts of the urls in a Wayback CDX response
+pub(crate) fn parse(body: &Body) -> Result<Vec<String>> {
+    let resp: Option<WaybackResult> = body.decode()?;
+    Ok(resp.map(|r| r.hosts).unwrap_or_default())
+}
+
 #[derive(Default, Clone)]
 pub struct Wayback {
     client: Client,
@@ -57,15 +104,14 @@ impl DataSource for Wayback {
     async fn run(&self, host: Arc<String>, mut tx: Sender<Vec<String>>) -> Result<()> {
         trace!("fetching data from wayback for: {}", &host);
         let uri = self.build_url(&host);
-        let resp: Option<Value> = self.client.get(&uri).send().await?.json().await?;
-
-        if let Some(data) = resp {
-            let subdomains = WaybackResult::new(data).subdomains();
-            if !subdomains.is_empty() {
-                info!("Discovered {} results for: {}", &subdomains.len(), &host);
-                let _ = tx.send(subdomains).await;
-                return Ok(());
-            }
+        // CDX results for popular roots run to hundreds of megabytes
+        let body = self.client.get(&uri).send().await?.body().await?;
+        let subdomains = parse(&body)?;
+
+        if !subdomains.is_empty() {
+            info!("Discovered {} results for: {}", &subdomains.len(), &host);
+            let _ = tx.send(subdomains).await;
+            return Ok(());
         }
 
         warn!("no results found for {} from Wayback Machine", &host);
@@ -76,9 +122,23 @@ impl DataSource for Wayback {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sources::{fixtures, Source};
     use matches::matches;
     use tokio::sync::mpsc::channel;
 
+    #[test]
+    fn parses_fixture() {
+        let body = Body::from_bytes(Some(Source::Wayback), fixtures::response("wayback.json"));
+        fixtures::assert_golden("wayback.golden.json", parse(&body).unwrap());
+    }
+
+    #[test]
+    fn reads_the_url_of_each_row() {
+        let rows = br#"[["original","timestamp"],["https://api.hackerone.com/x","2020"],[1],[]]"#;
+        let body = Body::from_bytes(Some(Source::Wayback), rows.to_vec());
+        assert_eq!(parse(&body).unwrap(), vec!["api.hackerone.com"]);
+    }
+
     #[test]
     fn url_builder() {
         let correct_uri =
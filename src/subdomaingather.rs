@@ -1,81 +1,117 @@
+use crate::checkpoint::{Chunk, RunState};
+use crate::dates::{Date, DateRange};
+use crate::enrich::{Enricher, Enrichment};
+use crate::fallback::{self, Fallback};
+use crate::output::{Fanout, OutputSink, Subdomain, Summary};
+use crate::policy::{self, ScrapePolicy};
+use crate::preflight;
 use crate::sources::{
     alienvault::AlienVault, anubisdb::AnubisDB, binaryedge::BinaryEdge, c99::C99,
     certspotter::CertSpotter, chaos::Chaos, crtsh::Crtsh, facebook::Facebook,
     hackertarget::HackerTarget, intelx::Intelx, passivetotal::PassiveTotal,
     securitytrails::SecurityTrails, sonarsearch::SonarSearch, spyse::Spyse, sublister::Sublister,
     threatcrowd::ThreatCrowd, threatminer::ThreatMiner, urlscan::UrlScan, virustotal::VirusTotal,
-    wayback::Wayback,
+    wayback::Wayback, Source,
+};
+use crate::{
+    error::{BuildError, Result, SubError},
+    http::{self, Client, Fixtures, IpVersion, TlsBackend},
+    CleanExt, DataSource, Event, Heuristics, PostProcessor, ProfileSettings, Quotas, Records,
+    SkipReason, Skipped,
 };
-use crate::{client, error::Result, DataSource};
 
-use futures::stream::{FuturesUnordered, StreamExt};
+use futures::future::{self, Either, FutureExt};
+use futures::stream::{self, BoxStream, FuturesUnordered, SelectAll, StreamExt};
 use futures_core::stream::Stream;
-use reqwest::Client;
+use std::any::Any;
 use std::collections::HashMap;
 use std::collections::HashSet;
-use std::str::FromStr;
+use std::net::IpAddr;
+use std::panic::AssertUnwindSafe;
+use std::path::PathBuf;
+use std::pin::Pin;
 use std::sync::Arc;
-use strum_macros::EnumString;
-use tokio::sync::mpsc;
-use tracing::{info, warn};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+use tracing::{debug, info, warn};
 
 const CHAN_SIZE: usize = 255;
-
-#[derive(Debug, Eq, PartialEq, Hash, EnumString)]
-enum Source {
-    AlienVault,
-    AnubisDB,
-    BinaryEdge,
-    C99,
-    CertSpotter,
-    Chaos,
-    Crtsh,
-    Facebook,
-    HackerTarget,
-    Intelx,
-    PassiveTotal,
-    SecurityTrails,
-    SonarSearch,
-    Spyse,
-    Sublister,
-    ThreatCrowd,
-    ThreatMiner,
-    UrlScan,
-    VirusTotal,
-    Wayback,
-}
+/// The longest request timeout `Runner::build` accepts, in seconds
+const MAX_TIMEOUT: u64 = 3600;
+/// The average gap between requests of sources which scrape websites under `polite`
+const POLITE_INTERVAL: Duration = Duration::from_secs(1);
 
 struct Config {
     timeout: u64,
+    connect_timeout: Option<u64>,
+    source_connect_timeouts: HashMap<Source, u64>,
     concurrency: usize,
+    seed: Option<u64>,
+    pool_idle_timeout: Option<u64>,
+    pool_max_idle_per_host: Option<usize>,
+    tcp_keepalive: Option<u64>,
+    http2_prior_knowledge: bool,
+    local_address: Option<IpAddr>,
+    ip_version: IpVersion,
+    tls_backend: Option<TlsBackend>,
+    root_certificates: Vec<PathBuf>,
+    sample: Option<usize>,
+    dates: DateRange,
+    // a budget of running sources shared with other runners, set by `share_concurrency`
+    slots: Option<Arc<Semaphore>>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             timeout: 15,
+            connect_timeout: None,
+            source_connect_timeouts: HashMap::new(),
             concurrency: 200,
+            seed: None,
+            pool_idle_timeout: None,
+            pool_max_idle_per_host: None,
+            tcp_keepalive: None,
+            http2_prior_knowledge: false,
+            local_address: None,
+            ip_version: IpVersion::Auto,
+            tls_backend: None,
+            root_certificates: Vec::new(),
+            sample: None,
+            dates: DateRange::default(),
+            slots: None,
         }
     }
 }
 
+/// Sources and the client they share are only created once the run starts, so the order the
+/// builder methods are called in doesn't matter.
+#[derive(Default)]
 pub struct Runner {
     client: Client,
-    sources: HashMap<Source, Arc<dyn DataSource>>,
+    sources: HashSet<Source>,
+    fallbacks: Vec<Vec<Source>>,
+    // names passed to `exclude` which don't match a source, reported by `build`
+    unknown: Vec<String>,
+    excluded: HashSet<Source>,
+    // sources `all_sources` left out as their keys aren't set
+    missing_keys: Vec<Source>,
+    // sources `preflight` removed
+    unreachable: Vec<Source>,
+    policy: ScrapePolicy,
+    // sources `check_robots` removed
+    robots: Vec<Source>,
+    // the only sources queried for these hosts, set by `only_pairs`
+    pairs: HashMap<String, HashSet<Source>>,
+    all_requested: bool,
+    sinks: Fanout,
+    enrichment: Enrichment,
+    // what `collect` filters results with, any result under one of the roots when unset
+    cleaner: Option<PostProcessor>,
     config: Config,
 }
 
-impl Default for Runner {
-    fn default() -> Self {
-        let config = Config::default();
-        Self {
-            client: client!(config.timeout, config.timeout),
-            sources: HashMap::new(),
-            config,
-        }
-    }
-}
-
 impl Runner {
     /// Sets the limit of concurrent tasks
     pub fn concurrency(mut self, limit: usize) -> Self {
@@ -89,143 +125,766 @@ impl Runner {
         self
     }
 
-    /// Excludes a collection sources from data collection
+    /// Sets how long connecting to a source may take, defaults to the request timeout. A short
+    /// connect timeout lets dead endpoints fail fast while slow responses keep streaming.
+    pub fn connect_timeout(mut self, seconds: u64) -> Self {
+        self.config.connect_timeout = Some(seconds);
+        self
+    }
+
+    /// Sets the total time requests from `source` may take, in place of the request timeout
+    pub fn source_timeout(self, source: Source, seconds: u64) -> Self {
+        self.client
+            .timeouts()
+            .set(source, Duration::from_secs(seconds));
+        self
+    }
+
+    /// Sets how long connecting to `source` may take, in place of the connect timeout
+    pub fn source_connect_timeout(mut self, source: Source, seconds: u64) -> Self {
+        self.config.source_connect_timeouts.insert(source, seconds);
+        self
+    }
+
+    /// Sets how long idle connections are kept in the pool, defaults to the request timeout
+    pub fn pool_idle_timeout(mut self, seconds: u64) -> Self {
+        self.config.pool_idle_timeout = Some(seconds);
+        self
+    }
+
+    /// Binds outbound connections to `addr`. Only hosts reachable over the same address
+    /// family are connected to, so `0.0.0.0` or `::` can be used to force IPv4 or IPv6.
+    pub fn local_address(mut self, addr: IpAddr) -> Self {
+        self.config.local_address = Some(addr);
+        self
+    }
+
+    /// Sets which address family connections are made over. `Auto`, the default, races
+    /// both families so hosts with broken AAAA records fall back to IPv4 rather than hanging.
+    /// Forcing a family is ignored if a local address is bound to.
+    pub fn ip_version(mut self, version: IpVersion) -> Self {
+        self.config.ip_version = version;
+        self
+    }
+
+    /// Makes connections with `backend`, rather than native TLS when it's compiled in and
+    /// rustls otherwise
+    pub fn tls_backend(mut self, backend: TlsBackend) -> Self {
+        self.config.tls_backend = Some(backend);
+        self
+    }
+
+    /// Trusts the PEM certificates in the file at `path` along with the backend's own roots,
+    /// e.g. the CA of a proxy which intercepts TLS
+    pub fn root_certificate<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.config.root_certificates.push(path.into());
+        self
+    }
+
+    /// Sets the most idle connections kept open to each host
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.config.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// Enables TCP keepalive on connections, sending probes every `seconds`
+    pub fn tcp_keepalive(mut self, seconds: u64) -> Self {
+        self.config.tcp_keepalive = Some(seconds);
+        self
+    }
+
+    /// Speaks HTTP/2 without negotiating it first, which lets many requests to the same api
+    /// share a connection. Only use it when every enabled source supports HTTP/2.
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.config.http2_prior_knowledge = true;
+        self
+    }
+
+    /// Applies the source selection, timeout, concurrency and result cap from a profile
+    pub fn profile(self, settings: &ProfileSettings) -> Self {
+        let excluded: Vec<&str> = settings.exclude.iter().map(|s| s.as_str()).collect();
+        let mut runner = self
+            .concurrency(settings.concurrency)
+            .timeout(settings.timeout)
+            .free_sources();
+        if settings.all_sources {
+            runner = runner.all_sources();
+        }
+        if let Some(n) = settings.max_results {
+            runner = runner.sample(n);
+        }
+
+        runner.exclude(&excluded)
+    }
+
+    /// Makes runs reproducible, hosts and sources are queried in a fixed order and the random
+    /// gaps between `polite` requests are derived from `seed`.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self.client.quotas().seed(seed);
+        self
+    }
+
+    /// Caps the number of requests `source` may make during the run, once used up its
+    /// requests fail with `SubError::QuotaExceeded`.
+    pub fn max_requests(self, source: Source, max: usize) -> Self {
+        self.client.quotas().limit(source, max);
+        self
+    }
+
+    /// Caps how many bytes per second every source together downloads, so a run on a metered
+    /// network reads responses more slowly instead of saturating it
+    pub fn max_bandwidth(self, bytes_per_second: u64) -> Self {
+        self.client.quotas().max_bandwidth(bytes_per_second);
+        self
+    }
+
+    /// Limits the size of the responses read from every source to `bytes`, larger responses
+    /// fail with `SubError::ResponseTooLarge`
+    pub fn max_response_size(self, bytes: usize) -> Self {
+        self.client.size_limits().set_default(bytes);
+        self
+    }
+
+    /// Limits the size of the responses read from `source`, taking precedence over
+    /// `max_response_size`
+    pub fn source_max_response_size(self, source: Source, bytes: usize) -> Self {
+        self.client.size_limits().set(source, bytes);
+        self
+    }
+
+    /// Responses from sources known to send enormous bodies are written to a temporary file
+    /// once they grow past `bytes`, and parsed from there rather than from memory
+    pub fn spool_threshold(self, bytes: usize) -> Self {
+        self.client.spooling().set_threshold(bytes);
+        self
+    }
+
+    /// The directory spooled responses are written to, defaults to the system's temp dir
+    pub fn spool_dir<P: Into<PathBuf>>(self, dir: P) -> Self {
+        self.client.spooling().set_dir(dir);
+        self
+    }
+
+    /// Only fetches data seen on or after `date` from sources which can filter by date
+    pub fn since(mut self, date: Date) -> Self {
+        self.config.dates.since = Some(date);
+        self
+    }
+
+    /// Only fetches data seen on or before `date` from sources which can filter by date
+    pub fn until(mut self, date: Date) -> Self {
+        self.config.dates.until = Some(date);
+        self
+    }
+
+    /// Adds query parameters to every url `source` requests, replacing any of the same name
+    /// the source sets itself
+    pub fn extra_params(self, source: Source, params: Vec<(String, String)>) -> Self {
+        self.client.query_params().set(source, params);
+        self
+    }
+
+    /// Sets api keys which sources use in place of the environment variables of the same
+    /// name. Call it before `profile` or `all_sources`, which check which keys are set.
+    pub fn api_keys<I: IntoIterator<Item = (String, String)>>(self, keys: I) -> Self {
+        for (name, value) in keys {
+            self.client.keys().set(&name, &value);
+        }
+        self
+    }
+
+    /// Returns a handle to the request counts, which can be read once the run has finished
+    pub fn quotas(&self) -> Quotas {
+        self.client.quotas().clone()
+    }
+
+    /// Uses `quotas` in place of the runner's own, so several runners can share one set of
+    /// request budgets. Budgets set on this runner before are replaced by those in `quotas`.
+    pub fn share_quotas(mut self, quotas: Quotas) -> Self {
+        self.client = self.client.with_quotas(quotas);
+        self
+    }
+
+    /// Takes a slot from `slots` for every source it runs, on top of its own concurrency
+    /// limit, so several runners can share one limit
+    pub fn share_concurrency(mut self, slots: Arc<Semaphore>) -> Self {
+        self.config.slots = Some(slots);
+        self
+    }
+
+    /// Returns a handle to the dns records passive DNS sources return along with names, so
+    /// results can be matched up with their records as they're written
+    pub fn records(&self) -> Records {
+        self.client.records().clone()
+    }
+
+    /// Adds the records the sources see to `records` in place of the runner's own, so
+    /// several runners can share one set
+    pub fn share_records(mut self, records: Records) -> Self {
+        self.client = self.client.with_records(records);
+        self
+    }
+
+    /// Stops each source once it has returned `n` results for a host, usually after its first
+    /// page, to quickly see which sources are worth a full run for a target.
+    pub fn sample(mut self, n: usize) -> Self {
+        self.config.sample = Some(n);
+        self
+    }
+
+    /// Adds somewhere `collect` writes results to
+    pub fn sink<S: OutputSink + 'static>(mut self, sink: S) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Adds an enricher `collect` runs over results before they're written, with its own
+    /// limit of concurrent lookups
+    pub fn enricher<E: Enricher + 'static>(mut self, enricher: E, concurrency: usize) -> Self {
+        self.enrichment.add(enricher, concurrency);
+        self
+    }
+
+    /// Filters the results `collect` writes with `cleaner`, e.g. one set up with
+    /// `any_subdomain` or `within`, rather than keeping any result under one of the roots
+    pub fn post_processor(mut self, cleaner: PostProcessor) -> Self {
+        self.cleaner = Some(cleaner);
+        self
+    }
+
+    /// Excludes a collection sources from data collection, names are matched ignoring case
     pub fn exclude(mut self, excluded: &[&str]) -> Self {
         if !excluded.is_empty() {
-            excluded.iter().for_each(|s| {
-                if let Ok(source) = Source::from_str(s) {
+            excluded.iter().for_each(|s| match Source::from_name(s) {
+                Some(source) => {
                     info!("excluding {:?}", source);
                     self.sources.remove(&source);
-                };
+                    self.excluded.insert(source);
+                }
+                None => self.unknown.push(s.to_string()),
             });
         }
 
         self
     }
 
+    /// Overrides whether `source` is part of runs which didn't ask for all sources. An enabled
+    /// source which needs api keys is only added when they're set, so a default run can match
+    /// the subscriptions a user has. Excluded sources stay excluded and runs using
+    /// `all_sources` are unaffected, so call this after `profile`.
+    pub fn enabled_by_default(mut self, source: Source, enabled: bool) -> Self {
+        if self.all_requested || self.excluded.contains(&source) {
+            return self;
+        }
+
+        if !enabled {
+            self.sources.remove(&source);
+        } else if self.client.keys().has(source) {
+            self.sources.insert(source);
+        } else {
+            debug!("not enabling {:?} by default, its keys aren't set", source);
+        }
+        self
+    }
+
     /// Sets the sources to be all those which do not require an api key to use.
     pub fn free_sources(mut self) -> Self {
-        // Client uses Arc internally
-        let free: Vec<(Source, Arc<dyn DataSource>)> = vec![
-            (
-                Source::AnubisDB,
-                Arc::new(AnubisDB::new(self.client.clone())),
-            ),
-            (
-                Source::AlienVault,
-                Arc::new(AlienVault::new(self.client.clone())),
-            ),
-            (
-                Source::CertSpotter,
-                Arc::new(CertSpotter::new(self.client.clone())),
-            ),
-            (
-                Source::ThreatCrowd,
-                Arc::new(ThreatCrowd::new(self.client.clone())),
-            ),
-            (
-                Source::VirusTotal,
-                Arc::new(VirusTotal::new(self.client.clone())),
-            ),
-            (
-                Source::ThreatMiner,
-                Arc::new(ThreatMiner::new(self.client.clone())),
-            ),
-            (
-                Source::Sublister,
-                Arc::new(Sublister::new(self.client.clone())),
-            ),
-            (
-                Source::HackerTarget,
-                Arc::new(HackerTarget::new(self.client.clone())),
-            ),
-            (
-                Source::SonarSearch,
-                Arc::new(SonarSearch::new(self.client.clone())),
-            ),
-            (Source::Wayback, Arc::new(Wayback::new(self.client.clone()))),
-            (Source::UrlScan, Arc::new(UrlScan::new(self.client.clone()))),
-            (Source::Crtsh, Arc::new(Crtsh::new(self.client.clone()))),
+        let free = vec![
+            Source::AnubisDB,
+            Source::AlienVault,
+            Source::CertSpotter,
+            Source::ThreatCrowd,
+            Source::VirusTotal,
+            Source::ThreatMiner,
+            Source::Sublister,
+            Source::HackerTarget,
+            Source::SonarSearch,
+            Source::Wayback,
+            Source::UrlScan,
+            Source::Crtsh,
         ];
 
         self.sources.extend(free.into_iter());
         self
     }
 
+    /// Adds every source, those which need api keys only when the keys are set. The ones
+    /// left out are listed by `missing_keys` rather than failing for every root.
     pub fn all_sources(mut self) -> Self {
-        let all: Vec<(Source, Arc<dyn DataSource>)> = vec![
-            (
-                Source::AnubisDB,
-                Arc::new(AnubisDB::new(self.client.clone())),
-            ),
-            (
-                Source::AlienVault,
-                Arc::new(AlienVault::new(self.client.clone())),
-            ),
-            (
-                Source::CertSpotter,
-                Arc::new(CertSpotter::new(self.client.clone())),
-            ),
-            (
-                Source::ThreatCrowd,
-                Arc::new(ThreatCrowd::new(self.client.clone())),
-            ),
-            (
-                Source::VirusTotal,
-                Arc::new(VirusTotal::new(self.client.clone())),
-            ),
-            (
-                Source::ThreatMiner,
-                Arc::new(ThreatMiner::new(self.client.clone())),
-            ),
-            (
-                Source::Sublister,
-                Arc::new(Sublister::new(self.client.clone())),
-            ),
-            (
-                Source::SecurityTrails,
-                Arc::new(SecurityTrails::new(self.client.clone())),
-            ),
-            (
-                Source::HackerTarget,
-                Arc::new(HackerTarget::new(self.client.clone())),
-            ),
-            (
-                Source::SonarSearch,
-                Arc::new(SonarSearch::new(self.client.clone())),
-            ),
-            (
-                Source::BinaryEdge,
-                Arc::new(BinaryEdge::new(self.client.clone())),
-            ),
-            (
-                Source::PassiveTotal,
-                Arc::new(PassiveTotal::new(self.client.clone())),
-            ),
-            (
-                Source::Facebook,
-                Arc::new(Facebook::new(self.client.clone())),
-            ),
-            (Source::Spyse, Arc::new(Spyse::new(self.client.clone()))),
-            (Source::C99, Arc::new(C99::new(self.client.clone()))),
-            (Source::Intelx, Arc::new(Intelx::new(self.client.clone()))),
-            (Source::Wayback, Arc::new(Wayback::new(self.client.clone()))),
-            (Source::UrlScan, Arc::new(UrlScan::new(self.client.clone()))),
-            (Source::Crtsh, Arc::new(Crtsh::new(self.client.clone()))),
-            (Source::Chaos, Arc::new(Chaos::new(self.client.clone()))),
+        self.all_requested = true;
+        let all = vec![
+            Source::AnubisDB,
+            Source::AlienVault,
+            Source::CertSpotter,
+            Source::ThreatCrowd,
+            Source::VirusTotal,
+            Source::ThreatMiner,
+            Source::Sublister,
+            Source::SecurityTrails,
+            Source::HackerTarget,
+            Source::SonarSearch,
+            Source::BinaryEdge,
+            Source::PassiveTotal,
+            Source::Facebook,
+            Source::Spyse,
+            Source::C99,
+            Source::Intelx,
+            Source::Wayback,
+            Source::UrlScan,
+            Source::Crtsh,
+            Source::Chaos,
         ];
 
-        self.sources.extend(all.into_iter());
+        let keys = self.client.keys().clone();
+        let (keyed, missing): (Vec<Source>, Vec<Source>) =
+            all.into_iter().partition(|s| keys.has(*s));
+        self.sources.extend(keyed.into_iter());
+        self.missing_keys = missing;
+        self
+    }
+
+    /// Checks the configuration is usable, so mistakes are reported before the run starts
+    /// rather than part way through it.
+    pub fn build(self) -> Result<Self> {
+        if self.config.concurrency == 0 {
+            return Err(BuildError::ZeroConcurrency.into());
+        }
+        let connect_timeouts = self.config.source_connect_timeouts.values().copied();
+        let source_timeouts = self.client.timeouts().values();
+        for timeout in std::iter::once(self.config.timeout)
+            .chain(self.config.connect_timeout)
+            .chain(connect_timeouts)
+            .chain(source_timeouts.iter().map(Duration::as_secs))
+        {
+            if timeout == 0 {
+                return Err(BuildError::ZeroTimeout.into());
+            }
+            if timeout > MAX_TIMEOUT {
+                return Err(BuildError::TimeoutTooLong(timeout).into());
+            }
+        }
+        if let Some(backend) = self.config.tls_backend.filter(|b| !b.is_available()) {
+            return Err(BuildError::TlsUnavailable(backend).into());
+        }
+        if self.client.quotas().bandwidth() == Some(0) {
+            return Err(BuildError::ZeroBandwidth.into());
+        }
+        let mut unknown = self.unknown.clone();
+        unknown.extend(self.policy.unknown());
+        if !unknown.is_empty() {
+            return Err(BuildError::UnknownSources(unknown).into());
+        }
+        if let DateRange {
+            since: Some(since),
+            until: Some(until),
+        } = self.config.dates
+        {
+            if since > until {
+                return Err(BuildError::InvertedDateRange(since, until).into());
+            }
+        }
+
+        let keyed = self.sources.iter().any(|s| !s.keys().is_empty());
+        if self.all_requested && !self.missing_keys.is_empty() && !keyed {
+            return Err(BuildError::NoKeys.into());
+        }
+
+        Ok(self)
+    }
+
+    /// Builds the client every source shares from the configured timeouts and pool settings
+    fn http_client(&self) -> Result<Client> {
+        let connect_timeout = self.config.connect_timeout.unwrap_or(self.config.timeout);
+        Ok(self
+            .client
+            .with_inner(self.reqwest_client(connect_timeout)?))
+    }
+
+    /// The address connections are bound to, the unspecified address of the family when only
+    /// the family was forced
+    pub fn bind_address(&self) -> Option<IpAddr> {
+        self.config
+            .local_address
+            .or_else(|| self.config.ip_version.unspecified())
+    }
+
+    /// A client builder set up like the one the sources share, with the request timeout, the
+    /// bound address, the address family and the tls settings, for the other requests a run
+    /// makes like http probes
+    pub fn client_builder(&self) -> Result<reqwest::ClientBuilder> {
+        let connect_timeout = self.config.connect_timeout.unwrap_or(self.config.timeout);
+        self.reqwest_builder(connect_timeout)
+    }
+
+    fn reqwest_client(&self, connect_timeout: u64) -> Result<reqwest::Client> {
+        Ok(self.reqwest_builder(connect_timeout)?.build()?)
+    }
+
+    fn reqwest_builder(&self, connect_timeout: u64) -> Result<reqwest::ClientBuilder> {
+        let config = &self.config;
+        let mut builder = reqwest::ClientBuilder::new()
+            .timeout(Duration::from_secs(config.timeout))
+            .connect_timeout(Duration::from_secs(connect_timeout))
+            .pool_idle_timeout(Duration::from_secs(
+                config.pool_idle_timeout.unwrap_or(config.timeout),
+            ))
+            .tcp_keepalive(config.tcp_keepalive.map(Duration::from_secs))
+            .local_address(self.bind_address());
+        if let Some(max) = config.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        if config.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        match config.tls_backend {
+            #[cfg(feature = "native-tls")]
+            Some(TlsBackend::Native) => builder = builder.use_native_tls(),
+            #[cfg(feature = "rustls")]
+            Some(TlsBackend::Rustls) => builder = builder.use_rustls_tls(),
+            _ => {}
+        }
+        for path in config.root_certificates.iter() {
+            for cert in http::read_certificates(path)? {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        Ok(builder)
+    }
+
+    /// Creates the source with a client tagged with its name, the client uses Arc internally
+    fn create_source(client: &Client, source: Source) -> Arc<dyn DataSource> {
+        let client = client.for_source(source);
+        match source {
+            Source::AlienVault => Arc::new(AlienVault::new(client)),
+            Source::AnubisDB => Arc::new(AnubisDB::new(client)),
+            Source::BinaryEdge => Arc::new(BinaryEdge::new(client)),
+            Source::C99 => Arc::new(C99::new(client)),
+            Source::CertSpotter => Arc::new(CertSpotter::new(client)),
+            Source::Chaos => Arc::new(Chaos::new(client)),
+            Source::Crtsh => Arc::new(Crtsh::new(client)),
+            Source::Facebook => Arc::new(Facebook::new(client)),
+            Source::HackerTarget => Arc::new(HackerTarget::new(client)),
+            Source::Intelx => Arc::new(Intelx::new(client)),
+            Source::PassiveTotal => Arc::new(PassiveTotal::new(client)),
+            Source::SecurityTrails => Arc::new(SecurityTrails::new(client)),
+            Source::SonarSearch => Arc::new(SonarSearch::new(client)),
+            Source::Spyse => Arc::new(Spyse::new(client)),
+            Source::Sublister => Arc::new(Sublister::new(client)),
+            Source::ThreatCrowd => Arc::new(ThreatCrowd::new(client)),
+            Source::ThreatMiner => Arc::new(ThreatMiner::new(client)),
+            Source::UrlScan => Arc::new(UrlScan::new(client)),
+            Source::VirusTotal => Arc::new(VirusTotal::new(client)),
+            Source::Wayback => Arc::new(Wayback::new(client)),
+        }
+    }
+
+    /// Queries the sources in `group` one after another, alternates are only used when the
+    /// source before them fails, has no key, or returns nothing. Results are attributed to the
+    /// first source of the group. Sources which aren't enabled are left out of the chain.
+    pub fn fallback(mut self, group: &[Source]) -> Self {
+        self.fallbacks.push(group.to_vec());
+        self
+    }
+
+    /// Only queries each host for the sources it's paired with, e.g. to retry just the pairs
+    /// which failed in an earlier run. The sources are enabled if they weren't, hosts without
+    /// a pair are queried for every source.
+    pub fn only_pairs<I: IntoIterator<Item = (String, Source)>>(mut self, pairs: I) -> Self {
+        for (host, source) in pairs {
+            self.sources.insert(source);
+            self.pairs.entry(host).or_default().insert(source);
+        }
         self
     }
 
+    /// Replaces the enabled sources with `sources`, e.g. for roots which ask for their own.
+    /// Excluded sources stay excluded, and sources `all_sources` left out for missing keys
+    /// are no longer reported as skipped.
+    pub fn only_sources(mut self, sources: &[Source]) -> Self {
+        self.sources = sources
+            .iter()
+            .filter(|s| !self.excluded.contains(s))
+            .copied()
+            .collect();
+        self.all_requested = false;
+        self.missing_keys.clear();
+        self
+    }
+
+    /// Answers every source's requests from `fixtures` rather than the network. Checks which
+    /// would need the network, like `preflight`, pass without making a connection.
+    pub fn fixtures(mut self, fixtures: Fixtures) -> Self {
+        self.client = self.client.with_fixtures(fixtures);
+        self
+    }
+
+    /// Limits what sources which scrape websites may do. Sources the policy rules out are
+    /// never queried, however they were enabled, and are reported by `skipped`.
+    pub fn scrape_policy(mut self, policy: ScrapePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Spaces out the requests of each source which scrapes a website to about one a second,
+    /// with random gaps, so the sites don't see bursts of automated traffic. Use a scrape
+    /// policy with `api_only` set to not query them at all.
+    pub fn polite(self) -> Self {
+        self.client
+            .quotas()
+            .pace(&policy::scrapers(), POLITE_INTERVAL);
+        self
+    }
+
+    /// Enables the built in fallback groups, passive DNS and certificate transparency
+    pub fn fallbacks(self) -> Self {
+        fallback::GROUPS
+            .iter()
+            .fold(self, |runner, group| runner.fallback(group))
+    }
+
+    /// Checks every enabled source's endpoint can be reached, removing those which can't and
+    /// returning why. Uses the request timeout for each check, and connects from the address
+    /// the run's connections are bound to.
+    pub async fn preflight(&mut self) -> Vec<(Source, SubError)> {
+        if self.client.is_offline() {
+            return Vec::new();
+        }
+        let sources: Vec<Source> = self.permitted().collect();
+        let timeout = Duration::from_secs(self.config.timeout);
+        let unreachable = preflight::check(&sources, timeout, self.bind_address()).await;
+        for (source, e) in unreachable.iter() {
+            warn!("excluding {}: {}", source, e);
+            self.sources.remove(source);
+            self.unreachable.push(*source);
+        }
+
+        unreachable
+    }
+
+    /// Fetches the robots.txt of every enabled source which scrapes a website, removing those
+    /// it disallows and returning why. Does nothing unless the scrape policy asks for it.
+    pub async fn check_robots(&mut self) -> Result<Vec<(Source, String)>> {
+        if !self.policy.robots {
+            return Ok(Vec::new());
+        }
+
+        let client = self.http_client()?;
+        let scrapers: Vec<Source> = self
+            .permitted()
+            .filter(|s| policy::is_scraper(*s))
+            .collect();
+        let mut disallowed = Vec::new();
+        for source in scrapers {
+            if let Some(reason) = policy::check_robots(&client, source).await {
+                warn!("excluding {}: {}", source, reason);
+                self.sources.remove(&source);
+                self.robots.push(source);
+                disallowed.push((source, reason));
+            }
+        }
+
+        disallowed.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(disallowed)
+    }
+
+    /// The enabled sources the scrape policy allows
+    fn permitted(&self) -> impl Iterator<Item = Source> + '_ {
+        self.sources
+            .iter()
+            .copied()
+            .filter(move |s| self.policy.permits(*s))
+    }
+
+    /// How many connections the run may hold open at once, one per enabled source for each of
+    /// the hosts fetched concurrently
+    pub fn open_files(&self) -> u64 {
+        (self.config.concurrency * self.permitted().count()) as u64
+    }
+
+    /// Lowers the concurrency until `open_files` is within `max`, though never below one host
+    /// at a time. A concurrency which already fits is kept.
+    pub fn limit_open_files(mut self, max: u64) -> Self {
+        let sources = self.permitted().count().max(1) as u64;
+        let fits = (max / sources).max(1) as usize;
+        if fits < self.config.concurrency {
+            debug!(
+                "lowering concurrency from {} to {}",
+                self.config.concurrency, fits
+            );
+            self.config.concurrency = fits;
+        }
+        self
+    }
+
+    /// The sources `all_sources` left out because their api keys aren't set, in the order
+    /// they were checked
+    pub fn missing_keys(&self) -> &[Source] {
+        &self.missing_keys
+    }
+
+    /// The sources which won't be queried and why, ordered by source. Sources which use up
+    /// their budget during the run are listed by `Quotas::exhausted`.
+    pub fn skipped(&self) -> Vec<Skipped> {
+        let skip = |reason| {
+            move |source: &Source| Skipped {
+                source: *source,
+                reason,
+            }
+        };
+        let mut skipped: Vec<Skipped> = self
+            .missing_keys
+            .iter()
+            .filter(|s| !self.excluded.contains(s))
+            .map(skip(SkipReason::MissingKeys))
+            .chain(self.excluded.iter().map(skip(SkipReason::Excluded)))
+            .chain(self.unreachable.iter().map(skip(SkipReason::Unreachable)))
+            .chain(
+                self.sources
+                    .iter()
+                    .filter(|s| !self.policy.permits(**s))
+                    .map(skip(SkipReason::Policy)),
+            )
+            .chain(self.robots.iter().map(skip(SkipReason::Robots)))
+            .collect();
+        skipped.sort_by_key(|s| s.source);
+        skipped
+    }
+
+    /// The enabled sources `since` and `until` don't apply to as they can't filter by date,
+    /// ordered by source. Empty when no date range was set.
+    pub fn undated(&self) -> Vec<Source> {
+        if self.config.dates.is_empty() {
+            return Vec::new();
+        }
+
+        let mut undated: Vec<Source> = self
+            .permitted()
+            .filter(|s| !DateRange::supports(*s))
+            .collect();
+        undated.sort();
+        undated
+    }
+
+    /// Every host the enabled sources would connect to, sorted and deduplicated
+    pub fn endpoints(&self) -> Vec<String> {
+        let mut hosts: Vec<String> = self
+            .permitted()
+            .filter_map(|s| match s.endpoint() {
+                Some(host) => Some(host.to_string()),
+                // intelx instances are configured per user
+                None if s == Source::Intelx => self.client.keys().get("INTELX_URL"),
+                None => None,
+            })
+            .collect();
+        hosts.sort();
+        hosts.dedup();
+        hosts
+    }
+
+    /// Returns the names of the sources which will be queried, in the form `exclude` accepts
+    pub fn source_names(&self) -> Vec<String> {
+        self.permitted().map(|s| format!("{:?}", s)).collect()
+    }
+
     /// Fetches data from the sources concurrently
     pub async fn run(self, hosts: HashSet<String>) -> Result<impl Stream<Item = Vec<String>>> {
-        let (tx, rx) = mpsc::channel::<Vec<String>>(CHAN_SIZE);
-        let sources = Arc::new(self.sources);
+        let events = self.events(hosts).await?;
+        Ok(events.filter_map(|e| async move {
+            match e {
+                Event::Results { results, .. } => Some(results),
+                _ => None,
+            }
+        }))
+    }
+
+    /// Fetches data from the sources and writes every unique result the post processor keeps,
+    /// by default those which belong to one of the hosts, to the registered sinks, returning
+    /// a summary of the run.
+    pub async fn collect(mut self, hosts: HashSet<String>) -> Result<Summary> {
+        let start = Instant::now();
+        self.check_robots().await?;
+        let mut sinks = std::mem::take(&mut self.sinks);
+        let enrichment = std::mem::take(&mut self.enrichment);
+        let heuristics = Heuristics::default();
+        let cleaner = self.cleaner.take().unwrap_or_else(|| {
+            let mut cleaner = PostProcessor::default();
+            cleaner.any_root(hosts.clone());
+            cleaner
+        });
+
+        let mut summary = Summary {
+            roots: hosts.len(),
+            skipped: self.skipped(),
+            ..Summary::default()
+        };
+        let quotas = self.quotas();
+        let records = self.records();
+        let mut seen = HashSet::new();
+        let mut events = self.events(hosts).await?;
+        while let Some(event) = events.next().await {
+            let (host, results) = match event {
+                Event::Results { host, results, .. } => (host, results),
+                Event::Failed { .. } => {
+                    summary.failures += 1;
+                    continue;
+                }
+                Event::Finished { .. } => continue,
+            };
+
+            let mut batch = Vec::new();
+            for r in results.iter().clean_for(&cleaner, &host) {
+                if !seen.insert(r.clone()) {
+                    continue;
+                }
+
+                let mut subdomain = Subdomain::new(r);
+                subdomain.interesting = heuristics
+                    .matches(&subdomain.name)
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect();
+                subdomain.root = Some(host.to_string());
+                subdomain.records = records.get(&subdomain.name);
+                batch.push(subdomain);
+            }
+
+            for subdomain in enrichment.run(batch).await {
+                sinks.write(&subdomain).await?;
+                summary.results += 1;
+            }
+        }
+
+        summary.elapsed = start.elapsed();
+        summary
+            .skipped
+            .extend(quotas.exhausted().into_iter().map(|source| Skipped {
+                source,
+                reason: SkipReason::BudgetExhausted,
+            }));
+        sinks.finalize(&summary).await?;
+
+        Ok(summary)
+    }
+
+    /// Fetches data from the sources concurrently, returning every result along with the
+    /// host and source it came from, any errors sources returned, and `Event::Finished` for
+    /// each host once all of its sources are done. Hosts are polled in turn, so a host still
+    /// streaming a huge response doesn't hold up the events of the others.
+    pub async fn events(self, hosts: HashSet<String>) -> Result<impl Stream<Item = Event>> {
+        let (mut roots_tx, roots_rx) = mpsc::channel::<BoxStream<'static, Event>>(CHAN_SIZE);
+        let mut hosts: Vec<String> = hosts.into_iter().collect();
+        if self.config.seed.is_some() {
+            hosts.sort();
+        }
+
+        let sources = Arc::new(self.data_sources()?);
         let max_concurrent = self.config.concurrency;
+        let slots = self.config.slots.clone();
+        let sample = self.config.sample;
+        let pairs = self.pairs;
 
-        let tx2 = tx.clone();
         tokio::spawn(async move {
             let mut futures = FuturesUnordered::new();
             for host in hosts.into_iter() {
@@ -235,13 +894,33 @@ impl Runner {
                     futures.next().await;
                 }
 
-                for source in sources.values() {
+                // every host gets a channel of its own, which ends once its last source is done
+                let (tx, rx) = mpsc::channel::<Event>(CHAN_SIZE);
+                let finished = Event::Finished {
+                    host: Arc::clone(&host),
+                };
+                let events = rx.chain(stream::once(future::ready(finished))).boxed();
+                if roots_tx.send(events).await.is_err() {
+                    debug!("event receiver dropped, stopping the run");
+                    break;
+                }
+
+                for (name, source) in sources.iter() {
+                    if pairs
+                        .get(host.as_str())
+                        .map_or(false, |p| !p.contains(name))
+                    {
+                        continue;
+                    }
                     let source = Arc::clone(source);
                     let host = Arc::clone(&host);
-                    let tx = tx2.clone();
-                    futures.push(tokio::spawn(async move { source.run(host, tx).await }));
+                    let tx = tx.clone();
+                    let slot = slot(&slots).await;
+                    let run = run_source(*name, source, host, tx, sample).map(|()| drop(slot));
+                    futures.push(tokio::spawn(run));
                 }
             }
+            drop(roots_tx);
 
             // Get the remaining futures
             while let Some(res) = futures.next().await {
@@ -251,8 +930,527 @@ impl Runner {
             }
         });
 
-        // explicitly drop the remaning sender
-        drop(tx);
+        Ok(Roots {
+            started: Some(roots_rx),
+            active: SelectAll::new(),
+        })
+    }
+
+    /// Fetches data one root at a time, yielding the unique results for each root once all
+    /// of its sources have finished, with at most the concurrency limit of sources running at
+    /// once. Each chunk carries the `RunState` to resume from after it has been handled, so
+    /// embedders can save it and carry on after a restart with everything already yielded
+    /// skipped.
+    pub async fn run_chunked(self, mut state: RunState) -> Result<impl Stream<Item = Chunk>> {
+        let (mut tx, rx) = mpsc::channel::<Chunk>(1);
+        let sources = Arc::new(self.data_sources()?);
+        let max_concurrent = self.config.concurrency;
+        let slots = self.config.slots.clone();
+        let sample = self.config.sample;
+        let pairs = self.pairs;
+
+        tokio::spawn(async move {
+            while let Some(root) = state.remaining.first().cloned() {
+                let host = Arc::new(root.clone());
+                let (events_tx, mut events) = mpsc::channel::<Event>(CHAN_SIZE);
+                let only = pairs.get(&root).cloned();
+                let sources = Arc::clone(&sources);
+                let slots = slots.clone();
+                // started apart from reading the events, so waiting for a free slot doesn't
+                // stop the running sources from sending
+                tokio::spawn(async move {
+                    let mut futures = FuturesUnordered::new();
+                    for (name, source) in sources.iter() {
+                        if only.as_ref().map_or(false, |p| !p.contains(name)) {
+                            continue;
+                        }
+                        if futures.len() >= max_concurrent {
+                            futures.next().await;
+                        }
+                        let source = Arc::clone(source);
+                        let host = Arc::clone(&host);
+                        let tx = events_tx.clone();
+                        let slot = slot(&slots).await;
+                        let run = run_source(*name, source, host, tx, sample).map(|()| drop(slot));
+                        futures.push(tokio::spawn(run));
+                    }
+                    drop(events_tx);
+                    while futures.next().await.is_some() {}
+                });
+
+                let mut cleaner = PostProcessor::default();
+                cleaner.any_root(std::iter::once(root.clone()));
+                let mut results = HashSet::new();
+                let mut failed = Vec::new();
+                while let Some(event) = events.recv().await {
+                    match event {
+                        Event::Results { results: r, .. } => {
+                            results.extend(r.iter().clean(&cleaner))
+                        }
+                        Event::Failed { source, .. } => failed.push(source),
+                        Event::Finished { .. } => {}
+                    }
+                }
+
+                state.advance();
+                let mut results: Vec<String> = results.into_iter().collect();
+                results.sort();
+                let chunk = Chunk {
+                    root,
+                    results,
+                    failed,
+                    state: state.clone(),
+                };
+                if tx.send(chunk).await.is_err() {
+                    debug!("chunk receiver dropped, stopping the run");
+                    break;
+                }
+            }
+        });
+
         Ok(rx)
     }
+
+    /// Creates every enabled source, with fallback groups wrapped up as a single source named
+    /// after their preferred member
+    fn data_sources(&self) -> Result<Vec<(Source, Arc<dyn DataSource>)>> {
+        let client = self.http_client()?;
+        // extra params from the config take precedence over the date range
+        for source in self.permitted() {
+            let params = self.config.dates.params(source);
+            if !params.is_empty() {
+                client.query_params().add_missing(source, params);
+            }
+        }
+
+        // sources with their own connect timeout need a client, and connection pool, of their own
+        let mut sources: HashMap<Source, Arc<dyn DataSource>> = HashMap::new();
+        for source in self.permitted() {
+            let client = match self.config.source_connect_timeouts.get(&source) {
+                Some(timeout) => client.with_inner(self.reqwest_client(*timeout)?),
+                None => client.clone(),
+            };
+            sources.insert(source, Self::create_source(&client, source));
+        }
+        for group in self.fallbacks.iter() {
+            let chain: Vec<(Source, Arc<dyn DataSource>)> = group
+                .iter()
+                .filter_map(|s| sources.remove(s).map(|source| (*s, source)))
+                .collect();
+            if let Some((primary, _)) = chain.first() {
+                info!("querying {:?} as a fallback chain", group);
+                sources.insert(*primary, Arc::new(Fallback::new(chain)));
+            }
+        }
+
+        let mut sources: Vec<(Source, Arc<dyn DataSource>)> = sources.into_iter().collect();
+        if self.config.seed.is_some() {
+            sources.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+
+        Ok(sources)
+    }
+}
+
+/// The events of every host started so far, merged. Hosts are polled in turn rather than
+/// sharing a channel, so results wait behind their own host's backlog and not everyone's.
+struct Roots {
+    // hosts whose sources have been started, closed once the last host has been
+    started: Option<mpsc::Receiver<BoxStream<'static, Event>>>,
+    active: SelectAll<BoxStream<'static, Event>>,
+}
+
+impl Stream for Roots {
+    type Item = Event;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Event>> {
+        let this = self.get_mut();
+        while let Some(started) = this.started.as_mut() {
+            match started.poll_recv(cx) {
+                Poll::Ready(Some(events)) => this.active.push(events),
+                Poll::Ready(None) => this.started = None,
+                Poll::Pending => break,
+            }
+        }
+
+        match this.active.poll_next_unpin(cx) {
+            // every started host is done but more are still to come
+            Poll::Ready(None) if this.started.is_some() => Poll::Pending,
+            poll => poll,
+        }
+    }
+}
+
+/// The message a panic was raised with, if it had one
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".into())
+}
+
+/// Runs a single source for a host, tagging everything it sends with where it came from.
+/// When sampling, the source is dropped as soon as it has sent `sample` results.
+/// Waits for a slot from the budget shared with other runners, if there is one
+async fn slot(slots: &Option<Arc<Semaphore>>) -> Option<OwnedSemaphorePermit> {
+    match slots {
+        Some(slots) => Some(Arc::clone(slots).acquire_owned().await),
+        None => None,
+    }
+}
+
+async fn run_source(
+    name: Source,
+    source: Arc<dyn DataSource>,
+    host: Arc<String>,
+    mut tx: mpsc::Sender<Event>,
+    sample: Option<usize>,
+) {
+    let res = {
+        let (source_tx, mut source_rx) = mpsc::channel::<Vec<String>>(1);
+        let forward = async {
+            let mut sent = 0;
+            while let Some(mut results) = source_rx.recv().await {
+                if let Some(n) = sample {
+                    results.truncate(n - sent);
+                }
+                sent += results.len();
+
+                let event = Event::Results {
+                    host: Arc::clone(&host),
+                    source: name,
+                    results,
+                };
+                let _ = tx.send(event).await;
+                if sample.map_or(false, |n| sent >= n) {
+                    return true;
+                }
+            }
+
+            false
+        };
+
+        // a panic is reported as this source failing rather than taking the run down with it
+        let run = AssertUnwindSafe(source.run(Arc::clone(&host), source_tx))
+            .catch_unwind()
+            .map(|res| {
+                res.unwrap_or_else(|panic| {
+                    Err(SubError::SourcePanicked(
+                        name.to_string(),
+                        panic_message(&*panic),
+                    ))
+                })
+            });
+        futures::pin_mut!(run, forward);
+        match future::select(run, forward).await {
+            Either::Left((res, forward)) => {
+                forward.await;
+                res
+            }
+            Either::Right((true, _)) => {
+                debug!("sampled {} for {}, stopping it", name, &host);
+                Ok(())
+            }
+            Either::Right((false, run)) => run.await,
+        }
+    };
+
+    if let Err(error) = res {
+        match &error {
+            SubError::SchemaChanged { .. } => warn!("{}", &error),
+            _ => debug!("{} failed for {}: {}", name, &host, &error),
+        }
+
+        let _ = tx
+            .send(Event::Failed {
+                host,
+                source: name,
+                error,
+            })
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Keys;
+    use matches::matches;
+
+    #[test]
+    fn build_rejects_zero_concurrency() {
+        let res = Runner::default().free_sources().concurrency(0).build();
+        assert!(matches!(
+            res.err().unwrap(),
+            SubError::Build(BuildError::ZeroConcurrency)
+        ));
+    }
+
+    #[test]
+    fn build_rejects_unknown_sources() {
+        let res = Runner::default()
+            .free_sources()
+            .exclude(&["Wayback", "Waybak"])
+            .build();
+        match res.err().unwrap() {
+            SubError::Build(BuildError::UnknownSources(v)) => assert_eq!(v, vec!["Waybak"]),
+            e => panic!("unexpected error {}", e),
+        }
+    }
+
+    #[test]
+    fn build_rejects_inverted_date_range() {
+        let res = Runner::default()
+            .free_sources()
+            .since("2021-01-01".parse().unwrap())
+            .until("2020-01-01".parse().unwrap())
+            .build();
+        assert!(matches!(
+            res.err().unwrap(),
+            SubError::Build(BuildError::InvertedDateRange(_, _))
+        ));
+    }
+
+    #[test]
+    fn all_sources_skips_those_without_keys() {
+        let runner = Runner::default().all_sources();
+        for source in Source::ALL.iter() {
+            let skipped = runner.missing_keys().contains(source);
+            assert_eq!(skipped, !Keys::default().has(*source));
+            assert_ne!(skipped, runner.sources.contains(source));
+        }
+    }
+
+    #[test]
+    fn limits_open_files() {
+        let runner = Runner::default()
+            .only_sources(&[Source::Crtsh, Source::Wayback])
+            .concurrency(200);
+        assert_eq!(runner.open_files(), 400);
+
+        let runner = runner.limit_open_files(101);
+        assert_eq!(runner.open_files(), 100);
+        assert_eq!(runner.limit_open_files(1).config.concurrency, 1);
+    }
+
+    #[test]
+    fn pairs_enable_their_sources() {
+        let runner = Runner::default().free_sources().only_pairs(vec![
+            ("hackerone.com".to_string(), Source::Chaos),
+            ("hackerone.com".to_string(), Source::Crtsh),
+        ]);
+        assert!(runner.sources.contains(&Source::Chaos));
+        assert_eq!(runner.pairs["hackerone.com"].len(), 2);
+    }
+
+    #[test]
+    fn only_sources_replaces_enabled_sources() {
+        let runner = Runner::default()
+            .all_sources()
+            .exclude(&["wayback"])
+            .only_sources(&[Source::Crtsh, Source::Wayback]);
+        assert_eq!(runner.sources.len(), 1);
+        assert!(runner.sources.contains(&Source::Crtsh));
+        assert!(runner.missing_keys().is_empty());
+    }
+
+    #[test]
+    fn scrape_policy_skips_scrapers() {
+        let runner = Runner::default()
+            .free_sources()
+            .scrape_policy(ScrapePolicy {
+                api_only: true,
+                ..ScrapePolicy::default()
+            });
+        let names = runner.source_names();
+        assert!(!names.contains(&"Crtsh".to_string()));
+        assert!(names.contains(&"Wayback".to_string()));
+        assert!(runner.skipped().contains(&Skipped {
+            source: Source::VirusTotal,
+            reason: SkipReason::Policy
+        }));
+
+        let runner = Runner::default()
+            .free_sources()
+            .scrape_policy(ScrapePolicy {
+                deny: vec!["crtshh".into()],
+                ..ScrapePolicy::default()
+            });
+        assert!(runner.build().is_err());
+    }
+
+    #[test]
+    fn reports_skipped_sources() {
+        let runner = Runner::default()
+            .free_sources()
+            .exclude(&["wayback", "crtsh"]);
+        let skipped: Vec<Source> = runner.skipped().iter().map(|s| s.source).collect();
+        assert_eq!(skipped, vec![Source::Crtsh, Source::Wayback]);
+        assert!(runner
+            .skipped()
+            .iter()
+            .all(|s| s.reason == SkipReason::Excluded));
+    }
+
+    #[test]
+    fn applies_default_source_policy() {
+        let runner = Runner::default()
+            .free_sources()
+            .exclude(&["wayback"])
+            .enabled_by_default(Source::Crtsh, false)
+            .enabled_by_default(Source::Wayback, true)
+            .enabled_by_default(Source::HackerTarget, true);
+        assert!(!runner.sources.contains(&Source::Crtsh));
+        assert!(!runner.sources.contains(&Source::Wayback));
+        assert!(runner.sources.contains(&Source::HackerTarget));
+
+        // a paid source is only enabled by default when its keys are set
+        assert!(!Runner::default()
+            .free_sources()
+            .sources
+            .contains(&Source::Chaos));
+        let runner = Runner::default()
+            .free_sources()
+            .enabled_by_default(Source::Chaos, true);
+        assert_eq!(
+            runner.sources.contains(&Source::Chaos),
+            Keys::default().has(Source::Chaos)
+        );
+
+        // asking for every source wins over the policy
+        let runner = Runner::default()
+            .all_sources()
+            .enabled_by_default(Source::Crtsh, false);
+        assert!(runner.sources.contains(&Source::Crtsh));
+    }
+
+    #[test]
+    fn api_keys_take_the_place_of_the_environment() {
+        let runner = Runner::default()
+            .api_keys(vec![("CHAOS_KEY".to_string(), "abc".to_string())])
+            .all_sources();
+        assert!(runner.sources.contains(&Source::Chaos));
+        assert!(!runner.missing_keys().contains(&Source::Chaos));
+    }
+
+    #[test]
+    fn build_checks_the_tls_backend() {
+        for backend in [TlsBackend::Native, TlsBackend::Rustls] {
+            let res = Runner::default()
+                .free_sources()
+                .tls_backend(backend)
+                .build();
+            assert_eq!(res.is_ok(), backend.is_available());
+        }
+    }
+
+    #[test]
+    fn build_rejects_zero_bandwidth() {
+        let res = Runner::default().free_sources().max_bandwidth(0).build();
+        assert!(matches!(
+            res.err().unwrap(),
+            SubError::Build(BuildError::ZeroBandwidth)
+        ));
+    }
+
+    #[test]
+    fn build_rejects_zero_connect_timeout() {
+        let res = Runner::default()
+            .free_sources()
+            .source_connect_timeout(Source::Wayback, 0)
+            .build();
+        assert!(matches!(
+            res.err().unwrap(),
+            SubError::Build(BuildError::ZeroTimeout)
+        ));
+    }
+
+    #[test]
+    fn build_checks_source_timeouts() {
+        let res = Runner::default()
+            .free_sources()
+            .source_timeout(Source::Wayback, 0)
+            .build();
+        assert!(matches!(
+            res.err().unwrap(),
+            SubError::Build(BuildError::ZeroTimeout)
+        ));
+
+        let res = Runner::default()
+            .free_sources()
+            .source_timeout(Source::Wayback, MAX_TIMEOUT + 1)
+            .build();
+        assert!(matches!(
+            res.err().unwrap(),
+            SubError::Build(BuildError::TimeoutTooLong(_))
+        ));
+    }
+
+    #[test]
+    fn build_accepts_defaults() {
+        assert!(Runner::default().free_sources().build().is_ok());
+    }
+
+    struct Panics;
+
+    #[async_trait::async_trait]
+    impl DataSource for Panics {
+        async fn run(&self, _: Arc<String>, _: mpsc::Sender<Vec<String>>) -> Result<()> {
+            panic!("unexpected response");
+        }
+    }
+
+    #[tokio::test]
+    async fn hosts_finish_independently() {
+        let host = |h: &str| Arc::new(h.to_string());
+        let (mut roots_tx, roots_rx) = mpsc::channel(2);
+        let mut roots = Roots {
+            started: Some(roots_rx),
+            active: SelectAll::new(),
+        };
+
+        // a host with a source which is still sending doesn't hold up one which is done
+        let (mut slow_tx, slow_rx) = mpsc::channel(1);
+        roots_tx.send(slow_rx.boxed()).await.unwrap();
+        let done = Event::Finished {
+            host: host("google.com"),
+        };
+        roots_tx
+            .send(stream::once(future::ready(done)).boxed())
+            .await
+            .unwrap();
+        match roots.next().await {
+            Some(Event::Finished { host }) => assert_eq!(host.as_str(), "google.com"),
+            _ => panic!("expected google.com to finish first"),
+        }
+
+        drop(roots_tx);
+        let done = Event::Finished {
+            host: host("hackerone.com"),
+        };
+        slow_tx.send(done).await.unwrap();
+        drop(slow_tx);
+        assert!(matches!(roots.next().await, Some(Event::Finished { .. })));
+        assert!(roots.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn reports_panicking_sources() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let host = Arc::new("hackerone.com".to_string());
+        run_source(Source::Wayback, Arc::new(Panics), host, tx, None).await;
+
+        match rx.recv().await {
+            Some(Event::Failed {
+                source,
+                error: SubError::SourcePanicked(_, msg),
+                ..
+            }) => {
+                assert_eq!(source, Source::Wayback);
+                assert_eq!(msg, "unexpected response");
+            }
+            _ => panic!("expected the panic to be reported as a failure"),
+        }
+    }
 }
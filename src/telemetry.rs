@@ -0,0 +1,104 @@
+use crate::error::SubError;
+use crate::sources::Source;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+/// An anonymous report of the sources which sent responses that couldn't be parsed, so broken
+/// providers can be prioritised. Only source names, how often they failed and the version are
+/// included, never hosts, keys or response bodies.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Report {
+    pub version: &'static str,
+    pub schema_changed: BTreeMap<Source, usize>,
+}
+
+impl Report {
+    /// Counts the schema changes among the errors sources returned
+    pub fn new<'a, I>(failures: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a Source, &'a SubError)>,
+    {
+        let mut schema_changed = BTreeMap::new();
+        for (source, error) in failures {
+            if let SubError::SchemaChanged { .. } = error {
+                *schema_changed.entry(*source).or_insert(0) += 1;
+            }
+        }
+
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            schema_changed,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.schema_changed.is_empty()
+    }
+}
+
+/// Where reports are sent, nothing is sent unless one of these is chosen
+pub enum Telemetry {
+    /// Posts the report as json to the maintainers' endpoint
+    Endpoint(String),
+    /// Appends the report as a json line to a local file, nothing leaves the machine
+    File(PathBuf),
+}
+
+impl Telemetry {
+    /// Sends a report, posting it with `client`. Failures are only logged so they never
+    /// affect the run
+    pub async fn send(&self, report: &Report, client: &reqwest::Client) {
+        match self {
+            Telemetry::Endpoint(url) => match client.post(url).json(report).send().await {
+                Ok(resp) if resp.status().is_success() => info!("sent telemetry to {}", url),
+                Ok(resp) => warn!("got status: {} sending telemetry", resp.status().as_str()),
+                Err(e) => warn!("couldn't send telemetry got {}", e),
+            },
+            Telemetry::File(path) => {
+                let res = serde_json::to_string(report)
+                    .map_err(|e| e.to_string())
+                    .and_then(|line| {
+                        let mut file = OpenOptions::new()
+                            .create(true)
+                            .append(true)
+                            .open(path)
+                            .map_err(|e| e.to_string())?;
+                        writeln!(file, "{}", line).map_err(|e| e.to_string())
+                    });
+                if let Err(e) = res {
+                    warn!("couldn't write telemetry to {:?} got {}", path, e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_counts_schema_changes() {
+        let changed = SubError::SchemaChanged {
+            source: "Crtsh".into(),
+            error: "missing field".into(),
+            sample: "secret.hackerone.com".into(),
+        };
+        let failures = vec![
+            (Source::Crtsh, changed),
+            (Source::Wayback, SubError::EmptyResults),
+        ];
+
+        let report = Report::new(failures.iter().map(|(s, e)| (s, e)));
+        assert_eq!(report.schema_changed.len(), 1);
+        assert_eq!(report.schema_changed[&Source::Crtsh], 1);
+
+        // hosts and samples never make it into the report
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(!json.contains("hackerone"));
+    }
+}
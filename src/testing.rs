@@ -0,0 +1,9 @@
+use std::env;
+use std::path::PathBuf;
+use std::process;
+
+/// A path in the temp dir named after `name` and the test process, so tests writing files
+/// don't trip over those of another run. `name` keeps its extension at the end.
+pub fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("sub-{}-{}", process::id(), name))
+}
@@ -0,0 +1,203 @@
+use crate::error::{Result, SubError};
+use serde::{de, Deserialize, Deserializer};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+pub const KIB: u64 = 1024;
+pub const MIB: u64 = 1024 * KIB;
+
+const TIME_UNITS: &[(&str, u64)] = &[("s", 1), ("m", 60), ("h", 60 * 60), ("d", 24 * 60 * 60)];
+const SIZE_UNITS: &[(&str, u64)] = &[
+    ("b", 1),
+    ("k", KIB),
+    ("kib", KIB),
+    ("kb", 1000),
+    ("m", MIB),
+    ("mib", MIB),
+    ("mb", 1000 * 1000),
+    ("g", 1024 * MIB),
+    ("gib", 1024 * MIB),
+    ("gb", 1000 * 1000 * 1000),
+    ("t", 1024 * 1024 * MIB),
+    ("tib", 1024 * 1024 * MIB),
+    ("tb", 1000 * 1000 * 1000 * 1000),
+];
+
+/// A length of time like `30s`, `10m`, `6h` or `1h30m`, in whole seconds. Units are `s`, `m`,
+/// `h` and `d`, a bare number is seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimeSpan(u64);
+
+impl TimeSpan {
+    pub fn from_secs(secs: u64) -> Self {
+        Self(secs)
+    }
+
+    pub fn as_secs(&self) -> u64 {
+        self.0
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.0)
+    }
+}
+
+impl FromStr for TimeSpan {
+    type Err = SubError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || SubError::ConfigError(format!("{} isn't a duration like 30s or 2h", s));
+        let secs = parse(s, TIME_UNITS, 1).ok_or_else(invalid)?;
+        Ok(Self(secs))
+    }
+}
+
+impl fmt::Display for TimeSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}s", self.0)
+    }
+}
+
+/// An amount of data like `512MiB` or `2GiB`. `KiB`, `MiB`, `GiB` and `TiB` or just `K`, `M`,
+/// `G` and `T` are powers of 1024, `KB`, `MB`, `GB` and `TB` powers of 1000, and units ignore
+/// case. A bare number is in bytes unless read with `parse_in`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ByteSize(u64);
+
+impl ByteSize {
+    pub fn from_bytes(bytes: u64) -> Self {
+        Self(bytes)
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.0
+    }
+
+    /// Parses `s` with bare numbers counted in `unit` bytes, for settings which took a number
+    /// of MiB before units were accepted
+    pub fn parse_in(s: &str, unit: u64) -> Result<Self> {
+        let invalid = || SubError::ConfigError(format!("{} isn't a size like 512MiB", s));
+        let bytes = parse(s, SIZE_UNITS, unit).ok_or_else(invalid)?;
+        Ok(Self(bytes))
+    }
+}
+
+impl FromStr for ByteSize {
+    type Err = SubError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse_in(s, 1)
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}B", self.0)
+    }
+}
+
+/// Adds up the numbers in `s` each multiplied by the unit after it, e.g. `1h30m`. Only a
+/// single number may go without a unit, counted in `bare`.
+fn parse(s: &str, units: &[(&str, u64)], bare: u64) -> Option<u64> {
+    let s = s.trim().to_ascii_lowercase();
+    if s.is_empty() {
+        return None;
+    }
+
+    let mut total: f64 = 0.0;
+    let mut rest = s.as_str();
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let (number, after) = rest.split_at(split);
+        let number: f64 = number.parse().ok()?;
+
+        let split = after
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(after.len());
+        let (unit, after) = after.split_at(split);
+        let unit = match unit.trim() {
+            "" if after.is_empty() && rest.len() == s.len() => bare,
+            unit => units.iter().find(|(name, _)| *name == unit)?.1,
+        };
+
+        total += number * unit as f64;
+        rest = after;
+    }
+
+    if !total.is_finite() || total > u64::MAX as f64 {
+        return None;
+    }
+    Some(total.round() as u64)
+}
+
+/// A setting given as a bare number or a string with units
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Value {
+    Number(u64),
+    Text(String),
+}
+
+/// Deserializes a number of seconds, or a duration like `"2m"`
+pub(crate) fn seconds<'de, D>(d: D) -> std::result::Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Value::deserialize(d)? {
+        Value::Number(secs) => Ok(Some(secs)),
+        Value::Text(s) => s
+            .parse::<TimeSpan>()
+            .map(|t| Some(t.as_secs()))
+            .map_err(de::Error::custom),
+    }
+}
+
+/// Deserializes a number of bytes, or a size like `"100MiB"`
+pub(crate) fn bytes<'de, D>(d: D) -> std::result::Result<Option<usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Value::deserialize(d)? {
+        Value::Number(bytes) => Ok(Some(bytes as usize)),
+        Value::Text(s) => s
+            .parse::<ByteSize>()
+            .map(|b| Some(b.bytes() as usize))
+            .map_err(de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        let secs = |s: &str| s.parse::<TimeSpan>().map(|t| t.as_secs()).ok();
+        assert_eq!(secs("30"), Some(30));
+        assert_eq!(secs("30s"), Some(30));
+        assert_eq!(secs("10m"), Some(600));
+        assert_eq!(secs("6h"), Some(21600));
+        assert_eq!(secs("1h30m"), Some(5400));
+        assert_eq!(secs("1.5h"), Some(5400));
+        assert_eq!(secs("1D"), Some(86400));
+        assert_eq!(secs(""), None);
+        assert_eq!(secs("10x"), None);
+        assert_eq!(secs("1h30"), None);
+    }
+
+    #[test]
+    fn parses_sizes() {
+        let bytes = |s: &str| s.parse::<ByteSize>().map(|b| b.bytes()).ok();
+        assert_eq!(bytes("1024"), Some(1024));
+        assert_eq!(bytes("512MiB"), Some(512 * MIB));
+        assert_eq!(bytes("2GiB"), Some(2048 * MIB));
+        assert_eq!(bytes("2 gb"), Some(2_000_000_000));
+        assert_eq!(bytes("10k"), Some(10 * KIB));
+        assert_eq!(bytes("-1"), None);
+        assert_eq!(ByteSize::parse_in("256", MIB).unwrap().bytes(), 256 * MIB);
+        assert_eq!(ByteSize::parse_in("1KiB", MIB).unwrap().bytes(), KIB);
+    }
+}
@@ -0,0 +1,282 @@
+use crate::error::Result;
+use crate::{CleanExt, Event, PostProcessor, Runner};
+use futures::stream::StreamExt;
+use redis::aio::Connection;
+use redis::AsyncCommands;
+use serde::Serialize;
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+/// How long to wait for a new root before giving up when `exit_when_empty` is set
+const IDLE_TIMEOUT: usize = 5;
+/// Prefix for the keys used to coordinate workers
+const PREFIX: &str = "sub";
+/// Sets a new ttl on the lease in `KEYS[1]` only while it's still held by `ARGV[1]`, so a
+/// worker whose lease expired doesn't extend the one another worker took since
+const EXTEND_LEASE: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("EXPIRE", KEYS[1], ARGV[2])
+end
+return 0
+"#;
+/// Deletes the lease in `KEYS[1]` only while it's still held by `ARGV[1]`
+const RELEASE_LEASE: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
+/// A single result pushed onto the output list
+#[derive(Serialize)]
+struct Message<'a> {
+    root: &'a str,
+    subdomain: &'a str,
+}
+
+/// Pops roots from a Redis list, enumerates them, and pushes the results onto another list.
+///
+/// Many workers can consume the same queue, which makes it easy to scale enumeration across
+/// hosts. Each result is pushed as a json object: `{"root": "...", "subdomain": "..."}`.
+pub struct Worker {
+    client: redis::Client,
+    queue: String,
+    output: String,
+    subs_only: bool,
+    exit_when_empty: bool,
+    coordination: Option<Coordination>,
+}
+
+/// Settings for sharing work between workers enumerating the same roots.
+///
+/// Before querying a source for a root a worker takes a lease on the pair, any other worker
+/// skips pairs it can't lease. Once a source succeeds the lease is kept for `done_ttl` so the
+/// pair isn't queried again in that time, a source which failed has its lease released so
+/// another worker can retry it. Results are deduplicated against a shared set per root.
+pub struct Coordination {
+    id: String,
+    lease_ttl: usize,
+    done_ttl: usize,
+}
+
+impl Default for Coordination {
+    fn default() -> Self {
+        let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "worker".into());
+        Self {
+            id: format!("{}-{}", host, std::process::id()),
+            lease_ttl: 600,
+            done_ttl: 86400,
+        }
+    }
+}
+
+impl Coordination {
+    /// How long in seconds a worker may hold a lease while it's querying a source
+    pub fn lease_ttl(mut self, seconds: usize) -> Self {
+        self.lease_ttl = seconds;
+        self
+    }
+
+    /// How long in seconds finished root and source pairs, and seen results, are remembered
+    pub fn done_ttl(mut self, seconds: usize) -> Self {
+        self.done_ttl = seconds;
+        self
+    }
+}
+
+impl Worker {
+    /// Creates a worker which connects to the Redis server at `url`
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Self {
+            client: redis::Client::open(url)?,
+            queue: "sub:roots".into(),
+            output: "sub:results".into(),
+            subs_only: false,
+            exit_when_empty: false,
+            coordination: None,
+        })
+    }
+
+    /// Sets the list roots are popped from
+    pub fn queue(mut self, key: &str) -> Self {
+        self.queue = key.into();
+        self
+    }
+
+    /// Sets the list results are pushed onto
+    pub fn output(mut self, key: &str) -> Self {
+        self.output = key.into();
+        self
+    }
+
+    /// Filters results to those which have the same subdomain as the root
+    pub fn subs_only(mut self, enabled: bool) -> Self {
+        self.subs_only = enabled;
+        self
+    }
+
+    /// Stops the worker once the queue has been empty for a few seconds, instead of waiting
+    /// for new roots forever.
+    pub fn exit_when_empty(mut self, enabled: bool) -> Self {
+        self.exit_when_empty = enabled;
+        self
+    }
+
+    /// Coordinates with other workers so sources aren't queried redundantly and results are
+    /// deduplicated centrally.
+    pub fn coordinate(mut self, coordination: Coordination) -> Self {
+        self.coordination = Some(coordination);
+        self
+    }
+
+    /// Processes roots until the queue is empty or the connection fails. `runner` is called
+    /// to build a fresh `Runner` for every root.
+    pub async fn run<F: Fn() -> Runner>(self, runner: F) -> Result<()> {
+        let mut con = self.client.get_async_connection().await?;
+        let timeout = if self.exit_when_empty {
+            IDLE_TIMEOUT
+        } else {
+            0
+        };
+
+        info!("waiting for roots on {}", &self.queue);
+        loop {
+            let popped: Option<(String, String)> = con.blpop(&self.queue, timeout).await?;
+            let root = match popped {
+                Some((_, root)) => root,
+                None => break,
+            };
+
+            let count = self.enumerate(&mut con, runner(), root.clone()).await?;
+            info!("pushed {} results for {} to {}", count, &root, &self.output);
+        }
+
+        info!("{} is empty, stopping worker", &self.queue);
+        Ok(())
+    }
+
+    async fn enumerate(
+        &self,
+        con: &mut Connection,
+        mut runner: Runner,
+        root: String,
+    ) -> Result<usize> {
+        // before leasing, so sources robots.txt rules out aren't held from other workers
+        runner.check_robots().await?;
+        let mut leased = Vec::new();
+        if let Some(coordination) = &self.coordination {
+            let mut taken = Vec::new();
+            for source in runner.source_names() {
+                let key = format!("{}:lease:{}:{}", PREFIX, &root, &source);
+                let acquired: Option<String> = redis::cmd("SET")
+                    .arg(&key)
+                    .arg(&coordination.id)
+                    .arg("NX")
+                    .arg("EX")
+                    .arg(coordination.lease_ttl)
+                    .query_async(con)
+                    .await?;
+
+                match acquired {
+                    Some(_) => leased.push((key, source)),
+                    None => taken.push(source),
+                }
+            }
+
+            if leased.is_empty() {
+                info!(
+                    "every source for {} is leased by another worker, skipping",
+                    &root
+                );
+                return Ok(0);
+            }
+
+            let taken: Vec<&str> = taken.iter().map(|s| s.as_str()).collect();
+            runner = runner.exclude(&taken);
+        }
+
+        let mut hosts = HashSet::new();
+        hosts.insert(root.clone());
+
+        let mut cleaner = PostProcessor::default();
+        if self.subs_only {
+            cleaner.any_subdomain(hosts.clone());
+        } else {
+            cleaner.any_root(hosts.clone());
+        }
+
+        let mut results: Vec<String> = Vec::new();
+        let mut unique = HashSet::new();
+        let mut failed = HashSet::new();
+        let mut events = runner.events(hosts).await?;
+        while let Some(event) = events.next().await {
+            match event {
+                Event::Results { results: v, .. } => {
+                    for r in v.iter().clean(&cleaner) {
+                        if unique.insert(r.clone()) {
+                            results.push(r);
+                        }
+                    }
+                }
+                Event::Failed { source, .. } => {
+                    failed.insert(format!("{:?}", source));
+                }
+                Event::Finished { .. } => {}
+            }
+        }
+
+        if let Some(coordination) = &self.coordination {
+            let seen = format!("{}:seen:{}", PREFIX, &root);
+            let mut pipe = redis::pipe();
+            for r in results.iter() {
+                pipe.sadd(&seen, r);
+            }
+            pipe.expire(&seen, coordination.done_ttl).ignore();
+            for (key, source) in leased.iter() {
+                if failed.contains(source) {
+                    pipe.cmd("EVAL")
+                        .arg(RELEASE_LEASE)
+                        .arg(1)
+                        .arg(key)
+                        .arg(&coordination.id)
+                        .ignore();
+                } else {
+                    pipe.cmd("EVAL")
+                        .arg(EXTEND_LEASE)
+                        .arg(1)
+                        .arg(key)
+                        .arg(&coordination.id)
+                        .arg(coordination.done_ttl)
+                        .ignore();
+                }
+            }
+
+            let added: Vec<usize> = pipe.query_async(con).await?;
+            results = results
+                .into_iter()
+                .zip(added)
+                .filter(|(_, added)| *added == 1)
+                .map(|(r, _)| r)
+                .collect();
+        }
+
+        let messages: Vec<String> = results
+            .iter()
+            .filter_map(|subdomain| {
+                serde_json::to_string(&Message {
+                    root: &root,
+                    subdomain,
+                })
+                .ok()
+            })
+            .collect();
+
+        if messages.is_empty() {
+            warn!("no results for {}", &root);
+            return Ok(0);
+        }
+
+        let _: usize = con.rpush(&self.output, &messages).await?;
+        Ok(messages.len())
+    }
+}